@@ -1,5 +1,6 @@
 //! Compression caching to avoid re-compressing unchanged files.
 
+use crate::log::status;
 use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::io::Cursor;
@@ -58,31 +59,31 @@ impl CompressionCache {
 
     /// Compress data, using in-memory dedup and disk cache if available.
     /// Returns a `BlobInfo` with the content hash and compressed size.
-    pub fn compress(&mut self, data: &[u8]) -> BlobInfo {
+    pub fn compress(&mut self, data: &[u8]) -> Result<BlobInfo, String> {
         let hash = blake3::hash(data).to_hex().to_string();
 
         // 1. In-memory dedup hit
         if let Some(existing) = self.blobs.get(&hash) {
             self.dedup_hits += 1;
             self.dedup_saved_bytes += existing.len();
-            return BlobInfo {
+            return Ok(BlobInfo {
                 compressed_len: existing.len(),
                 hash,
-            };
+            });
         }
 
         // 2. Load from disk cache or compress fresh
-        let compressed = self.load_or_compress(data, &hash);
+        let compressed = self.load_or_compress(data, &hash)?;
         let compressed_len = compressed.len();
         self.blobs.insert(hash.clone(), compressed);
-        BlobInfo {
+        Ok(BlobInfo {
             compressed_len,
             hash,
-        }
+        })
     }
 
     /// Try to load compressed data from disk cache, or compress fresh.
-    fn load_or_compress(&mut self, data: &[u8], hash: &str) -> Vec<u8> {
+    fn load_or_compress(&mut self, data: &[u8], hash: &str) -> Result<Vec<u8>, String> {
         if let Some(cache_dir) = &self.cache_dir {
             let cache_filename = format!("{hash}_{}.zst", self.level);
             let cache_path = cache_dir.join(&cache_filename);
@@ -90,11 +91,11 @@ impl CompressionCache {
 
             if let Ok(cached) = fs::read(&cache_path) {
                 self.cache_hits += 1;
-                return cached;
+                return Ok(cached);
             }
 
             self.misses += 1;
-            let compressed = self.compress_raw(data);
+            let compressed = self.compress_raw(data)?;
 
             // Atomic write: write to tmp file then rename
             let tmp_path = cache_dir.join(format!(".tmp_{}", std::process::id()));
@@ -102,7 +103,7 @@ impl CompressionCache {
                 let _ = fs::rename(&tmp_path, &cache_path);
             }
 
-            compressed
+            Ok(compressed)
         } else {
             self.misses += 1;
             self.compress_raw(data)
@@ -155,18 +156,18 @@ impl CompressionCache {
         let total = self.cache_hits + self.misses + self.dedup_hits;
         let unique = self.blobs.len();
         if self.cache_dir.is_some() {
-            eprintln!(
+            status!(
                 "typst-bake: Compression level {}, {total} files, {unique} unique blobs ({} cached, {} compressed)",
                 self.level, self.cache_hits, self.misses
             );
         } else {
-            eprintln!(
+            status!(
                 "typst-bake: Compression level {}, {total} files, {unique} unique blobs (cache disabled)",
                 self.level
             );
         }
         if self.dedup_hits > 0 {
-            eprintln!(
+            status!(
                 "typst-bake: Dedup: removed {} duplicates, saved {}",
                 self.dedup_hits,
                 format_size(self.dedup_saved_bytes)
@@ -184,12 +185,14 @@ impl CompressionCache {
         }
     }
 
-    fn compress_raw(&self, data: &[u8]) -> Vec<u8> {
-        zstd::encode_all(Cursor::new(data), self.level).expect("zstd compression failed")
+    fn compress_raw(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        zstd::encode_all(Cursor::new(data), self.level)
+            .map_err(|e| format!("zstd compression failed: {e}"))
     }
 }
 
-fn format_size(bytes: usize) -> String {
+/// Format bytes into a short human-readable size string.
+pub fn format_size(bytes: usize) -> String {
     if bytes < 1024 {
         format!("{bytes} B")
     } else if bytes < 1024 * 1024 {
@@ -1,8 +1,12 @@
 //! Cargo.toml metadata parsing for typst-bake configuration.
 
+use crate::log::LogLevel;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
 
 /// Read and parse the Cargo.toml in the given manifest directory.
 fn read_manifest(manifest_dir: &Path) -> Result<toml::Table, String> {
@@ -14,6 +18,53 @@ fn read_manifest(manifest_dir: &Path) -> Result<toml::Table, String> {
         .map_err(|e| format!("Failed to parse Cargo.toml: {e}"))
 }
 
+/// The manifest of the crate being compiled.
+struct Manifest {
+    /// `CARGO_MANIFEST_DIR`, which relative paths in the metadata resolve from.
+    dir: PathBuf,
+    table: toml::Table,
+}
+
+/// The crate's Cargo.toml, parsed once per change and shared by every setting.
+///
+/// Cached per manifest directory, since one loaded proc-macro library can expand
+/// `document!` for several crates, and re-read when the file's modification time changes,
+/// since rust-analyzer's proc-macro server keeps running while Cargo.toml is edited.
+fn manifest() -> Result<Arc<Manifest>, String> {
+    type Cache = HashMap<PathBuf, (SystemTime, Arc<Manifest>)>;
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+    let dir =
+        env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR not set".to_owned())?;
+    let dir = PathBuf::from(dir);
+    // Without a modification time there is nothing to validate a cached copy against.
+    let modified = fs::metadata(dir.join("Cargo.toml"))
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .map_err(|_| "Cargo.toml cache lock poisoned".to_owned())?;
+    if let Some((cached_at, manifest)) = cache.get(&dir) {
+        if Some(*cached_at) == modified {
+            return Ok(Arc::clone(manifest));
+        }
+    }
+    let manifest = Arc::new(Manifest {
+        table: read_manifest(&dir)?,
+        dir: dir.clone(),
+    });
+    if let Some(modified) = modified {
+        cache.insert(dir, (modified, Arc::clone(&manifest)));
+    }
+    Ok(manifest)
+}
+
+/// Get a value from the crate's [package.metadata.typst-bake] section.
+fn metadata(key: &str) -> Result<Option<toml::Value>, String> {
+    Ok(get_metadata_value(&manifest()?.table, key).cloned())
+}
+
 /// Get a value from [package.metadata.typst-bake] section.
 fn get_metadata_value<'a>(manifest: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
     manifest
@@ -48,19 +99,16 @@ fn get_config_dir(
     not_configured_msg: &str,
     dir_kind: &str,
 ) -> Result<PathBuf, String> {
-    let manifest_dir =
-        env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR not set".to_owned())?;
-    let manifest_dir = Path::new(&manifest_dir);
+    let manifest = manifest()?;
 
     // Priority 1: Environment variable
     let path = if let Ok(dir) = env::var(env_var) {
-        resolve_path(manifest_dir, &dir)
+        resolve_path(&manifest.dir, &dir)
     } else {
         // Priority 2: Cargo.toml metadata
-        let manifest = read_manifest(manifest_dir)?;
-        let dir = get_metadata_str(&manifest, metadata_key)
+        let dir = get_metadata_str(&manifest.table, metadata_key)
             .ok_or_else(|| not_configured_msg.to_owned())?;
-        resolve_path(manifest_dir, dir)
+        resolve_path(&manifest.dir, dir)
     };
 
     if !path.exists() {
@@ -92,6 +140,90 @@ pub fn get_template_dir() -> Result<PathBuf, String> {
     )
 }
 
+/// Get the template directories merged into the virtual root.
+///
+/// Priority:
+/// 1. Environment variable TYPST_BAKE_TEMPLATE_DIR (a single directory)
+/// 2. Cargo.toml [package.metadata.typst-bake] template-dirs, an array of directories
+/// 3. Cargo.toml [package.metadata.typst-bake] template-dir
+///
+/// Setting both `template-dir` and `template-dirs` is an error. Also returns whether the
+/// directories come from `template-dirs`, even a one-element list, since that setting
+/// can't be combined with `root-dir` or `template-sets`.
+pub fn get_template_dirs() -> Result<(Vec<PathBuf>, bool), String> {
+    // Priority 1: Environment variable
+    if env::var("TYPST_BAKE_TEMPLATE_DIR").is_ok() {
+        return Ok((vec![get_template_dir()?], false));
+    }
+
+    let manifest = manifest()?;
+
+    // Priority 3: a single template-dir
+    let Some(value) = get_metadata_value(&manifest.table, "template-dirs") else {
+        return Ok((vec![get_template_dir()?], false));
+    };
+    if get_metadata_value(&manifest.table, "template-dir").is_some() {
+        return Err("Set either template-dir or template-dirs, not both".to_owned());
+    }
+
+    let invalid = || {
+        "Invalid template-dirs value: expected a non-empty array of paths, \
+         e.g. template-dirs = [\"./templates\", \"./shared\"]"
+            .to_owned()
+    };
+    let dirs = value
+        .as_array()
+        .filter(|a| !a.is_empty())
+        .ok_or_else(invalid)?;
+    let dirs = dirs
+        .iter()
+        .map(|dir| {
+            let path = resolve_path(&manifest.dir, dir.as_str().ok_or_else(invalid)?);
+            if !path.is_dir() {
+                return Err(format!(
+                    "Template directory does not exist: {}",
+                    path.display()
+                ));
+            }
+            Ok(path)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((dirs, true))
+}
+
+/// Get the virtual root directory, which `/`-prefixed paths in templates resolve from.
+///
+/// Priority:
+/// 1. Environment variable TYPST_BAKE_ROOT_DIR
+/// 2. Cargo.toml [package.metadata.typst-bake] root-dir
+///
+/// Defaults to `template_dir`. When set, it must contain `template_dir`; the whole root
+/// is embedded so templates can reach shared files outside the template directory.
+pub fn get_root_dir(template_dir: &Path) -> Result<PathBuf, String> {
+    if !is_configured("TYPST_BAKE_ROOT_DIR", "root-dir") {
+        return Ok(template_dir.to_path_buf());
+    }
+
+    let root = get_config_dir(
+        "TYPST_BAKE_ROOT_DIR",
+        "root-dir",
+        "Root directory not configured.",
+        "Root",
+    )?;
+    let canonical = |path: &Path| {
+        path.canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {e}", path.display()))
+    };
+    if !canonical(template_dir)?.starts_with(canonical(&root)?) {
+        return Err(format!(
+            "Template directory {} is not inside root directory {}",
+            template_dir.display(),
+            root.display()
+        ));
+    }
+    Ok(root)
+}
+
 /// Check if package cache should be bypassed.
 pub fn should_refresh_cache() -> bool {
     env::var("TYPST_BAKE_PKG_NOCACHE").is_ok()
@@ -103,8 +235,14 @@ pub fn should_refresh_cache() -> bool {
 /// 1. Environment variable TYPST_BAKE_FONTS_DIR
 /// 2. Cargo.toml [package.metadata.typst-bake] fonts-dir
 ///
-/// At least one font file (.ttf, .otf, .ttc) must exist.
-pub fn get_fonts_dir() -> Result<PathBuf, String> {
+/// At least one font file (.ttf, .otf, .ttc) must exist, unless `fonts_optional` is set
+/// (fonts come from another source such as `google-fonts`). In that case an unconfigured
+/// fonts directory yields `None` and an empty one is accepted.
+pub fn get_fonts_dir(fonts_optional: bool) -> Result<Option<PathBuf>, String> {
+    if fonts_optional && !is_configured("TYPST_BAKE_FONTS_DIR", "fonts-dir") {
+        return Ok(None);
+    }
+
     let path = get_config_dir(
         "TYPST_BAKE_FONTS_DIR",
         "fonts-dir",
@@ -123,7 +261,7 @@ pub fn get_fonts_dir() -> Result<PathBuf, String> {
         .filter_map(Result::ok)
         .any(|entry| is_font_file(entry.path()));
 
-    if !has_fonts {
+    if !has_fonts && !fonts_optional {
         return Err(format!(
             "No font files found in fonts directory: {}\n\n\
             Supported formats: .ttf, .otf, .ttc",
@@ -131,7 +269,122 @@ pub fn get_fonts_dir() -> Result<PathBuf, String> {
         ));
     }
 
-    Ok(path)
+    Ok(Some(path))
+}
+
+/// Whether a directory setting is provided via environment variable or Cargo.toml.
+fn is_configured(env_var: &str, metadata_key: &str) -> bool {
+    env::var(env_var).is_ok() || metadata(metadata_key).is_ok_and(|value| value.is_some())
+}
+
+/// Get the Google Fonts families to embed.
+///
+/// Reads the Cargo.toml [package.metadata.typst-bake] google-fonts array, e.g.
+/// `google-fonts = ["Inter:400,700", "Noto Sans KR"]`. Returns an empty list if unset.
+pub fn get_google_fonts() -> Result<Vec<String>, String> {
+    let Some(value) = metadata("google-fonts")? else {
+        return Ok(Vec::new());
+    };
+    let invalid = || {
+        "Invalid google-fonts value: expected an array of strings, \
+         e.g. google-fonts = [\"Inter:400,700\"]"
+            .to_owned()
+    };
+    value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|v| v.as_str().map(str::to_owned).ok_or_else(invalid))
+        .collect()
+}
+
+/// Get the font family aliases.
+///
+/// Reads the Cargo.toml [package.metadata.typst-bake] font-aliases table, e.g.
+/// `font-aliases = { "Helvetica" = "Inter" }`, as `(alias, family)` pairs.
+/// Returns an empty list if unset.
+pub fn get_font_aliases() -> Result<Vec<(String, String)>, String> {
+    let Some(value) = metadata("font-aliases")? else {
+        return Ok(Vec::new());
+    };
+    let invalid = || {
+        "Invalid font-aliases value: expected a table of family names, \
+         e.g. font-aliases = { \"Helvetica\" = \"Inter\" }"
+            .to_owned()
+    };
+    value
+        .as_table()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|(alias, family)| {
+            let family = family.as_str().ok_or_else(invalid)?;
+            Ok((alias.clone(), family.to_owned()))
+        })
+        .collect()
+}
+
+/// Whether each subdirectory of `template-dir` is a separate template set.
+///
+/// Reads the Cargo.toml [package.metadata.typst-bake] template-sets boolean (default
+/// `false`).
+pub fn get_template_sets() -> Result<bool, String> {
+    match metadata("template-sets")? {
+        None => Ok(false),
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| "Invalid template-sets value: expected true or false".to_owned()),
+    }
+}
+
+/// The template set a document uses until `with_template_set` picks another.
+///
+/// Reads the Cargo.toml [package.metadata.typst-bake] default-template-set string.
+/// Returns `None` if unset.
+pub fn get_default_template_set() -> Result<Option<String>, String> {
+    match metadata("default-template-set")? {
+        None => Ok(None),
+        Some(value) => value.as_str().map(|s| Some(s.to_owned())).ok_or_else(|| {
+            "Invalid default-template-set value: expected the name of a template set".to_owned()
+        }),
+    }
+}
+
+/// How to report a build-time check's findings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Don't check.
+    Allow,
+    /// Print a warning during the build.
+    Warn,
+    /// Fail the build.
+    Deny,
+}
+
+/// Read a lint level from the Cargo.toml [package.metadata.typst-bake] `key`:
+/// `"allow"`, `"warn"`, or `"deny"`, with `default` when unset.
+fn get_lint_level(key: &str, default: LintLevel) -> Result<LintLevel, String> {
+    match metadata(key)?.as_ref().map(|v| v.as_str()) {
+        None => Ok(default),
+        Some(Some("allow")) => Ok(LintLevel::Allow),
+        Some(Some("warn")) => Ok(LintLevel::Warn),
+        Some(Some("deny")) => Ok(LintLevel::Deny),
+        Some(_) => Err(format!(
+            "Invalid {key} value: expected \"allow\", \"warn\", or \"deny\""
+        )),
+    }
+}
+
+/// Get the unused template file check level (`unused-files`, default `"allow"`).
+///
+/// Off by default: reachability follows only the macro's entry, so bundles rendering other
+/// entries with `with_entry` or reading files with `read_template` would always warn.
+pub fn get_unused_files_level() -> Result<LintLevel, String> {
+    get_lint_level("unused-files", LintLevel::Allow)
+}
+
+/// Get the template lint level (`lint`, default `"allow"`).
+pub fn get_template_lint_level() -> Result<LintLevel, String> {
+    get_lint_level("lint", LintLevel::Allow)
 }
 
 /// Check if a path refers to a hidden file or directory (name starts with '.').
@@ -152,6 +405,35 @@ pub fn is_font_file(path: &Path) -> bool {
         })
 }
 
+/// Get the build output verbosity.
+///
+/// Priority:
+/// 1. Environment variable TYPST_BAKE_LOG
+/// 2. Cargo.toml [package.metadata.typst-bake] log
+/// 3. Default: "info"
+///
+/// Accepts `"quiet"`, `"warn"`, or `"info"`; other values are ignored.
+pub fn get_log_level() -> LogLevel {
+    let parse = |value: &str| match value {
+        "quiet" => Some(LogLevel::Quiet),
+        "warn" => Some(LogLevel::Warn),
+        "info" => Some(LogLevel::Info),
+        _ => None,
+    };
+
+    // Priority 1: Environment variable
+    if let Some(level) = env::var("TYPST_BAKE_LOG").ok().and_then(|v| parse(&v)) {
+        return level;
+    }
+
+    // Priority 2: Cargo.toml metadata
+    metadata("log")
+        .ok()
+        .flatten()
+        .and_then(|value| value.as_str().and_then(parse))
+        .unwrap_or(LogLevel::Info)
+}
+
 const ZSTD_LEVEL_MIN: i32 = 1;
 const ZSTD_LEVEL_MAX: i32 = 22;
 const ZSTD_LEVEL_DEFAULT: i32 = 19;
@@ -172,12 +454,10 @@ pub fn get_compression_level() -> i32 {
     }
 
     // Priority 2: Cargo.toml metadata
-    if let Some(level) = env::var("CARGO_MANIFEST_DIR")
+    if let Some(level) = metadata("compression-level")
         .ok()
-        .and_then(|dir| read_manifest(Path::new(&dir)).ok())
-        .and_then(|manifest| {
-            get_metadata_value(&manifest, "compression-level").and_then(toml::Value::as_integer)
-        })
+        .flatten()
+        .and_then(|value| value.as_integer())
     {
         return (level as i32).clamp(ZSTD_LEVEL_MIN, ZSTD_LEVEL_MAX);
     }
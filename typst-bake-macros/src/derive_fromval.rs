@@ -0,0 +1,168 @@
+use crate::derive_intoval::{with_bound, FieldAttrs};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Result};
+
+/// Generate the `FromValue` and `Reflect` implementations for a struct, reading it from a
+/// Typst dictionary.
+pub fn derive_from_value(item: DeriveInput) -> Result<TokenStream> {
+    let ty = &item.ident;
+    let construct = construct_from_dict(&item)?;
+    let generics = bounded_generics(&item);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let foundations = quote!(::typst_bake::__internal::typst::foundations);
+
+    Ok(quote! {
+        impl #impl_generics #foundations::Reflect for #ty #ty_generics #where_clause {
+            fn input() -> #foundations::CastInfo {
+                #foundations::CastInfo::Type(#foundations::Type::of::<#foundations::Dict>())
+            }
+
+            fn output() -> #foundations::CastInfo {
+                #foundations::CastInfo::Type(#foundations::Type::of::<#foundations::Dict>())
+            }
+
+            fn castable(value: &#foundations::Value) -> bool {
+                ::core::matches!(value, #foundations::Value::Dict(_))
+            }
+        }
+
+        impl #impl_generics #foundations::FromValue for #ty #ty_generics #where_clause {
+            fn from_value(
+                value: #foundations::Value,
+            ) -> ::typst_bake::__internal::typst::diag::HintedStrResult<Self> {
+                #[allow(unused_mut, unused_variables)]
+                let mut dict = match value {
+                    #foundations::Value::Dict(dict) => dict,
+                    other => {
+                        let err = <Self as #foundations::Reflect>::error(&other);
+                        return ::core::result::Result::Err(err);
+                    }
+                };
+                ::core::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
+
+/// Generate the `from_dict` constructor and [`TryFrom`] conversion for a struct.
+pub fn derive_from_dict(item: DeriveInput) -> Result<TokenStream> {
+    let ty = &item.ident;
+    let construct = construct_from_dict(&item)?;
+    let generics = bounded_generics(&item);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let foundations = quote!(::typst_bake::__internal::typst::foundations);
+    let diag = quote!(::typst_bake::__internal::typst::diag);
+
+    Ok(quote! {
+        impl #impl_generics #ty #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            pub fn from_dict(mut dict: #foundations::Dict) -> #diag::HintedStrResult<Self> {
+                ::core::result::Result::Ok(#construct)
+            }
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<#foundations::Dict>
+            for #ty #ty_generics #where_clause
+        {
+            type Error = #diag::HintedString;
+
+            fn try_from(dict: #foundations::Dict) -> ::core::result::Result<Self, Self::Error> {
+                Self::from_dict(dict)
+            }
+        }
+    })
+}
+
+/// The generics of `item` with a `FromValue` bound on every type parameter, since fields
+/// of those types are read.
+fn bounded_generics(item: &DeriveInput) -> syn::Generics {
+    with_bound(
+        item,
+        syn::parse_quote!(::typst_bake::__internal::typst::foundations::FromValue),
+    )
+}
+
+/// `Self { .. }` with each field read from a mutable `dict` in scope.
+fn construct_from_dict(item: &DeriveInput) -> Result<TokenStream> {
+    let Data::Struct(ref data) = item.data else {
+        return Err(syn::Error::new_spanned(item, "only structs are supported"));
+    };
+    let Fields::Named(ref fields) = data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "only named fields are supported",
+        ));
+    };
+
+    let mut inits = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().unwrap();
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        if attrs.with.is_some() || attrs.bytes {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`with` and `bytes` can't be read back, only `rename`, `skip`, and `skip_if` are supported",
+            ));
+        }
+        if attrs.skip {
+            inits.push(quote!(#ident: ::core::default::Default::default()));
+            continue;
+        }
+        let key = attrs.rename.unwrap_or_else(|| ident.to_string());
+        // A field omitted by `skip_if` reads back as its default.
+        let read = match attrs.skip_if {
+            Some(_) => quote!(::typst_bake::__internal::dict_field_or_default),
+            None => quote!(::typst_bake::__internal::dict_field),
+        };
+        inits.push(quote!(#ident: #read(&mut dict, #key)?));
+    }
+    Ok(quote!(Self { #(#inits),* }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> Result<String> {
+        let item = syn::parse_str::<DeriveInput>(input).expect("valid struct");
+        derive_from_value(item).map(|tokens| tokens.to_string())
+    }
+
+    #[test]
+    fn test_fields() {
+        let code = expand(
+            "struct Totals { #[typst(rename = \"grand-total\")] total: f64, #[typst(skip)] cached: bool, pages: Vec<i64> }",
+        )
+        .unwrap();
+        assert!(code.contains(
+            "total : :: typst_bake :: __internal :: dict_field (& mut dict , \"grand-total\") ?"
+        ));
+        assert!(code.contains("cached : :: core :: default :: Default :: default ()"));
+        assert!(code.contains("\"pages\""));
+
+        let code =
+            expand("struct Totals { #[typst(skip_if = \"Vec::is_empty\")] notes: Vec<String> }")
+                .unwrap();
+        assert!(code.contains("dict_field_or_default (& mut dict , \"notes\")"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let err = expand("struct Logo { #[typst(bytes)] png: Vec<u8> }").unwrap_err();
+        assert!(err.to_string().contains("can't be read back"));
+        let err = expand("enum Status { Draft }").unwrap_err();
+        assert!(err.to_string().contains("only structs"));
+    }
+
+    #[test]
+    fn test_from_dict() {
+        let item = syn::parse_str::<DeriveInput>("struct Page<T> { items: Vec<T> }").unwrap();
+        let code = derive_from_dict(item).unwrap().to_string();
+        assert!(code.contains("pub fn from_dict"));
+        assert!(
+            code.contains("T : :: typst_bake :: __internal :: typst :: foundations :: FromValue")
+        );
+        assert!(code.contains("TryFrom"));
+    }
+}
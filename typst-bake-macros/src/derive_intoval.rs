@@ -3,20 +3,54 @@
 // Licensed under Apache-2.0 / MIT
 
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{Data, DeriveInput, Fields, Result};
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Data, DataEnum, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, Index, LitStr, Result};
 
-/// Generate the [`IntoValue`] trait implementation for a struct.
+/// Generate the [`IntoValue`] trait implementation for a struct or enum.
 pub fn derive_into_value(item: DeriveInput) -> Result<TokenStream> {
-    let (ty, dictentries) = gather_input(&item)?;
+    let ty = &item.ident;
+    let generics = bounded_generics(&item);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &item.data {
+        Data::Enum(data) => {
+            let arms = enum_arms(data)?;
+            Some(quote! {
+                match self {
+                    #(#arms),*
+                }
+            })
+        }
+        // A newtype is its inner value, a tuple struct an array.
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(fields) => Some(unnamed_value(fields, |i| {
+                let index = Index::from(i);
+                quote!(self.#index)
+            })?),
+            _ => None,
+        },
+        Data::Union(_) => None,
+    };
+    if let Some(body) = body {
+        return Ok(quote! {
+            impl #impl_generics ::typst_bake::__internal::typst::foundations::IntoValue
+                for #ty #ty_generics #where_clause
+            {
+                fn into_value(self) -> ::typst_bake::__internal::typst::foundations::Value {
+                    #body
+                }
+            }
+        });
+    }
+
+    let (ty, dict) = gather_input(&item)?;
 
     Ok(quote! {
-        impl ::typst_bake::__internal::typst::foundations::IntoValue for #ty {
+        impl #impl_generics ::typst_bake::__internal::typst::foundations::IntoValue
+            for #ty #ty_generics #where_clause
+        {
             fn into_value(self) -> ::typst_bake::__internal::typst::foundations::Value {
-                let d = ::typst_bake::__internal::typst::foundations::dict!(
-                    #(#dictentries),*
-                );
-                ::typst_bake::__internal::typst::foundations::Value::Dict(d)
+                ::typst_bake::__internal::typst::foundations::Value::Dict(#dict)
             }
         }
     })
@@ -24,28 +58,48 @@ pub fn derive_into_value(item: DeriveInput) -> Result<TokenStream> {
 
 /// Generate the [`IntoDict`] implementation and [`From`] conversion for a struct.
 pub fn derive_into_dict(item: DeriveInput) -> Result<TokenStream> {
-    let (ty, dictentries) = gather_input(&item)?;
+    let (ty, dict) = gather_input(&item)?;
+    let generics = bounded_generics(&item);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     Ok(quote! {
-        impl #ty {
+        impl #impl_generics #ty #ty_generics #where_clause {
             #[inline]
             #[must_use]
             pub fn into_dict(self) -> ::typst_bake::__internal::typst::foundations::Dict {
-                ::typst_bake::__internal::typst::foundations::dict!(
-                    #(#dictentries),*
-                )
+                #dict
             }
         }
 
-        impl ::core::convert::From<#ty> for ::typst_bake::__internal::typst::foundations::Dict {
-            fn from(value: #ty) -> Self {
+        impl #impl_generics ::core::convert::From<#ty #ty_generics>
+            for ::typst_bake::__internal::typst::foundations::Dict #where_clause
+        {
+            fn from(value: #ty #ty_generics) -> Self {
                 value.into_dict()
             }
         }
     })
 }
 
-fn gather_input(item: &DeriveInput) -> Result<(&syn::Ident, Vec<TokenStream>)> {
+/// The generics of `item` with an `IntoValue` bound on every type parameter, since
+/// fields of those types are converted.
+fn bounded_generics(item: &DeriveInput) -> syn::Generics {
+    with_bound(
+        item,
+        syn::parse_quote!(::typst_bake::__internal::typst::foundations::IntoValue),
+    )
+}
+
+/// The generics of `item` with `bound` added to every type parameter.
+pub(crate) fn with_bound(item: &DeriveInput, bound: syn::TypeParamBound) -> syn::Generics {
+    let mut generics = item.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+    generics
+}
+
+fn gather_input(item: &DeriveInput) -> Result<(&syn::Ident, TokenStream)> {
     let ty = &item.ident;
 
     let Data::Struct(ref data) = &item.data else {
@@ -59,18 +113,288 @@ fn gather_input(item: &DeriveInput) -> Result<(&syn::Ident, Vec<TokenStream>)> {
         ));
     };
 
-    let dictentries: Vec<_> = fields
-        .named
+    let (_, dict) = named_dict(fields, |ident| quote!(self.#ident))?;
+    Ok((ty, dict))
+}
+
+/// An expression building the dictionary of named fields, each read through `access`,
+/// plus the identifiers of the fields that were not skipped. Entries are inserted in
+/// field order, each behind its `skip_if` predicate if it has one.
+fn named_dict(
+    fields: &FieldsNamed,
+    access: impl Fn(&syn::Ident) -> TokenStream,
+) -> Result<(Vec<&syn::Ident>, TokenStream)> {
+    let foundations = quote!(::typst_bake::__internal::typst::foundations);
+    let mut used = Vec::new();
+    let mut inserts = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().unwrap();
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let key = attrs.rename.unwrap_or_else(|| ident.to_string());
+        let expr = access(ident);
+        let value = match attrs.with {
+            Some(with) => quote!(#with(#expr)),
+            None if attrs.bytes => quote!(::typst_bake::__internal::bytes_value(#expr)),
+            None => field_value(expr.clone(), &field.ty),
+        };
+        let insert = quote!(__typst_dict.insert(#foundations::Str::from(#key), #value););
+        inserts.push(match attrs.skip_if {
+            Some(predicate) => quote!(if !#predicate(&#expr) { #insert }),
+            None => insert,
+        });
+        used.push(ident);
+    }
+    let dict = quote! {{
+        #[allow(unused_mut)]
+        let mut __typst_dict = #foundations::Dict::new();
+        #(#inserts)*
+        __typst_dict
+    }};
+    Ok((used, dict))
+}
+
+/// Convert unnamed fields, each read through `access`: one field to its value, several
+/// to an array.
+fn unnamed_value(
+    fields: &FieldsUnnamed,
+    access: impl Fn(usize) -> TokenStream,
+) -> Result<TokenStream> {
+    if let Some(attr) = fields
+        .unnamed
+        .iter()
+        .flat_map(|field| &field.attrs)
+        .find(|attr| attr.path().is_ident("typst"))
+    {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "typst attributes are only supported on named fields",
+        ));
+    }
+
+    let mut values = fields
+        .unnamed
         .iter()
-        .map(|field| {
-            let ident = field.ident.as_ref().unwrap();
-            let key = ident.to_string();
+        .enumerate()
+        .map(|(i, field)| field_value(access(i), &field.ty));
+    if fields.unnamed.len() == 1 {
+        return Ok(values.next().unwrap());
+    }
+    Ok(quote! {
+        ::typst_bake::__internal::typst::foundations::Value::Array(
+            ::core::iter::IntoIterator::into_iter([#(#values),*])
+                .collect::<::typst_bake::__internal::typst::foundations::Array>()
+        )
+    })
+}
+
+/// Convert one field. `Field` picks the conversion by type, which also covers std types
+/// that can't implement Typst's `IntoValue`. The call is spanned to the field's type, so
+/// an unsupported type is reported on the field rather than on the derive.
+fn field_value(expr: TokenStream, ty: &syn::Type) -> TokenStream {
+    quote_spanned! {ty.span()=> {
+        use ::typst_bake::__internal::{
+            DateTimeField as _, DecimalField as _, IntoValueField as _, MapField as _,
+            StdTimeField as _, StrField as _, TupleField as _, UnsupportedField as _,
+        };
+        ::typst_bake::__internal::Field(#expr).into_field_value()
+    }}
+}
 
-            quote! {
-                #key => ::typst_bake::__internal::typst::foundations::IntoValue::into_value(self.#ident)
+/// Match arms converting each variant, tagged externally like serde's default so derived
+/// and serialized inputs look the same to templates: a unit variant becomes its name as
+/// a string, a newtype variant `(Name: value)`, a tuple variant `(Name: (a, b))`, and a
+/// struct variant `(Name: (field: value))`.
+fn enum_arms(data: &DataEnum) -> Result<Vec<TokenStream>> {
+    let foundations = quote!(::typst_bake::__internal::typst::foundations);
+    data.variants
+        .iter()
+        .map(|variant| {
+            let ident = &variant.ident;
+            let attrs = FieldAttrs::parse(&variant.attrs)?;
+            if attrs.skip || attrs.skip_if.is_some() || attrs.with.is_some() || attrs.bytes {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "only `rename` is supported on enum variants",
+                ));
             }
+            let name = attrs.rename.unwrap_or_else(|| ident.to_string());
+
+            let (pattern, value) = match &variant.fields {
+                Fields::Unit => {
+                    let value = quote!(#foundations::Value::Str(#name.into()));
+                    return Ok(quote!(Self::#ident => #value));
+                }
+                Fields::Unnamed(fields) => {
+                    let bindings: Vec<_> = (0..fields.unnamed.len())
+                        .map(|i| format_ident!("f{i}"))
+                        .collect();
+                    let value = unnamed_value(fields, |i| {
+                        let binding = &bindings[i];
+                        quote!(#binding)
+                    })?;
+                    (quote!((#(#bindings),*)), value)
+                }
+                Fields::Named(fields) => {
+                    let (used, dict) = named_dict(fields, |ident| quote!(#ident))?;
+                    (
+                        quote!({ #(#used,)* .. }),
+                        quote!(#foundations::Value::Dict(#dict)),
+                    )
+                }
+            };
+            Ok(quote! {
+                Self::#ident #pattern => #foundations::Value::Dict(
+                    #foundations::dict!(#name => #value)
+                )
+            })
         })
-        .collect();
+        .collect()
+}
+
+/// Options given with `#[typst(...)]` on a field or enum variant.
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// Dictionary key to use instead of the field name.
+    pub rename: Option<String>,
+    /// Leave the field out of the dictionary.
+    pub skip: bool,
+    /// Leave the field out of the dictionary when this predicate, called with a reference
+    /// to the field, returns `true`.
+    pub skip_if: Option<syn::ExprPath>,
+    /// Function converting the field to a `Value`, instead of the built-in conversion.
+    pub with: Option<syn::ExprPath>,
+    /// Convert the field to Typst `bytes` rather than an array of integers.
+    pub bytes: bool,
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[syn::Attribute]) -> Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("typst")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    parsed.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("skip_if") {
+                    parsed.skip_if = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("with") {
+                    parsed.with = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("bytes") {
+                    parsed.bytes = true;
+                    Ok(())
+                } else {
+                    Err(meta.error(
+                        "unknown typst attribute, expected `rename`, `skip`, `skip_if`, `with`, or `bytes`",
+                    ))
+                }
+            })?;
+            if parsed.bytes && parsed.with.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`bytes` and `with` cannot be combined",
+                ));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> Result<String> {
+        let item = syn::parse_str::<DeriveInput>(input).expect("valid struct");
+        derive_into_value(item).map(|tokens| tokens.to_string())
+    }
+
+    #[test]
+    fn test_rename() {
+        let code =
+            expand("struct Invoice { #[typst(rename = \"invoice-number\")] invoice_number: u32 }")
+                .unwrap();
+        assert!(code.contains("\"invoice-number\""));
+        assert!(!code.contains("\"invoice_number\""));
+
+        let err = expand("struct Invoice { #[typst(name = \"x\")] number: u32 }").unwrap_err();
+        assert!(err.to_string().contains("unknown typst attribute"));
+    }
+
+    #[test]
+    fn test_skip() {
+        let code = expand("struct Invoice { #[typst(skip)] db_id: u64, number: u32 }").unwrap();
+        assert!(code.contains("\"number\""));
+        assert!(!code.contains("db_id"));
+    }
+
+    #[test]
+    fn test_enum() {
+        let code = expand(
+            "enum Status { Draft, #[typst(rename = \"paid\")] Paid { on: String }, Refund(u32) }",
+        )
+        .unwrap();
+        assert!(code.contains("Self :: Draft => "));
+        assert!(code.contains("\"paid\""));
+        assert!(code.contains("Self :: Refund (f0)"));
+
+        let err = expand("enum Status { #[typst(skip)] Draft }").unwrap_err();
+        assert!(err.to_string().contains("only `rename` is supported"));
+        let err = expand("enum Status { #[typst(with = \"f\")] Draft }").unwrap_err();
+        assert!(err.to_string().contains("only `rename` is supported"));
+    }
+
+    #[test]
+    fn test_with() {
+        let code = expand("struct Order { #[typst(with = \"geo::to_value\")] origin: geo::Point }")
+            .unwrap();
+        assert!(code.contains("Str :: from (\"origin\") , geo :: to_value (self . origin)"));
+    }
+
+    #[test]
+    fn test_skip_if() {
+        let code = expand(
+            "struct Invoice { #[typst(skip_if = \"Option::is_none\")] due: Option<String>, number: u32 }",
+        )
+        .unwrap();
+        assert!(code.contains("if ! Option :: is_none (& self . due)"));
+        let due = code.find("\"due\"").unwrap();
+        assert!(due < code.find("\"number\"").unwrap());
+    }
+
+    #[test]
+    fn test_bytes() {
+        let code = expand("struct Logo { #[typst(bytes)] png: Vec<u8> }").unwrap();
+        assert!(code.contains("bytes_value (self . png)"));
+
+        let err = expand("struct Logo { #[typst(bytes, with = \"f\")] png: Vec<u8> }").unwrap_err();
+        assert!(err.to_string().contains("cannot be combined"));
+    }
+
+    #[test]
+    fn test_generics() {
+        let code = expand("struct Page<'a, T: Clone> { title: &'a str, items: Vec<T> }").unwrap();
+        assert!(code.contains(
+            "T : Clone + :: typst_bake :: __internal :: typst :: foundations :: IntoValue"
+        ));
+        assert!(code.contains("for Page < 'a , T >"));
+    }
+
+    #[test]
+    fn test_tuple_structs() {
+        let newtype = expand("struct Money(Decimal);").unwrap();
+        assert!(newtype.contains("Field (self . 0)"));
+        assert!(!newtype.contains("Array"));
 
-    Ok((ty, dictentries))
+        let pair = expand("struct Point(f64, f64);").unwrap();
+        assert!(pair.contains("Field (self . 1)"));
+        assert!(pair.contains("Array"));
+    }
 }
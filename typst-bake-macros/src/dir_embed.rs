@@ -4,8 +4,10 @@ use crate::compression_cache::CompressionCache;
 use crate::config::{is_font_file, is_hidden};
 use proc_macro2::TokenStream;
 use quote::quote;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Result of embedding a directory, containing entries and statistics.
 #[derive(Default)]
@@ -18,6 +20,11 @@ pub struct DirEmbedResult {
     pub compressed_size: usize,
     /// Number of files embedded.
     pub file_count: usize,
+    /// `(relative path, BLAKE3 hex hash)` of every embedded file, in embedding order.
+    pub content_hashes: Vec<(String, String)>,
+    /// Files that could not be embedded, one message each. Collected instead of
+    /// failing fast so the build reports every problem at once.
+    pub errors: Vec<String>,
 }
 
 impl DirEmbedResult {
@@ -28,15 +35,77 @@ impl DirEmbedResult {
             ::typst_bake::__internal::include_dir::Dir::new(#name, &[#(#entries),*])
         }
     }
+
+    /// Append the entries and statistics of `other`.
+    pub fn extend(&mut self, other: DirEmbedResult) {
+        self.entries.extend(other.entries);
+        self.original_size += other.original_size;
+        self.compressed_size += other.compressed_size;
+        self.file_count += other.file_count;
+        self.content_hashes.extend(other.content_hashes);
+        self.errors.extend(other.errors);
+    }
+}
+
+/// One embedded file: its DirEntry code plus statistics.
+struct FileEmbed {
+    entry: TokenStream,
+    original_len: usize,
+    compressed_len: usize,
+    hash: String,
+}
+
+/// Generate the DirEntry code for one file.
+fn file_entry(name: &str, path: &Path, cache: &mut CompressionCache) -> Result<FileEmbed, String> {
+    let file_bytes =
+        fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+    let original_len = file_bytes.len();
+    let blob_info = cache
+        .compress(&file_bytes)
+        .map_err(|e| format!("Failed to compress {}: {e}", path.display()))?;
+    let blob_ident = quote::format_ident!("BLOB_{}", blob_info.hash);
+
+    // Absolute path for Cargo file tracking; a relative one would resolve against the
+    // calling source file.
+    let abs_path = path
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {e}", path.display()))?;
+    let abs_path = abs_path
+        .to_str()
+        .ok_or_else(|| format!("Path is not valid UTF-8: {}", path.display()))?
+        .replace('\\', "/");
+
+    let entry = quote! {
+        ::typst_bake::__internal::include_dir::DirEntry::File(
+            ::typst_bake::__internal::include_dir::File::new(
+                #name,
+                {
+                    // Cargo file tracking (not used at runtime)
+                    const _: &[u8] = include_bytes!(#abs_path);
+                    &#blob_ident
+                }
+            )
+        )
+    };
+    Ok(FileEmbed {
+        entry,
+        original_len,
+        compressed_len: blob_info.compressed_len,
+        hash: blob_info.hash,
+    })
 }
 
 /// Context for recursive directory scanning, bundling mutable state and config.
 struct ScanContext<'a, F> {
-    base: &'a Path,
+    /// Directories merged into one tree, in configuration order.
+    bases: &'a [PathBuf],
     file_filter: F,
     original_size: usize,
     compressed_size: usize,
     file_count: usize,
+    content_hashes: Vec<(String, String)>,
+    errors: Vec<String>,
     cache: &'a mut CompressionCache,
 }
 
@@ -44,86 +113,102 @@ impl<'a, F> ScanContext<'a, F>
 where
     F: Fn(&Path) -> bool + Copy,
 {
-    fn new(base: &'a Path, file_filter: F, cache: &'a mut CompressionCache) -> Self {
+    fn new(bases: &'a [PathBuf], file_filter: F, cache: &'a mut CompressionCache) -> Self {
         Self {
-            base,
+            bases,
             file_filter,
             original_size: 0,
             compressed_size: 0,
             file_count: 0,
+            content_hashes: Vec::new(),
+            errors: Vec::new(),
             cache,
         }
     }
 
-    /// Recursively scan directory and generate DirEntry code for each item.
-    fn scan_entries(&mut self, current: &Path) -> Vec<TokenStream> {
+    /// Recursively scan the directory `rel` of every base and generate DirEntry code for
+    /// each item. Directories of the same name are merged; a file present in more than
+    /// one base is an error.
+    fn scan_entries(&mut self, rel: &Path) -> Vec<TokenStream> {
         let mut entries = Vec::new();
 
-        let Ok(read_dir) = fs::read_dir(current) else {
-            return entries;
-        };
-
-        // Collect and sort entries for consistent ordering
-        let mut dir_entries: Vec<_> = read_dir.filter_map(Result::ok).collect();
-        dir_entries.sort_by_key(|e| e.path());
+        // Group by name, sorted for consistent ordering.
+        let mut by_name: BTreeMap<OsString, Vec<PathBuf>> = BTreeMap::new();
+        for base in self.bases {
+            let current = base.join(rel);
+            if !current.is_dir() {
+                continue;
+            }
+            let read_dir = match fs::read_dir(&current) {
+                Ok(read_dir) => read_dir,
+                Err(e) => {
+                    self.errors.push(format!(
+                        "Failed to read directory {}: {e}",
+                        current.display()
+                    ));
+                    continue;
+                }
+            };
+            for entry in read_dir.filter_map(Result::ok) {
+                by_name
+                    .entry(entry.file_name())
+                    .or_default()
+                    .push(entry.path());
+            }
+        }
 
-        for entry in dir_entries {
-            let path = entry.path();
+        for (name, paths) in by_name {
+            let path = &paths[0];
 
-            if is_hidden(&path) {
+            if is_hidden(path) {
                 continue;
             }
 
-            let Ok(rel_path) = path.strip_prefix(self.base) else {
+            let rel_path = rel.join(&name);
+
+            // Use just the file/dir name (not full relative path) for proper nesting.
+            // Templates can only name UTF-8 paths, so anything else could never be read.
+            let Some(name) = name.to_str().map(str::to_owned) else {
+                self.errors.push(format!(
+                    "File name is not valid UTF-8: {}",
+                    rel_path.display()
+                ));
                 continue;
             };
 
-            // Use just the file/dir name (not full relative path) for proper nesting
-            let name = match path.file_name().and_then(|n| n.to_str()) {
-                Some(n) => n.to_owned(),
-                None => rel_path.to_string_lossy().into_owned(),
-            };
+            if paths.len() > 1 && !paths.iter().all(|p| p.is_dir()) {
+                let list = paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.errors.push(format!(
+                    "{} is in more than one template directory: {list}",
+                    rel_path.display()
+                ));
+                continue;
+            }
 
             if path.is_file() {
-                if !(self.file_filter)(&path) {
+                if !(self.file_filter)(path) {
                     continue;
                 }
 
-                let file_bytes = fs::read(&path).unwrap_or_else(|e| {
-                    panic!("Failed to read file {}: {e}", path.display());
-                });
-
-                let original_len = file_bytes.len();
-                let blob_info = self.cache.compress(&file_bytes);
-                let compressed_len = blob_info.compressed_len;
-
-                self.original_size += original_len;
-                self.compressed_size += compressed_len;
+                let file = match file_entry(&name, path, self.cache) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        self.errors.push(e);
+                        continue;
+                    }
+                };
+                self.original_size += file.original_len;
+                self.compressed_size += file.compressed_len;
                 self.file_count += 1;
-
-                let blob_ident = quote::format_ident!("BLOB_{}", blob_info.hash);
-
-                // Get absolute path for Cargo file tracking
-                let abs_path = path
-                    .canonicalize()
-                    .unwrap_or_else(|_| path.to_path_buf())
-                    .to_string_lossy()
-                    .replace('\\', "/");
-
-                entries.push(quote! {
-                    ::typst_bake::__internal::include_dir::DirEntry::File(
-                        ::typst_bake::__internal::include_dir::File::new(
-                            #name,
-                            {
-                                // Cargo file tracking (not used at runtime)
-                                const _: &[u8] = include_bytes!(#abs_path);
-                                &#blob_ident
-                            }
-                        )
-                    )
-                });
+                self.content_hashes
+                    .push((rel_path.to_string_lossy().replace('\\', "/"), file.hash));
+                entries.push(file.entry);
             } else if path.is_dir() {
-                let sub_entries = self.scan_entries(&path);
+                let sub_entries = self.scan_entries(&rel_path);
                 entries.push(quote! {
                     ::typst_bake::__internal::include_dir::DirEntry::Dir(
                         ::typst_bake::__internal::include_dir::Dir::new(
@@ -145,31 +230,56 @@ where
             original_size: self.original_size,
             compressed_size: self.compressed_size,
             file_count: self.file_count,
+            content_hashes: self.content_hashes,
+            errors: self.errors,
         }
     }
 }
 
 fn embed_with_filter(
-    dir_path: &Path,
+    dirs: &[PathBuf],
     filter: impl Fn(&Path) -> bool + Copy,
     cache: &mut CompressionCache,
 ) -> DirEmbedResult {
-    if !dir_path.exists() {
-        return DirEmbedResult::default();
-    }
-    let mut ctx = ScanContext::new(dir_path, filter, cache);
-    let entries = ctx.scan_entries(dir_path);
+    let mut ctx = ScanContext::new(dirs, filter, cache);
+    let entries = ctx.scan_entries(Path::new(""));
     ctx.into_result(entries)
 }
 
 /// Generate code that creates a Dir struct from a directory path.
 /// Files are compressed with zstd using the configured compression level and cache.
 pub fn embed_dir(dir_path: &Path, cache: &mut CompressionCache) -> DirEmbedResult {
-    embed_with_filter(dir_path, |_| true, cache)
+    embed_dirs(&[dir_path.to_path_buf()], cache)
+}
+
+/// Like [`embed_dir`], merging several directories into one tree. A file at the same
+/// relative path in two of them is reported as an error.
+pub fn embed_dirs(dirs: &[PathBuf], cache: &mut CompressionCache) -> DirEmbedResult {
+    embed_with_filter(dirs, |_| true, cache)
 }
 
 /// Generate code that embeds only font files from a directory.
 /// Supported formats: .ttf, .otf, .ttc.
 pub fn embed_fonts_dir(dir_path: &Path, cache: &mut CompressionCache) -> DirEmbedResult {
-    embed_with_filter(dir_path, is_font_file, cache)
+    embed_with_filter(&[dir_path.to_path_buf()], is_font_file, cache)
+}
+
+/// Generate top-level file entries for individual files, given as `(name, path)` pairs.
+pub fn embed_files(files: &[(String, PathBuf)], cache: &mut CompressionCache) -> DirEmbedResult {
+    let mut result = DirEmbedResult::default();
+    for (name, path) in files {
+        let file = match file_entry(name, path, cache) {
+            Ok(file) => file,
+            Err(e) => {
+                result.errors.push(e);
+                continue;
+            }
+        };
+        result.entries.push(file.entry);
+        result.original_size += file.original_len;
+        result.compressed_size += file.compressed_len;
+        result.file_count += 1;
+        result.content_hashes.push((name.clone(), file.hash));
+    }
+    result
 }
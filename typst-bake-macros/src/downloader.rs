@@ -1,5 +1,6 @@
 //! Package download and cache management.
 
+use crate::log::{status, warning};
 use crate::scanner::{extract_packages, PackageSpec, ResolvedPackage};
 use std::collections::{HashSet, VecDeque};
 use std::fs;
@@ -104,7 +105,7 @@ pub fn resolve_packages(
         if let Some(data) = data_dir {
             let local_path = pkg.package_dir(data);
             if local_path.exists() {
-                eprintln!("  Local: {pkg}");
+                status!("  Local: {pkg}");
                 for dep in resolve_dependencies(&local_path) {
                     queue.push_back(dep);
                 }
@@ -119,7 +120,7 @@ pub fn resolve_packages(
         // 2. Check cache directory
         let cache_path = pkg.package_dir(cache_dir);
         if cache_path.exists() && !refresh {
-            eprintln!("  Cached: {pkg}");
+            status!("  Cached: {pkg}");
             for dep in resolve_dependencies(&cache_path) {
                 queue.push_back(dep);
             }
@@ -132,13 +133,13 @@ pub fn resolve_packages(
 
         // 3. Download from Universe (only for downloadable namespaces)
         if pkg.is_downloadable() {
-            eprintln!("  Downloading: {pkg}");
+            status!("  Downloading: {pkg}");
             if let Err(e) = download_and_extract(&pkg.download_url(), &cache_path, refresh) {
-                eprintln!("  ✗ Failed: {pkg}: {e}");
+                warning!("  ✗ Failed: {pkg}: {e}");
                 failed_packages.push(format!("{pkg}: download failed: {e}"));
                 continue;
             }
-            eprintln!("  ✓ {pkg}");
+            status!("  ✓ {pkg}");
             for dep in resolve_dependencies(&cache_path) {
                 queue.push_back(dep);
             }
@@ -189,10 +190,11 @@ fn download_and_extract(
     }
 
     // Acquire per-package exclusive lock (other processes block here)
-    let lock_path = dest.with_file_name(format!(
-        "{}.lock",
-        dest.file_name().unwrap().to_string_lossy()
-    ));
+    let dest_name = dest
+        .file_name()
+        .ok_or_else(|| format!("Invalid package directory: {}", dest.display()))?
+        .to_string_lossy();
+    let lock_path = dest.with_file_name(format!("{dest_name}.lock"));
     let mut lock = fd_lock::RwLock::new(fs::File::create(&lock_path)?);
     let _guard = lock.write()?;
 
@@ -223,11 +225,11 @@ fn extract_tar_gz(bytes: &[u8], dest: &Path) -> Result<(), Box<dyn std::error::E
     use flate2::read::GzDecoder;
 
     // PID-based unique temp directory (same parent = same filesystem → rename is atomic)
-    let temp = dest.with_file_name(format!(
-        "{}.tmp.{}",
-        dest.file_name().unwrap().to_string_lossy(),
-        std::process::id()
-    ));
+    let dest_name = dest
+        .file_name()
+        .ok_or_else(|| format!("Invalid package directory: {}", dest.display()))?
+        .to_string_lossy();
+    let temp = dest.with_file_name(format!("{dest_name}.tmp.{}", std::process::id()));
 
     // Clean up any leftover temp directory and create fresh
     if temp.exists() {
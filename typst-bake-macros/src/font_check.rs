@@ -0,0 +1,272 @@
+//! Build-time checks on embedded font files.
+
+use crate::config::{is_font_file, is_hidden};
+use crate::log::warning;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Identity of a font face: two faces with equal keys are interchangeable for Typst.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FaceKey {
+    pub family: String,
+    pub subfamily: String,
+    pub weight: u16,
+    pub italic: bool,
+    pub version: String,
+}
+
+/// A face found in a font file.
+#[derive(Clone, Debug)]
+pub struct FaceRecord {
+    pub key: FaceKey,
+    pub path: PathBuf,
+    /// BLAKE3 hash of the whole file, so byte-identical copies can be told apart.
+    pub hash: [u8; 32],
+    pub file_size: usize,
+}
+
+/// A face contributed by more than one font file.
+#[derive(Debug)]
+pub struct DuplicateFace {
+    pub key: FaceKey,
+    /// The files providing the face, sorted.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Parse every face in the font files under `fonts_dir`.
+///
+/// Files that fail to parse are skipped; Typst ignores them as well.
+pub fn collect_faces(fonts_dir: &Path) -> Vec<FaceRecord> {
+    let mut records = Vec::new();
+
+    for entry in WalkDir::new(fonts_dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_hidden(e.path()))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && is_font_file(e.path()))
+    {
+        let Ok(data) = fs::read(entry.path()) else {
+            continue;
+        };
+        let hash = *blake3::hash(&data).as_bytes();
+        let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+        for index in 0..count {
+            let Ok(face) = ttf_parser::Face::parse(&data, index) else {
+                continue;
+            };
+            let Some(key) = face_key(&face) else {
+                continue;
+            };
+            records.push(FaceRecord {
+                key,
+                path: entry.path().to_path_buf(),
+                hash,
+                file_size: data.len(),
+            });
+        }
+    }
+
+    records
+}
+
+/// Read the identifying names and style of a face.
+fn face_key(face: &ttf_parser::Face) -> Option<FaceKey> {
+    use ttf_parser::name_id;
+
+    let name = |ids: &[u16]| {
+        ids.iter().find_map(|&id| {
+            face.names()
+                .into_iter()
+                .filter(|n| n.name_id == id && n.is_unicode())
+                .find_map(|n| n.to_string())
+        })
+    };
+
+    Some(FaceKey {
+        family: name(&[name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY])?,
+        subfamily: name(&[name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]).unwrap_or_default(),
+        weight: face.weight().to_number(),
+        italic: face.is_italic(),
+        version: name(&[name_id::VERSION]).unwrap_or_default(),
+    })
+}
+
+/// The distinct files providing each face, keyed by content hash. Byte-identical copies
+/// collapse into one entry under the first of their paths.
+fn files_by_key(records: &[FaceRecord]) -> BTreeMap<&FaceKey, BTreeMap<[u8; 32], &Path>> {
+    let mut by_key: BTreeMap<&FaceKey, BTreeMap<[u8; 32], &Path>> = BTreeMap::new();
+    for record in records {
+        let path = by_key
+            .entry(&record.key)
+            .or_default()
+            .entry(record.hash)
+            .or_insert(&record.path);
+        *path = (*path).min(record.path.as_path());
+    }
+    by_key
+}
+
+/// Find faces provided by more than one distinct file.
+///
+/// Byte-identical copies are not reported: the compression cache already stores them
+/// once, so they cost nothing.
+pub fn find_duplicates(records: &[FaceRecord]) -> Vec<DuplicateFace> {
+    files_by_key(records)
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(key, files)| {
+            let mut paths: Vec<PathBuf> = files.into_values().map(Path::to_path_buf).collect();
+            paths.sort();
+            DuplicateFace {
+                key: key.clone(),
+                paths,
+            }
+        })
+        .collect()
+}
+
+/// Files whose every face is also provided by another file, with their sizes, sorted.
+/// Removing them loses nothing.
+///
+/// Each face is kept from the first of its files by path; a file is redundant when it
+/// is kept for none of its faces. A collection with one duplicated face among unique ones
+/// is still needed, and a file is listed once however many of its faces are duplicated.
+pub fn redundant_files(records: &[FaceRecord]) -> Vec<(PathBuf, usize)> {
+    let kept: HashSet<[u8; 32]> = files_by_key(records)
+        .into_values()
+        .filter_map(|files| files.into_iter().min_by_key(|&(_, path)| path))
+        .map(|(hash, _)| hash)
+        .collect();
+
+    let mut files: BTreeMap<[u8; 32], (&Path, usize)> = BTreeMap::new();
+    for record in records.iter().filter(|r| !kept.contains(&r.hash)) {
+        let file = files
+            .entry(record.hash)
+            .or_insert((&record.path, record.file_size));
+        file.0 = file.0.min(record.path.as_path());
+    }
+    let mut redundant: Vec<(PathBuf, usize)> = files
+        .into_values()
+        .map(|(path, size)| (path.to_path_buf(), size))
+        .collect();
+    redundant.sort();
+    redundant
+}
+
+/// Warn about font faces embedded more than once from different files.
+pub fn warn_duplicate_faces(fonts_dir: &Path) {
+    let records = collect_faces(fonts_dir);
+    let display = |p: &Path| p.strip_prefix(fonts_dir).unwrap_or(p).display().to_string();
+
+    for dup in find_duplicates(&records) {
+        let files = dup
+            .paths
+            .iter()
+            .map(|p| display(p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warning!(
+            "typst-bake: Warning: font face \"{} {}\" ({}) is embedded from {} files: {files}",
+            dup.key.family,
+            dup.key.subfamily,
+            dup.key.version,
+            dup.paths.len(),
+        );
+    }
+
+    let redundant = redundant_files(&records);
+    if !redundant.is_empty() {
+        let wasted: usize = redundant.iter().map(|(_, size)| size).sum();
+        let files = redundant
+            .iter()
+            .map(|(p, _)| display(p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warning!(
+            "typst-bake: Warning: font files only contain faces embedded from other files: \
+             {files} ({} wasted)",
+            crate::compression_cache::format_size(wasted),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(family: &str, path: &str, hash: u8, size: usize) -> FaceRecord {
+        FaceRecord {
+            key: FaceKey {
+                family: family.to_owned(),
+                subfamily: "Regular".to_owned(),
+                weight: 400,
+                italic: false,
+                version: "Version 4.0".to_owned(),
+            },
+            path: PathBuf::from(path),
+            hash: [hash; 32],
+            file_size: size,
+        }
+    }
+
+    #[test]
+    fn test_find_duplicates_reports_distinct_files() {
+        let records = [
+            record("Inter", "Inter-Regular.otf", 1, 300),
+            record("Inter", "Inter-Regular.ttf", 2, 200),
+            record("Source Serif 4", "SourceSerif4.ttf", 3, 100),
+        ];
+        let dups = find_duplicates(&records);
+        assert_eq!(dups.len(), 1);
+        assert_eq!(dups[0].key.family, "Inter");
+        assert_eq!(dups[0].paths.len(), 2);
+        assert_eq!(
+            redundant_files(&records),
+            [(PathBuf::from("Inter-Regular.ttf"), 200)]
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates_ignores_unique_faces() {
+        let records = [
+            record("Inter", "Inter.ttf", 1, 300),
+            record("JetBrains Mono", "JetBrainsMono.otf", 2, 200),
+        ];
+        assert!(find_duplicates(&records).is_empty());
+        assert!(redundant_files(&records).is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicates_ignores_identical_copies() {
+        let records = [
+            record("Inter", "a/Inter.ttf", 1, 300),
+            record("Inter", "b/Inter.ttf", 1, 300),
+        ];
+        assert!(find_duplicates(&records).is_empty());
+        assert!(redundant_files(&records).is_empty());
+    }
+
+    #[test]
+    fn test_collections_count_once_and_only_when_fully_duplicated() {
+        // One face of the collection is unique, so it is still needed.
+        let partial = [
+            record("Inter", "Inter.ttf", 1, 300),
+            record("Inter", "Shared.ttc", 2, 900),
+            record("Lora", "Shared.ttc", 2, 900),
+        ];
+        assert_eq!(find_duplicates(&partial).len(), 1);
+        assert!(redundant_files(&partial).is_empty());
+
+        // Every face is provided elsewhere: the collection is wasted, counted once.
+        let full = [
+            record("Inter", "Inter.ttf", 1, 300),
+            record("Lora", "Lora.ttf", 3, 200),
+            record("Inter", "Shared.ttc", 2, 900),
+            record("Lora", "Shared.ttc", 2, 900),
+        ];
+        assert_eq!(find_duplicates(&full).len(), 2);
+        assert_eq!(redundant_files(&full), [(PathBuf::from("Shared.ttc"), 900)]);
+    }
+}
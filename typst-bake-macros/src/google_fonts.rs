@@ -0,0 +1,344 @@
+//! Fetch static font files from Google Fonts by family name.
+//!
+//! Families listed under `google-fonts` in the typst-bake metadata are resolved through
+//! the Google Fonts CSS2 API. Requests are made with a non-browser user agent, for which
+//! the API serves plain TrueType files. Downloaded files are cached per family, weight,
+//! and style, so later builds work offline.
+
+use crate::log::{status, warning};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const CSS_API_URL: &str = "https://fonts.googleapis.com/css2";
+
+/// A requested face: `Inter:700` or `Inter:700i`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FaceSpec {
+    pub family: String,
+    pub weight: u16,
+    pub italic: bool,
+}
+
+impl FaceSpec {
+    /// File name used both in the cache and as the embedded font name.
+    pub fn file_name(&self) -> String {
+        let slug: String = self.family.chars().filter(|c| !c.is_whitespace()).collect();
+        let style = if self.italic { "Italic" } else { "" };
+        format!("{slug}-{}{style}.ttf", self.weight)
+    }
+}
+
+/// A font file resolved from Google Fonts.
+pub struct FetchedFont {
+    /// Embedded file name, e.g. `Inter-700.ttf`.
+    pub name: String,
+    /// Location of the cached file.
+    pub path: PathBuf,
+}
+
+/// Parse one `google-fonts` entry: `"Family"` or `"Family:400,700,400i"`.
+///
+/// Without weights, the regular (400) upright face is fetched.
+pub fn parse_spec(spec: &str) -> Result<Vec<FaceSpec>, String> {
+    let (family, styles) = match spec.split_once(':') {
+        Some((family, styles)) => (family.trim(), Some(styles)),
+        None => (spec.trim(), None),
+    };
+    if family.is_empty() {
+        return Err(format!(
+            "Invalid google-fonts entry \"{spec}\": missing family name"
+        ));
+    }
+
+    let Some(styles) = styles else {
+        return Ok(vec![FaceSpec {
+            family: family.to_owned(),
+            weight: 400,
+            italic: false,
+        }]);
+    };
+
+    let mut faces = styles
+        .split(',')
+        .map(|style| {
+            let style = style.trim();
+            let (weight, italic) = match style.strip_suffix('i') {
+                Some(weight) => (weight, true),
+                None => (style, false),
+            };
+            let weight = weight
+                .parse::<u16>()
+                .ok()
+                .filter(|w| (1..=1000).contains(w))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid google-fonts entry \"{spec}\": \"{style}\" is not a weight \
+                         (expected e.g. 400 or 700i)"
+                    )
+                })?;
+            Ok(FaceSpec {
+                family: family.to_owned(),
+                weight,
+                italic,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    faces.sort();
+    faces.dedup();
+    Ok(faces)
+}
+
+/// Build the CSS2 API URL requesting all `faces` of one family.
+fn css_url(family: &str, faces: &[&FaceSpec]) -> String {
+    let family = family.replace(' ', "+");
+    let mut tuples: Vec<String> = faces
+        .iter()
+        .map(|f| format!("{},{}", u8::from(f.italic), f.weight))
+        .collect();
+    tuples.sort();
+    tuples.dedup();
+    format!(
+        "{CSS_API_URL}?family={family}:ital,wght@{}",
+        tuples.join(";")
+    )
+}
+
+/// Extract `(weight, italic, url)` for every `@font-face` rule in a CSS2 response.
+fn parse_css(css: &str) -> Vec<(u16, bool, String)> {
+    css.split("@font-face")
+        .skip(1)
+        .filter_map(|block| {
+            let block = block.split('}').next()?;
+            let value = |prop: &str| {
+                let start = block.find(prop)? + prop.len();
+                let rest = block[start..].trim_start().strip_prefix(':')?;
+                Some(rest.split(';').next()?.trim())
+            };
+            let weight = value("font-weight")?.parse().ok()?;
+            let italic = value("font-style")? == "italic";
+            let src = value("src")?;
+            let url = src.split("url(").nth(1)?.split(')').next()?;
+            Some((weight, italic, url.trim_matches(['\'', '"']).to_owned()))
+        })
+        .collect()
+}
+
+/// Get the cache directory for Google Fonts downloads.
+fn cache_dir() -> Result<PathBuf, String> {
+    let dir = dirs::cache_dir()
+        .ok_or("Could not determine system cache directory".to_owned())?
+        .join("typst-bake")
+        .join("google-fonts");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache directory: {e}"))?;
+    Ok(dir)
+}
+
+/// Resolve all configured Google Fonts entries to cached font files.
+///
+/// Faces already in the cache are used as-is unless `refresh` is set; the rest of each
+/// family is fetched with a single CSS request.
+pub fn fetch(specs: &[String], refresh: bool) -> Result<Vec<FetchedFont>, String> {
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut faces = Vec::new();
+    for spec in specs {
+        faces.extend(parse_spec(spec)?);
+    }
+    faces.sort();
+    faces.dedup();
+
+    let cache = cache_dir()?;
+    let mut fetched = Vec::new();
+    let mut failed = Vec::new();
+
+    let mut families: Vec<&str> = faces.iter().map(|f| f.family.as_str()).collect();
+    families.dedup();
+
+    for family in families {
+        let family_faces: Vec<&FaceSpec> = faces.iter().filter(|f| f.family == family).collect();
+        if let Err(e) = fetch_family(family, &family_faces, &cache, refresh) {
+            warning!("  ✗ Failed: {family}: {e}");
+            failed.push(format!("{family}: {e}"));
+            continue;
+        }
+        for face in family_faces {
+            let name = face.file_name();
+            let path = cache.join(&name);
+            if path.exists() {
+                fetched.push(FetchedFont { name, path });
+            } else {
+                failed.push(format!(
+                    "{family}: weight {}{} is not available",
+                    face.weight,
+                    if face.italic { " italic" } else { "" }
+                ));
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(format!(
+            "Failed to fetch {} Google Fonts face(s):\n  - {}",
+            failed.len(),
+            failed.join("\n  - ")
+        ));
+    }
+
+    Ok(fetched)
+}
+
+/// Download the missing faces of one family into `cache`.
+///
+/// Uses a per-family file lock so parallel builds don't download the same files twice.
+fn fetch_family(
+    family: &str,
+    faces: &[&FaceSpec],
+    cache: &Path,
+    refresh: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let missing = |faces: &[&FaceSpec]| -> Vec<FaceSpec> {
+        faces
+            .iter()
+            .filter(|f| refresh || !cache.join(f.file_name()).exists())
+            .map(|&f| f.clone())
+            .collect()
+    };
+    if missing(faces).is_empty() {
+        status!("  Cached: {family}");
+        return Ok(());
+    }
+
+    let lock_path = cache.join(format!("{}.lock", family.replace(' ', "")));
+    let mut lock = fd_lock::RwLock::new(fs::File::create(&lock_path)?);
+    let _guard = lock.write()?;
+
+    // After acquiring the lock: another process may have fetched the files meanwhile.
+    let missing = missing(faces);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    status!("  Downloading: {family}");
+    let missing_refs: Vec<&FaceSpec> = missing.iter().collect();
+    let css = http_get(&css_url(family, &missing_refs))?;
+    let css = String::from_utf8(css)?;
+
+    for (weight, italic, url) in parse_css(&css) {
+        let Some(face) = missing
+            .iter()
+            .find(|f| f.weight == weight && f.italic == italic)
+        else {
+            continue;
+        };
+        let bytes = http_get(&url)?;
+        if ttf_parser::Face::parse(&bytes, 0).is_err() {
+            return Err(format!("{url} did not return a TrueType font").into());
+        }
+
+        // Atomic write: write to a PID-stamped temp file, then rename.
+        let dest = cache.join(face.file_name());
+        let tmp = cache.join(format!(".{}.tmp.{}", face.file_name(), std::process::id()));
+        fs::write(&tmp, &bytes)?;
+        fs::rename(&tmp, &dest)?;
+    }
+    status!("  ✓ {family}");
+
+    Ok(())
+}
+
+/// Fetch a URL and return the response body.
+fn http_get(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let response = ureq::get(url).call()?;
+    let (_, body) = response.into_parts();
+    let mut bytes = Vec::new();
+    body.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec_defaults_to_regular() {
+        let faces = parse_spec("Noto Sans KR").unwrap();
+        assert_eq!(
+            faces,
+            vec![FaceSpec {
+                family: "Noto Sans KR".to_owned(),
+                weight: 400,
+                italic: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_weights_and_italics() {
+        let faces = parse_spec("Inter:700, 400,400i").unwrap();
+        let styles: Vec<_> = faces.iter().map(|f| (f.weight, f.italic)).collect();
+        assert_eq!(styles, vec![(400, false), (400, true), (700, false)]);
+    }
+
+    #[test]
+    fn test_parse_spec_invalid() {
+        assert!(parse_spec("Inter:bold").is_err());
+        assert!(parse_spec(":400").is_err());
+    }
+
+    #[test]
+    fn test_file_name() {
+        let face = FaceSpec {
+            family: "Noto Sans KR".to_owned(),
+            weight: 700,
+            italic: true,
+        };
+        assert_eq!(face.file_name(), "NotoSansKR-700Italic.ttf");
+    }
+
+    #[test]
+    fn test_css_url() {
+        let faces = parse_spec("Noto Sans:700,400i").unwrap();
+        let refs: Vec<_> = faces.iter().collect();
+        assert_eq!(
+            css_url("Noto Sans", &refs),
+            "https://fonts.googleapis.com/css2?family=Noto+Sans:ital,wght@0,700;1,400"
+        );
+    }
+
+    #[test]
+    fn test_parse_css() {
+        let css = r#"
+/* latin */
+@font-face {
+  font-family: 'Inter';
+  font-style: normal;
+  font-weight: 700;
+  src: url(https://fonts.gstatic.com/s/inter/v18/bold.ttf) format('truetype');
+}
+@font-face {
+  font-family: 'Inter';
+  font-style: italic;
+  font-weight: 400;
+  src: url(https://fonts.gstatic.com/s/inter/v18/italic.ttf) format('truetype');
+}
+"#;
+        assert_eq!(
+            parse_css(css),
+            vec![
+                (
+                    700,
+                    false,
+                    "https://fonts.gstatic.com/s/inter/v18/bold.ttf".to_owned()
+                ),
+                (
+                    400,
+                    true,
+                    "https://fonts.gstatic.com/s/inter/v18/italic.ttf".to_owned()
+                ),
+            ]
+        );
+    }
+}
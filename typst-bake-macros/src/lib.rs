@@ -6,20 +6,29 @@
 
 mod compression_cache;
 mod config;
+mod derive_fromval;
 mod derive_intoval;
 mod dir_embed;
 mod downloader;
+mod font_check;
+mod google_fonts;
+mod lint;
+mod log;
 mod scanner;
+mod stub;
+mod unused;
 
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, LitStr};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, LitStr, Token};
 
 use compression_cache::CompressionCache;
 use dir_embed::DirEmbedResult;
+use log::{status, warning};
 
 use scanner::ResolvedPackage;
 
@@ -41,57 +50,346 @@ struct EmbeddedPackages {
     total_original: usize,
     total_compressed: usize,
     namespace_entries: Vec<proc_macro2::TokenStream>,
+    /// `(spec/path, hash)` of every package file.
+    content_hashes: Vec<(String, String)>,
+    /// Files that could not be embedded, prefixed with the package spec.
+    errors: Vec<String>,
 }
 
-/// Resolve template_dir, fonts_dir and validate the entry file exists.
+/// Resolved macro configuration.
+struct MacroConfig {
+    /// Directories merged into the virtual root: every entry of `template-dirs`, or the
+    /// single `root-dir`, or `template-dir` if unset.
+    root_dirs: Vec<PathBuf>,
+    /// Entry path relative to the virtual root.
+    entry_path: String,
+    /// Subdirectories of the root holding template sets; empty unless `template-sets`.
+    template_sets: Vec<String>,
+    /// `template-dir` relative to the root: the files the unused-file check reports.
+    /// Empty unless `root-dir` is set.
+    template_scope: PathBuf,
+    /// The set selected until `with_template_set` picks another; `Some` exactly when
+    /// `template-sets` is on.
+    default_template_set: Option<String>,
+    /// `None` when fonts come only from `google-fonts`.
+    fonts_dir: Option<PathBuf>,
+    google_fonts: Vec<String>,
+    /// `(alias, family)` pairs applied at runtime.
+    font_aliases: Vec<(String, String)>,
+    unused_files: config::LintLevel,
+    lint: config::LintLevel,
+}
+
+/// Resolve root_dirs, fonts_dir, google-fonts and validate the entry file exists.
 fn resolve_config(
     entry: &LitStr,
     entry_value: &str,
-) -> Result<(PathBuf, PathBuf), proc_macro2::TokenStream> {
-    let template_dir = config::get_template_dir()
+) -> Result<MacroConfig, proc_macro2::TokenStream> {
+    let (template_dirs, merged) = config::get_template_dirs()
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+    // With several directories, the entry may live in any of them.
+    let template_dir = template_dirs
+        .iter()
+        .find(|dir| dir.join(entry_value).exists())
+        .unwrap_or(&template_dirs[0])
+        .clone();
+
+    let sets_enabled = config::get_template_sets()
         .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+    let template_sets = if sets_enabled {
+        if merged {
+            return Err(syn::Error::new_spanned(
+                entry,
+                "template-sets cannot be combined with template-dirs",
+            )
+            .to_compile_error());
+        }
+        list_template_sets(&template_dir)
+    } else {
+        Vec::new()
+    };
 
     let entry_path = template_dir.join(entry_value);
-    if !entry_path.exists() {
+    if template_sets.is_empty() && !entry_path.exists() {
+        let message = if merged {
+            let dirs = template_dirs
+                .iter()
+                .map(|dir| format!("  - {}", dir.display()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("Entry file {entry_value} not found in any template directory:\n{dirs}")
+        } else {
+            format!("Entry file not found: {}", entry_path.display())
+        };
+        return Err(syn::Error::new_spanned(entry, message).to_compile_error());
+    }
+    let missing: Vec<_> = template_sets
+        .iter()
+        .map(|set| template_dir.join(set).join(entry_value))
+        .filter(|path| !path.exists())
+        .map(|path| format!("  - {}", path.display()))
+        .collect();
+    if !missing.is_empty() {
         return Err(syn::Error::new_spanned(
             entry,
-            format!("Entry file not found: {}", entry_path.display()),
+            format!(
+                "Entry file missing from {} template set(s):\n{}",
+                missing.len(),
+                missing.join("\n")
+            ),
         )
         .to_compile_error());
     }
 
-    let fonts_dir = config::get_fonts_dir()
+    // Without a default, `document!` would compile and then fail at runtime.
+    let default_template_set = config::get_default_template_set()
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+    match &default_template_set {
+        None if sets_enabled => {
+            return Err(syn::Error::new_spanned(
+                entry,
+                "template-sets requires default-template-set, naming the set used until \
+                 with_template_set() picks another",
+            )
+            .to_compile_error());
+        }
+        Some(_) if !sets_enabled => {
+            return Err(syn::Error::new_spanned(
+                entry,
+                "default-template-set requires template-sets = true",
+            )
+            .to_compile_error());
+        }
+        Some(name) if !template_sets.contains(name) => {
+            return Err(syn::Error::new_spanned(
+                entry,
+                format!(
+                    "default-template-set {name:?} is not a subdirectory of {}",
+                    template_dir.display()
+                ),
+            )
+            .to_compile_error());
+        }
+        _ => {}
+    }
+
+    let root_dir = config::get_root_dir(&template_dir)
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+    if !template_sets.is_empty() && root_dir != template_dir {
+        return Err(syn::Error::new_spanned(
+            entry,
+            "template-sets cannot be combined with root-dir",
+        )
+        .to_compile_error());
+    }
+    if merged && root_dir != template_dir {
+        return Err(syn::Error::new_spanned(
+            entry,
+            "root-dir cannot be combined with template-dirs",
+        )
+        .to_compile_error());
+    }
+    let template_scope = template_dir
+        .canonicalize()
+        .ok()
+        .zip(root_dir.canonicalize().ok())
+        .and_then(|(dir, root)| Some(dir.strip_prefix(root).ok()?.to_path_buf()))
+        .unwrap_or_default();
+
+    // Relative to the root, with forward slashes, as the runtime looks files up.
+    let entry_path = entry_path
+        .canonicalize()
+        .ok()
+        .zip(root_dir.canonicalize().ok())
+        .and_then(|(entry, root)| {
+            let rel = entry.strip_prefix(root).ok()?;
+            Some(rel.to_string_lossy().replace('\\', "/"))
+        })
+        .unwrap_or_else(|| entry_value.to_owned());
+
+    let google_fonts = config::get_google_fonts()
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+
+    let fonts_dir = config::get_fonts_dir(!google_fonts.is_empty())
         .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
 
-    Ok((template_dir, fonts_dir))
+    let font_aliases = config::get_font_aliases()
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+
+    let unused_files = config::get_unused_files_level()
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+
+    let lint = config::get_template_lint_level()
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+
+    let root_dirs = if merged {
+        template_dirs
+    } else {
+        vec![root_dir]
+    };
+
+    Ok(MacroConfig {
+        root_dirs,
+        entry_path,
+        template_sets,
+        template_scope,
+        default_template_set,
+        fonts_dir,
+        google_fonts,
+        font_aliases,
+        unused_files,
+        lint,
+    })
+}
+
+/// Names of the non-hidden subdirectories of `template_dir`, sorted.
+fn list_template_sets(template_dir: &Path) -> Vec<String> {
+    let Ok(read_dir) = std::fs::read_dir(template_dir) else {
+        return Vec::new();
+    };
+    let mut sets: Vec<String> = read_dir
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_dir() && !config::is_hidden(&e.path()))
+        .filter_map(|e| e.file_name().to_str().map(str::to_owned))
+        .collect();
+    sets.sort();
+    sets
 }
 
-/// Scan template directory for package imports and resolve them.
+/// Scan template directories for package imports and resolve them.
 fn resolve_and_download_packages(
     entry: &LitStr,
-    template_dir: &Path,
+    template_dirs: &[PathBuf],
 ) -> Result<ResolvedPackages, proc_macro2::TokenStream> {
-    eprintln!("typst-bake: Scanning for package imports...");
-    let packages = scanner::extract_packages(template_dir);
+    status!("typst-bake: Scanning for package imports...");
+    let mut packages = Vec::new();
+    for spec in template_dirs
+        .iter()
+        .flat_map(|dir| scanner::extract_packages(dir))
+    {
+        if !packages.contains(&spec) {
+            packages.push(spec);
+        }
+    }
 
     let data_dir = downloader::get_data_dir();
     let cache_dir = downloader::get_cache_dir()
         .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
 
     let resolved_packages = if !packages.is_empty() {
-        eprintln!("typst-bake: Found {} package(s) to bundle", packages.len());
+        status!("typst-bake: Found {} package(s) to bundle", packages.len());
 
         let refresh = config::should_refresh_cache();
         downloader::resolve_packages(&packages, data_dir.as_deref(), &cache_dir, refresh)
             .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?
     } else {
-        eprintln!("typst-bake: No packages found");
+        status!("typst-bake: No packages found");
         Vec::new()
     };
 
     Ok(resolved_packages)
 }
 
+/// Report embedded template files the entry never references, per the configured level.
+fn check_unused_files(
+    entry: &LitStr,
+    template_dirs: &[PathBuf],
+    template_sets: &[String],
+    template_scope: &Path,
+    entry_value: &str,
+    level: config::LintLevel,
+) -> Result<(), proc_macro2::TokenStream> {
+    if level == config::LintLevel::Allow {
+        return Ok(());
+    }
+    let unused: Vec<PathBuf> = if template_sets.is_empty() {
+        unused::find_unused(template_dirs, entry_value, template_scope)
+    } else {
+        // Each set is its own root, so check reachability within each. Template sets
+        // always come from a single directory.
+        template_sets
+            .iter()
+            .flat_map(|set| {
+                unused::find_unused(&[template_dirs[0].join(set)], entry_value, Path::new(""))
+                    .into_iter()
+                    .map(move |path| Path::new(set).join(path))
+            })
+            .collect()
+    };
+    if unused.is_empty() {
+        return Ok(());
+    }
+
+    let list = unused
+        .iter()
+        .map(|p| format!("  - {}", p.display()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = format!(
+        "{} embedded template file(s) not referenced from {entry_value}:\n{list}\n\n\
+         Remove them, or set unused-files = \"allow\" in [package.metadata.typst-bake] \
+         if they are used through computed paths, other entries, or read_template().",
+        unused.len()
+    );
+
+    if level == config::LintLevel::Deny {
+        return Err(syn::Error::new_spanned(entry, message).to_compile_error());
+    }
+    warning!("typst-bake: Warning: {message}");
+    Ok(())
+}
+
+/// Run the template lints, per the configured level.
+fn check_lints(
+    entry: &LitStr,
+    template_dirs: &[PathBuf],
+    template_sets: &[String],
+    level: config::LintLevel,
+) -> Result<(), proc_macro2::TokenStream> {
+    if level == config::LintLevel::Allow {
+        return Ok(());
+    }
+    let package_dirs: Vec<PathBuf> = downloader::get_data_dir()
+        .into_iter()
+        .chain(downloader::get_cache_dir().ok())
+        .collect();
+    let findings = lint::lint_templates(template_dirs, template_sets, &package_dirs);
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "{} template lint finding(s):\n  - {}\n\n\
+         Set lint = \"allow\" in [package.metadata.typst-bake] to turn these checks off.",
+        findings.len(),
+        findings.join("\n  - ")
+    );
+    if level == config::LintLevel::Deny {
+        return Err(syn::Error::new_spanned(entry, message).to_compile_error());
+    }
+    warning!("typst-bake: Warning: {message}");
+    Ok(())
+}
+
+/// Fetch the configured Google Fonts families, returning `(name, path)` pairs to embed.
+fn fetch_google_fonts(
+    entry: &LitStr,
+    specs: &[String],
+) -> Result<Vec<(String, PathBuf)>, proc_macro2::TokenStream> {
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    status!(
+        "typst-bake: Fetching {} Google Fonts family(ies)",
+        specs.len()
+    );
+    let fonts = google_fonts::fetch(specs, config::should_refresh_cache())
+        .map_err(|e| syn::Error::new_spanned(entry, e).to_compile_error())?;
+
+    Ok(fonts.into_iter().map(|f| (f.name, f.path)).collect())
+}
+
 /// Generate a `DirEntry::Dir` token wrapping children under a given name.
 fn dir_entry_token(name: &str, children: &[proc_macro2::TokenStream]) -> proc_macro2::TokenStream {
     quote! {
@@ -110,6 +408,8 @@ fn embed_packages(
     let mut pkg_total_original = 0;
     let mut pkg_total_compressed = 0;
     let mut namespace_entries = Vec::new();
+    let mut content_hashes = Vec::new();
+    let mut errors = Vec::new();
 
     // Group resolved packages into a sorted tree: namespace -> name -> (version -> path)
     let mut pkg_tree: BTreeMap<&str, BTreeMap<&str, BTreeMap<&str, &Path>>> = BTreeMap::new();
@@ -133,13 +433,20 @@ fn embed_packages(
                 let pkg_name = format!("@{namespace}/{name}:{version}");
 
                 package_infos.push(MacroPackageInfo {
-                    name: pkg_name,
+                    name: pkg_name.clone(),
                     original_size: pkg_result.original_size,
                     compressed_size: pkg_result.compressed_size,
                     file_count: pkg_result.file_count,
                 });
                 pkg_total_original += pkg_result.original_size;
                 pkg_total_compressed += pkg_result.compressed_size;
+                content_hashes.extend(
+                    pkg_result
+                        .content_hashes
+                        .iter()
+                        .map(|(path, hash)| (format!("{pkg_name}/{path}"), hash.clone())),
+                );
+                errors.extend(pkg_result.errors.iter().map(|e| format!("{pkg_name}: {e}")));
 
                 version_entries.push(dir_entry_token(version, &pkg_result.entries));
             }
@@ -155,15 +462,72 @@ fn embed_packages(
         total_original: pkg_total_original,
         total_compressed: pkg_total_compressed,
         namespace_entries,
+        content_hashes,
+        errors,
     }
 }
 
+/// Turn embedding failures from all sources into a single compile error, so one build
+/// shows every file that needs attention.
+fn check_embed_errors(
+    entry: &LitStr,
+    templates_result: &DirEmbedResult,
+    fonts_result: &DirEmbedResult,
+    embedded_packages: &EmbeddedPackages,
+) -> Result<(), proc_macro2::TokenStream> {
+    let errors: Vec<&String> = templates_result
+        .errors
+        .iter()
+        .chain(&fonts_result.errors)
+        .chain(&embedded_packages.errors)
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let list = errors
+        .iter()
+        .map(|e| format!("  - {e}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = format!("Failed to embed {} file(s):\n{list}", errors.len());
+    Err(syn::Error::new_spanned(entry, message).to_compile_error())
+}
+
+/// Hash of the entry path and every embedded file's path and content, used as the
+/// build-time part of `Document::fingerprint`.
+fn content_hash(
+    entry_value: &str,
+    templates_result: &DirEmbedResult,
+    fonts_result: &DirEmbedResult,
+    packages: &EmbeddedPackages,
+) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(entry_value.as_bytes());
+    for (category, hashes) in [
+        ("templates", &templates_result.content_hashes),
+        ("fonts", &fonts_result.content_hashes),
+        ("packages", &packages.content_hashes),
+    ] {
+        hasher.update(b"\0");
+        hasher.update(category.as_bytes());
+        for (path, hash) in hashes {
+            hasher.update(b"\0");
+            hasher.update(path.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(hash.as_bytes());
+        }
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
 /// Generate the final output `TokenStream` from embedded results and stats.
 fn generate_output(
     entry_value: &str,
     templates_result: &DirEmbedResult,
     fonts_result: &DirEmbedResult,
     packages: &EmbeddedPackages,
+    font_aliases: &[(String, String)],
     cache: &mut CompressionCache,
     compression_level: i32,
 ) -> proc_macro2::TokenStream {
@@ -176,6 +540,7 @@ fn generate_output(
     let dedup_duplicate_count = dedup.duplicate_count;
     let dedup_saved_bytes = dedup.saved_bytes;
     let dedup_statics = cache.dedup_statics();
+    let content_hash = content_hash(entry_value, templates_result, fonts_result, packages);
 
     let templates_code = templates_result.to_dir_code("");
     let fonts_code = fonts_result.to_dir_code("");
@@ -214,6 +579,9 @@ fn generate_output(
         })
         .collect();
 
+    let alias_names = font_aliases.iter().map(|(alias, _)| alias);
+    let alias_families = font_aliases.iter().map(|(_, family)| family);
+
     quote! {
         {
             use ::typst_bake::__internal::{Dir, Document};
@@ -249,49 +617,138 @@ fn generate_output(
                 compression_level: #compression_level,
             };
 
-            Document::__new(&TEMPLATES, &PACKAGES, &FONTS, #entry_value, stats)
+            Document::__new(&TEMPLATES, &PACKAGES, &FONTS, #entry_value, stats, #content_hash)
+                #(.with_font_alias(#alias_names, #alias_families))*
         }
     }
 }
 
+/// Macro arguments: `"main.typ"` or `"main.typ", inputs = Type`.
+struct DocumentArgs {
+    entry: LitStr,
+    /// Input type the document must be given before rendering.
+    inputs: Option<syn::Type>,
+}
+
+impl Parse for DocumentArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let entry = input.parse()?;
+        let mut inputs = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            if key != "inputs" {
+                return Err(syn::Error::new_spanned(key, "expected `inputs = Type`"));
+            }
+            input.parse::<Token![=]>()?;
+            inputs = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(Self { entry, inputs })
+    }
+}
+
 #[proc_macro]
 pub fn document(input: TokenStream) -> TokenStream {
-    let entry = parse_macro_input!(input as LitStr);
+    let DocumentArgs { entry, inputs } = parse_macro_input!(input as DocumentArgs);
+    let document = expand_document(&entry);
+    match inputs {
+        Some(ty) => quote! { #document.require_inputs::<#ty>() },
+        None => document,
+    }
+    .into()
+}
+
+/// Expand `document!` for `entry` into a `Document` expression.
+fn expand_document(entry: &LitStr) -> proc_macro2::TokenStream {
     let entry_value = entry.value();
 
-    let (template_dir, fonts_dir) = match resolve_config(&entry, &entry_value) {
+    if stub::stub_reason().is_some() {
+        return stub::generate_stub(&entry_value);
+    }
+
+    let MacroConfig {
+        root_dirs,
+        entry_path,
+        template_sets,
+        template_scope,
+        default_template_set,
+        fonts_dir,
+        google_fonts,
+        font_aliases,
+        unused_files,
+        lint,
+    } = match resolve_config(entry, &entry_value) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    if let Err(e) = check_unused_files(
+        entry,
+        &root_dirs,
+        &template_sets,
+        &template_scope,
+        &entry_path,
+        unused_files,
+    ) {
+        return e;
+    }
+
+    if let Err(e) = check_lints(entry, &root_dirs, &template_sets, lint) {
+        return e;
+    }
+
+    let resolved_packages = match resolve_and_download_packages(entry, &root_dirs) {
         Ok(v) => v,
-        Err(e) => return e.into(),
+        Err(e) => return e,
     };
 
-    let resolved_packages = match resolve_and_download_packages(&entry, &template_dir) {
+    let google_fonts = match fetch_google_fonts(entry, &google_fonts) {
         Ok(v) => v,
-        Err(e) => return e.into(),
+        Err(e) => return e,
     };
 
     let compression_level = config::get_compression_level();
     let compression_cache_dir = config::get_compression_cache_dir()
-        .map_err(|e| eprintln!("typst-bake: Compression cache disabled: {e}"))
+        .map_err(|e| warning!("typst-bake: Compression cache disabled: {e}"))
         .ok();
     let mut cache = CompressionCache::new(compression_cache_dir, compression_level);
 
-    let templates_result = dir_embed::embed_dir(&template_dir, &mut cache);
-    let fonts_result = dir_embed::embed_fonts_dir(&fonts_dir, &mut cache);
+    let templates_result = dir_embed::embed_dirs(&root_dirs, &mut cache);
+    let mut fonts_result = match &fonts_dir {
+        Some(fonts_dir) => {
+            let result = dir_embed::embed_fonts_dir(fonts_dir, &mut cache);
+            font_check::warn_duplicate_faces(fonts_dir);
+            result
+        }
+        None => DirEmbedResult::default(),
+    };
+    fonts_result.extend(dir_embed::embed_files(&google_fonts, &mut cache));
 
     let embedded_packages = embed_packages(&resolved_packages, &mut cache);
 
-    generate_output(
-        &entry_value,
+    if let Err(e) = check_embed_errors(entry, &templates_result, &fonts_result, &embedded_packages)
+    {
+        return e;
+    }
+
+    let document = generate_output(
+        &entry_path,
         &templates_result,
         &fonts_result,
         &embedded_packages,
+        &font_aliases,
         &mut cache,
         compression_level,
-    )
-    .into()
+    );
+    match default_template_set {
+        Some(default) => quote! {
+            #document.__with_template_sets(&[#(#template_sets),*], #default)
+        },
+        None => document,
+    }
 }
 
-#[proc_macro_derive(IntoValue)]
+#[proc_macro_derive(IntoValue, attributes(typst))]
 pub fn derive_into_value(item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as syn::DeriveInput);
     derive_intoval::derive_into_value(item)
@@ -299,10 +756,26 @@ pub fn derive_into_value(item: TokenStream) -> TokenStream {
         .into()
 }
 
-#[proc_macro_derive(IntoDict)]
+#[proc_macro_derive(IntoDict, attributes(typst))]
 pub fn derive_into_dict(item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as syn::DeriveInput);
     derive_intoval::derive_into_dict(item)
         .unwrap_or_else(|err| err.to_compile_error())
         .into()
 }
+
+#[proc_macro_derive(FromValue, attributes(typst))]
+pub fn derive_from_value(item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as syn::DeriveInput);
+    derive_fromval::derive_from_value(item)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(FromDict, attributes(typst))]
+pub fn derive_from_dict(item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as syn::DeriveInput);
+    derive_fromval::derive_from_dict(item)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
@@ -0,0 +1,367 @@
+//! Optional lints for embedded templates, run at build time.
+//!
+//! Enabled with `lint = "warn"` or `"deny"`. Each `.typ` file in the template directory
+//! is checked for:
+//!
+//! - names imported from a module but never used in the file;
+//! - package imports for which a newer version is installed or cached locally;
+//! - literal paths passed to `image`, `read`, `json`, ... (and imports and includes) that
+//!   don't name an embedded file, e.g. missing or hidden files;
+//! - string and raw literals over [`LARGE_INLINE_BYTES`], which are better kept in a file.
+
+use crate::config::is_hidden;
+use crate::scanner::{parse_package_specifier, PackageSpec};
+use crate::unused::normalize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use typst_syntax::ast::{self, Arg, Expr};
+use typst_syntax::{LinkedNode, Source, SyntaxKind, SyntaxNode};
+use walkdir::WalkDir;
+
+/// Inline string or raw literals larger than this are reported.
+pub const LARGE_INLINE_BYTES: usize = 16 * 1024;
+
+/// Functions whose first positional argument is a path into the template directory.
+const PATH_FUNCTIONS: &[&str] = &[
+    "image",
+    "read",
+    "json",
+    "csv",
+    "yaml",
+    "toml",
+    "xml",
+    "cbor",
+    "plugin",
+    "bibliography",
+];
+
+/// Lint every `.typ` file in `template_dirs`, which together form the virtual root.
+///
+/// With `template_sets`, each set is a root of its own, as at runtime: `/` paths and `..`
+/// resolve within the set, and files outside every set are skipped. `package_dirs` are
+/// searched for newer versions of imported packages. Returns one message per finding,
+/// prefixed with `file:line`, in file order.
+pub fn lint_templates(
+    template_dirs: &[PathBuf],
+    template_sets: &[String],
+    package_dirs: &[PathBuf],
+) -> Vec<String> {
+    // `(directories forming one root, their path relative to the template directory)`
+    let roots: Vec<(Vec<PathBuf>, PathBuf)> = if template_sets.is_empty() {
+        vec![(template_dirs.to_vec(), PathBuf::new())]
+    } else {
+        template_dirs
+            .iter()
+            .flat_map(|dir| {
+                template_sets
+                    .iter()
+                    .map(move |set| (vec![dir.join(set)], PathBuf::from(set)))
+            })
+            .collect()
+    };
+
+    let mut findings = Vec::new();
+    for (root_dirs, prefix) in &roots {
+        for root_dir in root_dirs {
+            for entry in WalkDir::new(root_dir)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !is_hidden(e.path()))
+                .filter_map(Result::ok)
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "typ"))
+            {
+                let Ok(content) = fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                let Ok(rel) = entry.path().strip_prefix(root_dir) else {
+                    continue;
+                };
+                for (offset, message) in lint_source(root_dirs, rel, &content, package_dirs) {
+                    let line = content[..offset].matches('\n').count() + 1;
+                    findings.push(format!("{}:{line}: {message}", prefix.join(rel).display()));
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Lint one file, given by its path relative to the virtual root. Returns
+/// `(byte offset, message)` pairs.
+fn lint_source(
+    template_dirs: &[PathBuf],
+    file: &Path,
+    content: &str,
+    package_dirs: &[PathBuf],
+) -> Vec<(usize, String)> {
+    let source = Source::detached(content);
+    let root = LinkedNode::new(source.root());
+
+    let mut used = BTreeSet::new();
+    collect_used_names(source.root(), &mut used);
+
+    let mut findings = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let offset = node.offset();
+
+        if matches!(node.kind(), SyntaxKind::Str | SyntaxKind::Raw)
+            && node.len() > LARGE_INLINE_BYTES
+        {
+            findings.push((
+                offset,
+                format!(
+                    "large inline literal ({} KiB); consider moving it to a file and using read()",
+                    node.len() / 1024
+                ),
+            ));
+        }
+
+        if let Some(import) = node.cast::<ast::ModuleImport>() {
+            for name in imported_names(node.get()) {
+                if !used.contains(&name) {
+                    findings.push((offset, format!("unused import `{name}`")));
+                }
+            }
+            if let Expr::Str(path) = import.source() {
+                let path = path.get();
+                match parse_package_specifier(&path) {
+                    Some(spec) => {
+                        findings.extend(newer_version(&spec, package_dirs).map(|newer| {
+                            (offset, format!("{spec} imported, but {newer} is available"))
+                        }))
+                    }
+                    None => findings.extend(missing_file(template_dirs, file, &path, offset)),
+                }
+            }
+        } else if let Some(include) = node.cast::<ast::ModuleInclude>() {
+            if let Expr::Str(path) = include.source() {
+                findings.extend(missing_file(template_dirs, file, &path.get(), offset));
+            }
+        } else if let Some(call) = node.cast::<ast::FuncCall>() {
+            let is_path_function = matches!(
+                call.callee(),
+                Expr::Ident(ident) if PATH_FUNCTIONS.contains(&ident.get().as_str())
+            );
+            let first = call.args().items().find_map(|arg| match arg {
+                Arg::Pos(expr) => Some(expr),
+                _ => None,
+            });
+            if let (true, Some(Expr::Str(path))) = (is_path_function, first) {
+                findings.extend(missing_file(template_dirs, file, &path.get(), offset));
+            }
+        }
+
+        stack.extend(node.children());
+    }
+
+    findings.sort_by_key(|(offset, _)| *offset);
+    findings
+}
+
+/// Names bound by the item list of an import node, e.g. `a` and `c` for
+/// `#import "x.typ": a, b as c`.
+fn imported_names(import: &SyntaxNode) -> Vec<String> {
+    let Some(items) = import
+        .children()
+        .find(|c| c.kind() == SyntaxKind::ImportItems)
+    else {
+        return Vec::new();
+    };
+    items
+        .children()
+        .filter_map(|item| match item.kind() {
+            SyntaxKind::Ident => Some(item.text().to_string()),
+            // The bound name is the last identifier: `b` in `a.b`, `c` in `b as c`.
+            SyntaxKind::ImportItemPath | SyntaxKind::RenamedImportItem => item
+                .children()
+                .rfind(|c| c.kind() == SyntaxKind::Ident)
+                .map(|ident| ident.text().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Identifiers used anywhere outside import statements.
+fn collect_used_names(node: &SyntaxNode, used: &mut BTreeSet<String>) {
+    match node.kind() {
+        SyntaxKind::ModuleImport => return,
+        SyntaxKind::Ident | SyntaxKind::MathIdent => {
+            used.insert(node.text().to_string());
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        collect_used_names(child, used);
+    }
+}
+
+/// A finding if `literal`, referenced from `file`, does not name an embedded file in any
+/// of `template_dirs`.
+fn missing_file(
+    template_dirs: &[PathBuf],
+    file: &Path,
+    literal: &str,
+    offset: usize,
+) -> Option<(usize, String)> {
+    if literal.is_empty() || literal.starts_with('@') {
+        return None;
+    }
+    let target = match literal.strip_prefix('/') {
+        Some(rooted) => PathBuf::from(rooted),
+        None => file.parent().unwrap_or(Path::new("")).join(literal),
+    };
+    if escapes_root(&target) {
+        return Some((
+            offset,
+            format!("\"{literal}\" is outside the template directory, which is not embedded"),
+        ));
+    }
+    // Resolve `..` lexically, like Typst does: `sub/../logo.png` names `logo.png` even in
+    // a template directory without `sub/`.
+    let target = normalize(&target);
+
+    let hidden = target
+        .components()
+        .any(|c| matches!(c, Component::Normal(part) if part.to_string_lossy().starts_with('.')));
+    if hidden {
+        Some((
+            offset,
+            format!("\"{literal}\" is a hidden file, which is not embedded"),
+        ))
+    } else if !template_dirs.iter().any(|dir| dir.join(&target).is_file()) {
+        Some((
+            offset,
+            format!("\"{literal}\" does not exist in the template directory"),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Whether `path`, relative to the root, climbs above it with `..`.
+fn escapes_root(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir if depth == 0 => return true,
+            Component::ParentDir => depth -= 1,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The newest locally available version of `spec`'s package, if newer than `spec`.
+fn newer_version(spec: &PackageSpec, package_dirs: &[PathBuf]) -> Option<String> {
+    let current = parse_version(&spec.version)?;
+    package_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir.join(&spec.namespace).join(&spec.name)).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_owned))
+        .filter_map(|version| Some((parse_version(&version)?, version)))
+        .filter(|(parsed, _)| *parsed > current)
+        .max()
+        .map(|(_, version)| version)
+}
+
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(content: &str) -> Vec<String> {
+        lint_source(
+            &[PathBuf::from("/nonexistent")],
+            Path::new("main.typ"),
+            content,
+            &[],
+        )
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+    }
+
+    #[test]
+    fn test_unused_import() {
+        let content = "#import \"@preview/cetz:0.3.4\": canvas, draw as d\n#canvas({})";
+        assert_eq!(messages(content), ["unused import `d`"]);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert_eq!(
+            messages("#image(\"logo.png\", width: 2cm)"),
+            ["\"logo.png\" does not exist in the template directory"]
+        );
+        assert_eq!(
+            messages("#let data = read(\".secret/key.txt\")"),
+            ["\".secret/key.txt\" is a hidden file, which is not embedded"]
+        );
+    }
+
+    #[test]
+    fn test_missing_file_resolves_parent_dirs_lexically() {
+        let root = std::env::temp_dir().join(format!("typst-bake-lint-{}", std::process::id()));
+        let templates = root.join("templates");
+        let shared = root.join("shared");
+        fs::create_dir_all(templates.join("sub")).unwrap();
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("logo.png"), b"").unwrap();
+
+        // `logo.png` only exists in `shared`, which has no `sub/` to walk through.
+        let findings = lint_source(
+            &[templates, shared],
+            Path::new("sub/page.typ"),
+            "#image(\"../logo.png\")",
+            &[],
+        );
+        fs::remove_dir_all(&root).unwrap();
+        assert!(findings.is_empty(), "{findings:?}");
+    }
+
+    #[test]
+    fn test_missing_file_outside_the_root() {
+        assert_eq!(
+            messages("#read(\"../Cargo.toml\")"),
+            ["\"../Cargo.toml\" is outside the template directory, which is not embedded"]
+        );
+        assert!(escapes_root(Path::new("sub/../../x")));
+        assert!(!escapes_root(Path::new("sub/../x")));
+    }
+
+    #[test]
+    fn test_missing_file_in_template_sets() {
+        let root = std::env::temp_dir().join(format!("typst-bake-sets-{}", std::process::id()));
+        fs::create_dir_all(root.join("acme")).unwrap();
+        fs::write(root.join("acme/logo.png"), b"").unwrap();
+        fs::write(root.join("acme/main.typ"), "#image(\"/logo.png\")").unwrap();
+
+        // `/logo.png` resolves inside the set, not at the template directory.
+        let findings = lint_templates(&[root.clone()], &["acme".to_owned()], &[]);
+        fs::remove_dir_all(&root).unwrap();
+        assert!(findings.is_empty(), "{findings:?}");
+    }
+
+    #[test]
+    fn test_large_inline_literal() {
+        let content = format!("#let data = \"{}\"", "x".repeat(LARGE_INLINE_BYTES + 1));
+        assert_eq!(
+            messages(&content),
+            ["large inline literal (16 KiB); consider moving it to a file and using read()"]
+        );
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert!(parse_version("0.10.0") > parse_version("0.9.3"));
+        assert_eq!(parse_version("latest"), None);
+    }
+}
@@ -0,0 +1,47 @@
+//! Build output, filtered by the configured verbosity.
+//!
+//! The macro runs inside rustc, where the host application has no logger or subscriber
+//! installed, so output goes to stderr and is filtered by `TYPST_BAKE_LOG` or `log` in
+//! `[package.metadata.typst-bake]` instead.
+
+use crate::config;
+
+/// How much the macro prints during the build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Print nothing.
+    Quiet,
+    /// Print warnings only.
+    Warn,
+    /// Print warnings and progress lines (the default).
+    Info,
+}
+
+/// Whether messages at `level` are printed.
+///
+/// The configuration is looked up on every call rather than once per process: a
+/// long-lived proc-macro server (rust-analyzer) expands for several crates and outlives
+/// edits to their Cargo.toml.
+pub fn enabled(level: LogLevel) -> bool {
+    level <= config::get_log_level()
+}
+
+/// Print a progress line unless the log level is `warn` or `quiet`.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::LogLevel::Info) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Print a warning unless the log level is `quiet`.
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::LogLevel::Warn) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {status, warning};
@@ -1,5 +1,6 @@
 //! Scan .typ files and parse package imports.
 
+use crate::log::warning;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -126,7 +127,7 @@ pub fn extract_packages(dir: &Path) -> Vec<PackageSpec> {
                 }
                 Err(e) => {
                     // Log but don't fail - graceful degradation
-                    eprintln!("Warning: Failed to parse {}: {e}", entry.path().display());
+                    warning!("Warning: Failed to parse {}: {e}", entry.path().display());
                 }
             }
         }
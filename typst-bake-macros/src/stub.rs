@@ -0,0 +1,127 @@
+//! Lightweight expansion for IDE analysis.
+//!
+//! rust-analyzer re-expands `document!` on every edit. A full expansion scans, downloads,
+//! and compresses everything, which makes the IDE unresponsive, while analysis only needs
+//! the expression's type. The same holds for `cargo check` and `cargo clippy`, which never
+//! link the embedded data. In those contexts the macro expands to a stub `Document` with
+//! nothing embedded instead.
+//!
+//! The same stub serves documentation builds on docs.rs, which have neither the template
+//! and fonts directories of the crate being documented nor network access.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::env;
+
+/// Environment variable forcing the stub (`1`) or the full expansion (`0`).
+pub const STUB_ENV: &str = "TYPST_BAKE_STUB";
+
+/// Why the macro should expand to a stub, or `None` for a full expansion.
+pub fn stub_reason() -> Option<&'static str> {
+    match env::var(STUB_ENV).as_deref() {
+        Ok("0") => return None,
+        Ok(_) => return Some(STUB_ENV),
+        Err(_) => {}
+    }
+
+    if env::var_os("DOCS_RS").is_some() {
+        return Some("docs.rs");
+    }
+
+    // Set by rust-analyzer for the cargo invocations it runs itself.
+    if env::var_os("RUST_ANALYZER_INTERNALS_DO_NOT_USE").is_some() {
+        return Some("rust-analyzer");
+    }
+
+    if is_metadata_only(env::args()) {
+        return Some("cargo check");
+    }
+
+    // rust-analyzer expands proc macros in its own proc-macro server process.
+    let exe = env::current_exe().ok()?;
+    let name = exe.file_stem()?.to_string_lossy();
+    (name.contains("rust-analyzer") || name.contains("proc-macro-srv")).then_some("rust-analyzer")
+}
+
+/// Whether the arguments of the running rustc ask for metadata but no linkable output,
+/// as `cargo check` and `cargo clippy` do (`--emit=dep-info,metadata`). Builds, tests,
+/// and benches also emit `link`.
+fn is_metadata_only(args: impl IntoIterator<Item = String>) -> bool {
+    let mut kinds = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let emit = match arg.strip_prefix("--emit=") {
+            Some(emit) => emit.to_owned(),
+            None if arg == "--emit" => args.next().unwrap_or_default(),
+            None => continue,
+        };
+        // Each kind may name an output path, e.g. `metadata=/path/to/lib.rmeta`.
+        kinds.extend(
+            emit.split(',')
+                .map(|k| k.split('=').next().unwrap_or(k).to_owned()),
+        );
+    }
+    kinds.iter().any(|k| k == "metadata") && !kinds.iter().any(|k| k == "link")
+}
+
+/// Expand to a `Document` with empty templates, fonts, and packages.
+///
+/// The result type-checks like the real expansion; rendering it fails with
+/// `Error::EntryNotFound`.
+pub fn generate_stub(entry_value: &str) -> TokenStream {
+    quote! {
+        {
+            use ::typst_bake::__internal::{Dir, Document};
+
+            static EMPTY: Dir<'static> = Dir::new("", &[]);
+
+            let empty = ::typst_bake::CategoryStats {
+                original_size: 0,
+                compressed_size: 0,
+                file_count: 0,
+            };
+            let stats = ::typst_bake::EmbedStats {
+                templates: empty,
+                packages: ::typst_bake::PackageStats {
+                    packages: ::std::vec::Vec::new(),
+                    original_size: 0,
+                    compressed_size: 0,
+                },
+                fonts: empty,
+                dedup: ::typst_bake::DedupStats {
+                    total_files: 0,
+                    unique_blobs: 0,
+                    duplicate_count: 0,
+                    saved_bytes: 0,
+                },
+                compression_level: 0,
+            };
+
+            Document::__new(&EMPTY, &EMPTY, &EMPTY, #entry_value, stats, "")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_is_metadata_only() {
+        assert!(is_metadata_only(args(
+            "rustc --crate-name app --emit=dep-info,metadata"
+        )));
+        assert!(is_metadata_only(args(
+            "rustc --emit dep-info,metadata=/t/libapp.rmeta"
+        )));
+        assert!(!is_metadata_only(args(
+            "rustc --emit=dep-info,metadata,link"
+        )));
+        assert!(!is_metadata_only(args("rustc --emit=dep-info,link")));
+        assert!(!is_metadata_only(args("rust-analyzer-proc-macro-srv")));
+    }
+}
@@ -0,0 +1,217 @@
+//! Detection of embedded template files the entry never references.
+//!
+//! Starting from the entry file, every string literal in a `.typ` file is treated as a
+//! potential path (imports, includes, `image`, `read`, `json`, ...). Literals that name
+//! an embedded file mark it as used, and referenced `.typ` files are scanned in turn.
+//! Paths built at runtime (e.g. `"img/" + name`) can't be followed, so directories
+//! named by a literal (e.g. `"img/"`) count as used as a whole.
+
+use crate::config::is_hidden;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use typst_syntax::ast::Str;
+use typst_syntax::{Source, SyntaxKind, SyntaxNode};
+use walkdir::WalkDir;
+
+/// Template files (relative to the virtual root formed by `template_dirs`) under `scope`
+/// not reachable from `entry`, sorted.
+///
+/// References are followed through the whole root, but only files under `scope` are
+/// reported: with `root-dir`, the root also holds other documents' directories, which
+/// this entry is not expected to reference.
+pub fn find_unused(template_dirs: &[PathBuf], entry: &str, scope: &Path) -> Vec<PathBuf> {
+    // Relative path -> path on disk. Collisions between directories are reported when
+    // embedding; the first directory wins here.
+    let mut files: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+    for template_dir in template_dirs {
+        for file in WalkDir::new(template_dir)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !is_hidden(e.path()))
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+        {
+            if let Ok(rel) = file.path().strip_prefix(template_dir) {
+                files
+                    .entry(rel.to_path_buf())
+                    .or_insert_with(|| file.path().to_path_buf());
+            }
+        }
+    }
+
+    let mut used = BTreeSet::new();
+    let mut used_dirs = BTreeSet::new();
+    let mut queue = VecDeque::from([normalize(Path::new(entry))]);
+
+    while let Some(file) = queue.pop_front() {
+        let Some(disk_path) = files.get(&file) else {
+            continue;
+        };
+        if !used.insert(file.clone()) {
+            continue;
+        }
+        if file.extension().is_none_or(|ext| ext != "typ") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(disk_path) else {
+            continue;
+        };
+
+        let base = file.parent().unwrap_or(Path::new(""));
+        for literal in string_literals(&content) {
+            if literal.starts_with('@') || literal.is_empty() {
+                continue;
+            }
+            let target = match literal.strip_prefix('/') {
+                Some(rooted) => normalize(Path::new(rooted)),
+                None => normalize(&base.join(&literal)),
+            };
+            if target.as_os_str().is_empty() {
+                continue;
+            }
+            if files.contains_key(&target) {
+                queue.push_back(target);
+            } else if files.keys().any(|f| f.starts_with(&target)) {
+                used_dirs.insert(target);
+            }
+        }
+    }
+
+    files
+        .into_keys()
+        .filter(|f| f.starts_with(scope))
+        .filter(|f| !used.contains(f) && !used_dirs.iter().any(|d| f.starts_with(d)))
+        .collect()
+}
+
+/// All string literal values in a Typst source file.
+fn string_literals(content: &str) -> Vec<String> {
+    fn walk(node: &SyntaxNode, out: &mut Vec<String>) {
+        if node.kind() == SyntaxKind::Str {
+            out.extend(node.cast::<Str>().map(|s| s.get().to_string()));
+        }
+        for child in node.children() {
+            walk(child, out);
+        }
+    }
+
+    let source = Source::detached(content);
+    let mut out = Vec::new();
+    walk(source.root(), &mut out);
+    out
+}
+
+/// Resolve `.` and `..` components lexically. `..` above the root is dropped.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::ParentDir => {
+                out.pop();
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_literals() {
+        let content = r#"
+#import "lib/util.typ": helper
+#image("img/logo.png", width: 2cm)
+#let data = json("/data/" + "items.json")
+"#;
+        assert_eq!(
+            string_literals(content),
+            ["lib/util.typ", "img/logo.png", "/data/", "items.json"]
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("a/./b/../c.typ")), Path::new("a/c.typ"));
+        assert_eq!(normalize(Path::new("../x.typ")), Path::new("x.typ"));
+    }
+
+    #[test]
+    fn test_find_unused() {
+        let dir = std::env::temp_dir().join(format!("typst-bake-unused-{}", std::process::id()));
+        let write = |path: &str, content: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "main.typ",
+            "#include \"parts/intro.typ\"\n#let d = read(\"data/\" + \"a.txt\")",
+        );
+        write("parts/intro.typ", "#image(\"../img/logo.png\")");
+        write("img/logo.png", "");
+        write("img/old.png", "");
+        write("data/a.txt", "");
+        write("drafts/unused.typ", "#image(\"../img/old.png\")");
+
+        let unused = find_unused(&[dir.clone()], "main.typ", Path::new(""));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            unused,
+            [
+                PathBuf::from("drafts/unused.typ"),
+                PathBuf::from("img/old.png")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_unused_across_dirs() {
+        let dir =
+            std::env::temp_dir().join(format!("typst-bake-unused-dirs-{}", std::process::id()));
+        let write = |path: &str, content: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("templates/main.typ", "#import \"/partials/header.typ\"");
+        write(
+            "shared/partials/header.typ",
+            "#image(\"../assets/logo.png\")",
+        );
+        write("shared/assets/logo.png", "");
+        write("shared/assets/old.png", "");
+
+        let dirs = [dir.join("templates"), dir.join("shared")];
+        let unused = find_unused(&dirs, "main.typ", Path::new(""));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(unused, [PathBuf::from("assets/old.png")]);
+    }
+
+    #[test]
+    fn test_find_unused_in_root_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("typst-bake-unused-root-{}", std::process::id()));
+        let write = |path: &str, content: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("invoice/main.typ", "#image(\"/shared/logo.png\")");
+        write("invoice/old.png", "");
+        write("shared/logo.png", "");
+        write("shared/unused.png", "");
+        write("reminder/main.typ", "#image(\"/shared/logo.png\")");
+
+        // Sibling documents and shared files belong to other entries too.
+        let unused = find_unused(&[dir.clone()], "invoice/main.typ", Path::new("invoice"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(unused, [PathBuf::from("invoice/old.png")]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Rendering many documents from one template.
+
+use crate::document::Document;
+use crate::error::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use typst::foundations::Dict;
+
+/// Options for [`Document::render_each`].
+#[derive(Default)]
+pub struct BatchOptions {
+    /// Number of worker threads. `0` and `1` render on the calling thread.
+    pub threads: usize,
+    /// Called after each document with the number of documents finished so far.
+    pub on_progress: Option<Box<dyn Fn(usize) + Send + Sync>>,
+}
+
+impl std::fmt::Debug for BatchOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchOptions")
+            .field("threads", &self.threads)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl Document {
+    /// Render one document per input set, e.g. for mail merge.
+    ///
+    /// Each item of `inputs` pairs a key (a customer id, a file name) with the inputs for
+    /// one document. `render` receives a document with those inputs and all other builder
+    /// settings of `self`, and turns it into output, typically with
+    /// [`to_pdf`](Self::to_pdf). Each result is passed to `sink` with its key as soon as
+    /// it is ready, on the calling thread, so a large batch never holds more than a few
+    /// outputs in memory. With several threads results arrive in completion order, not
+    /// input order. A failing item does not stop the batch.
+    ///
+    /// Typst's memoization cache is not cleared after every compile, so layout that does
+    /// not depend on the inputs (headers, backgrounds, packages) is reused between
+    /// documents. Instead it is evicted once per round of `threads` documents, dropping
+    /// what the last rounds didn't use, so memory stays bounded however long the batch
+    /// is. [`with_memo_retention`](Self::with_memo_retention) sets how many rounds unused
+    /// results survive (at least one), and applies once more at the end.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// use typst_bake::BatchOptions;
+    ///
+    /// let doc = typst_bake::document!("invoice.typ");
+    /// let options = BatchOptions {
+    ///     threads: 4,
+    ///     on_progress: Some(Box::new(|done| eprintln!("{done} invoices"))),
+    /// };
+    /// let invoices = customers.iter().map(|c| (c.id, Invoice::for_customer(c)));
+    /// doc.render_each(invoices, &options, |doc| doc.to_pdf(), |id, pdf| match pdf {
+    ///     Ok(pdf) => std::fs::write(format!("invoice-{id}.pdf"), pdf).expect("write"),
+    ///     Err(err) => eprintln!("invoice {id}: {err}"),
+    /// });
+    /// ```
+    pub fn render_each<K, T, R, I, F, S>(
+        &self,
+        inputs: I,
+        options: &BatchOptions,
+        render: F,
+        mut sink: S,
+    ) where
+        I: IntoIterator<Item = (K, T)>,
+        I::IntoIter: Send,
+        K: Send,
+        T: Into<Dict>,
+        R: Send,
+        F: Fn(&Document) -> Result<R> + Sync,
+        S: FnMut(K, Result<R>),
+    {
+        let queue = Mutex::new(inputs.into_iter());
+        let finished = AtomicUsize::new(0);
+        let round = options.threads.max(1);
+
+        // Each worker renders with its own fork, pulling items until the queue is empty.
+        let work = |emit: &mut dyn FnMut(K, Result<R>)| {
+            let mut doc = self.fork();
+            loop {
+                // Poisoned only if the input iterator panicked in another worker; stop and
+                // let the scope re-raise that panic.
+                let Ok(mut queue) = queue.lock() else {
+                    break;
+                };
+                let Some((key, inputs)) = queue.next() else {
+                    break;
+                };
+                drop(queue);
+                doc = doc.with_inputs(inputs);
+                let result = render(&doc);
+
+                let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                // One worker per round evicts; age counts rounds, and 0 would also drop
+                // what the round just used.
+                if done % round == 0 {
+                    typst::comemo::evict(self.batch_retention().max(1));
+                }
+                if let Some(on_progress) = &options.on_progress {
+                    on_progress(done);
+                }
+                emit(key, result);
+            }
+        };
+
+        if options.threads <= 1 {
+            work(&mut sink);
+        } else {
+            // Bounded, so workers wait for a slow sink instead of piling up outputs.
+            let (sender, receiver) = mpsc::sync_channel(options.threads);
+            std::thread::scope(|scope| {
+                for _ in 0..options.threads {
+                    let sender = sender.clone();
+                    let work = &work;
+                    scope.spawn(move || {
+                        work(&mut |key, result| {
+                            // Only fails if the sink panicked; the scope reports that.
+                            let _ = sender.send((key, result));
+                        });
+                    });
+                }
+                drop(sender);
+                for (key, result) in receiver {
+                    sink(key, result);
+                }
+            });
+        }
+        typst::comemo::evict(self.batch_retention());
+    }
+}
@@ -32,13 +32,24 @@ fn read_manifest(manifest_dir: &Path) -> toml::Table {
     content.parse().expect("Failed to parse Cargo.toml")
 }
 
-fn get_metadata_str<'a>(manifest: &'a toml::Table, key: &str) -> Option<&'a str> {
+fn get_metadata_value<'a>(manifest: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
     manifest
         .get("package")
         .and_then(|p| p.get("metadata"))
         .and_then(|m| m.get("typst-bake"))
         .and_then(|t| t.get(key))
-        .and_then(toml::Value::as_str)
+}
+
+fn get_metadata_str<'a>(manifest: &'a toml::Table, key: &str) -> Option<&'a str> {
+    get_metadata_value(manifest, key).and_then(toml::Value::as_str)
+}
+
+/// The strings of an array value; empty if unset or not an array.
+fn get_metadata_array<'a>(manifest: &'a toml::Table, key: &str) -> Vec<&'a str> {
+    get_metadata_value(manifest, key)
+        .and_then(toml::Value::as_array)
+        .map(|values| values.iter().filter_map(toml::Value::as_str).collect())
+        .unwrap_or_default()
 }
 
 fn resolve_path_string(manifest_dir: &Path, path: &str) -> String {
@@ -51,8 +62,9 @@ fn resolve_path_string(manifest_dir: &Path, path: &str) -> String {
 
 /// Emits `cargo:rerun-if-changed` directives for template and font directories.
 ///
-/// This function reads the `template-dir` and `fonts-dir` paths from your
-/// `Cargo.toml` metadata and tells Cargo to watch those directories for changes.
+/// This function reads the `template-dir` (or `template-dirs`), `root-dir`, and
+/// `fonts-dir` paths from your `Cargo.toml` metadata and tells Cargo to watch those
+/// directories for changes.
 ///
 /// # When to use
 ///
@@ -90,7 +102,7 @@ pub fn rebuild_if_changed() {
     let manifest_dir = Path::new(&manifest_dir);
     let manifest = read_manifest(manifest_dir);
 
-    for key in ["template-dir", "fonts-dir"] {
+    for key in ["template-dir", "root-dir", "fonts-dir"] {
         if let Some(dir) = get_metadata_str(&manifest, key) {
             println!(
                 "cargo:rerun-if-changed={}",
@@ -98,4 +110,10 @@ pub fn rebuild_if_changed() {
             );
         }
     }
+    for dir in get_metadata_array(&manifest, "template-dirs") {
+        println!(
+            "cargo:rerun-if-changed={}",
+            resolve_path_string(manifest_dir, dir)
+        );
+    }
 }
@@ -0,0 +1,50 @@
+//! A fixed clock for `datetime.today()`.
+
+use crate::util::utc_datetime;
+use std::time::SystemTime;
+use typst::diag::FileResult;
+use typst::foundations::{Bytes, Datetime};
+use typst::syntax::{FileId, Source};
+use typst::text::{Font, FontBook};
+use typst::utils::LazyHash;
+use typst::{Library, World};
+
+/// A [`World`] that answers `today` from a fixed instant and delegates everything else.
+pub(crate) struct FixedClock<'a> {
+    pub world: &'a dyn World,
+    pub now: SystemTime,
+}
+
+impl World for FixedClock<'_> {
+    fn library(&self) -> &LazyHash<Library> {
+        self.world.library()
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        self.world.book()
+    }
+
+    fn main(&self) -> FileId {
+        self.world.main()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        self.world.source(id)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.world.file(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.world.font(index)
+    }
+
+    /// The date of `now` at UTC plus `offset` hours. Without an offset, Typst asks for the
+    /// local date; there is no local timezone to speak of here, so UTC is used.
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        let now = utc_datetime(self.now)?;
+        let shifted = now.checked_add(time::Duration::hours(offset.unwrap_or(0)))?;
+        Some(Datetime::Date(shifted.date()))
+    }
+}
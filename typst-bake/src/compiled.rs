@@ -0,0 +1,517 @@
+//! A compiled document, ready to export.
+
+use crate::error::{Error, Result};
+#[cfg(feature = "pdf")]
+use crate::frames;
+#[cfg(feature = "pdf")]
+use crate::imposition;
+#[cfg(all(
+    feature = "metrics",
+    any(feature = "pdf", feature = "svg", feature = "png")
+))]
+use crate::metrics;
+#[cfg(feature = "pdf")]
+use crate::pdf_config::PdfConfig;
+#[cfg(feature = "png")]
+use crate::pixmap::{self, PngOptions, RgbaImage};
+#[cfg(any(feature = "pdf", feature = "svg", feature = "png"))]
+use crate::util::trace_span;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+#[cfg(any(feature = "svg", feature = "png"))]
+use typst::layout::Page;
+use typst::layout::PagedDocument;
+
+/// The result of [`Document::compile`](crate::Document::compile): laid-out pages plus the
+/// export settings of the document they came from.
+///
+/// Exports run directly on the pages, without compiling or taking any lock, and cloning
+/// is two reference-count increments, so one handle can be shared between threads that
+/// export in different formats or at different resolutions. Changing the originating
+/// `Document` afterwards does not affect it.
+///
+/// # Example
+/// ```rust,ignore
+/// let compiled = typst_bake::document!("report.typ").with_inputs(inputs).compile()?;
+/// let pdf = compiled.to_pdf()?;
+/// let thumbnails = compiled.to_png(36.0)?;
+/// ```
+#[derive(Clone)]
+pub struct CompiledDocument {
+    pub(crate) document: Arc<PagedDocument>,
+    settings: Arc<ExportSettings>,
+}
+
+/// Builder settings of a [`Document`](crate::Document) that apply at export time.
+pub(crate) struct ExportSettings {
+    #[cfg(feature = "pdf")]
+    pub(crate) pdf_config: PdfConfig,
+    pub(crate) max_output_size: Option<usize>,
+}
+
+impl CompiledDocument {
+    pub(crate) fn new(document: Arc<PagedDocument>, settings: ExportSettings) -> Self {
+        Self {
+            document,
+            settings: Arc::new(settings),
+        }
+    }
+
+    /// Number of pages.
+    pub fn page_count(&self) -> usize {
+        self.document.pages.len()
+    }
+
+    /// Generate PDF.
+    ///
+    /// # Errors
+    /// Returns an error if PDF generation fails or the output is too large.
+    #[cfg(feature = "pdf")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+    pub fn to_pdf(&self) -> Result<Vec<u8>> {
+        observed("pdf", Vec::len, || self.render_pdf_inner(None))
+    }
+
+    /// Generate one standalone PDF per page.
+    ///
+    /// See [`Document::to_pdf_pages`](crate::Document::to_pdf_pages).
+    ///
+    /// # Errors
+    /// Returns an error if PDF generation fails or the output is too large.
+    #[cfg(feature = "pdf")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+    pub fn to_pdf_pages(&self) -> Result<Vec<Vec<u8>>> {
+        let size = |pdfs: &Vec<Vec<u8>>| pdfs.iter().map(Vec::len).sum();
+        observed("pdf", size, || self.render_pdf_pages_inner())
+    }
+
+    /// Generate SVG for each page.
+    ///
+    /// # Errors
+    /// Returns an error if the output is too large.
+    #[cfg(feature = "svg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "svg")))]
+    pub fn to_svg(&self) -> Result<Vec<String>> {
+        let size = |svgs: &Vec<String>| svgs.iter().map(String::len).sum();
+        observed("svg", size, || self.render_svg_inner(None))
+    }
+
+    /// Generate PNG for each page.
+    ///
+    /// # Arguments
+    /// * `dpi` - Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print)
+    ///
+    /// # Errors
+    /// Returns an error if PNG encoding fails or the output is too large.
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_png(&self, dpi: f32) -> Result<Vec<Vec<u8>>> {
+        self.to_png_with(&PngOptions {
+            dpi,
+            ..Default::default()
+        })
+    }
+
+    /// Generate PNG for each page with the given options.
+    ///
+    /// See [`Document::to_png_with`](crate::Document::to_png_with).
+    ///
+    /// # Errors
+    /// Returns an error if PNG encoding fails or the output is too large.
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_png_with(&self, options: &PngOptions) -> Result<Vec<Vec<u8>>> {
+        let size = |pngs: &Vec<Vec<u8>>| pngs.iter().map(Vec::len).sum();
+        observed("png", size, || self.render_png_inner(None, options))
+    }
+
+    /// Render pages to PNG lazily, one page per iteration step.
+    ///
+    /// See [`Document::render_pages`](crate::Document::render_pages).
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn render_pages(&self, options: &PngOptions) -> RenderPages {
+        RenderPages {
+            compiled: self.clone(),
+            options: options.clone(),
+            next: 0,
+            total: 0,
+        }
+    }
+
+    /// Generate JPEG for each page.
+    ///
+    /// See [`Document::to_jpeg`](crate::Document::to_jpeg).
+    ///
+    /// # Errors
+    /// Returns an error if JPEG encoding fails or the output is too large.
+    #[cfg(feature = "jpeg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jpeg")))]
+    pub fn to_jpeg(&self, dpi: f32, quality: u8) -> Result<Vec<Vec<u8>>> {
+        let size = |jpegs: &Vec<Vec<u8>>| jpegs.iter().map(Vec::len).sum();
+        observed("jpeg", size, || self.render_jpeg_inner(None, dpi, quality))
+    }
+
+    /// Render each page to raw RGBA pixels.
+    ///
+    /// See [`Document::to_pixmaps`](crate::Document::to_pixmaps).
+    ///
+    /// # Errors
+    /// Returns an error if the output is too large.
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_pixmaps(&self, dpi: f32) -> Result<Vec<RgbaImage>> {
+        let size = |images: &Vec<RgbaImage>| images.iter().map(|i| i.data.len()).sum();
+        observed("rgba", size, || self.render_pixmaps_inner(None, dpi))
+    }
+
+    /// Fail with [`Error::OutputTooLarge`] if `size` exceeds the configured output limit.
+    fn check_output_size(&self, size: usize) -> Result<()> {
+        match self.settings.max_output_size {
+            Some(limit) if size > limit => Err(Error::OutputTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "pdf")]
+    pub(crate) fn render_pdf_inner(&self, selected: Option<&BTreeSet<usize>>) -> Result<Vec<u8>> {
+        let compiled = &*self.document;
+
+        // Base options come from the stored config (incl. `tagged`, standard, ident,
+        // timestamp). `options` borrows `pdf_config.ident`, which is fine: settings are
+        // immutable once compiled.
+        let pdf_config = &self.settings.pdf_config;
+        let mut options = pdf_config.to_typst()?;
+
+        let indices = validate_page_selection(selected, compiled.pages.len())?;
+
+        // Imposition builds a new document from the selected pages, so it needs
+        // neither `page_ranges` nor (and cannot support) tagging.
+        if let Some(imposition) = pdf_config.imposition {
+            if pdf_config.standard.requires_tagging() {
+                return Err(Error::InvalidPdfConfig(format!(
+                    "imposition is incompatible with {:?} (requires tagging)",
+                    pdf_config.standard
+                )));
+            }
+            options.tagged = false;
+            let pages = indices.unwrap_or_else(|| (0..compiled.pages.len()).collect());
+            let imposed = imposition::impose(compiled, &pages, imposition)?;
+            let pdf = typst_pdf::pdf(&imposed, &options)
+                .map_err(|e| Error::PdfGeneration(format!("{e:?}")))?;
+            self.check_output_size(pdf.len())?;
+            return Ok(pdf);
+        }
+
+        if let Some(indices) = indices {
+            use std::num::NonZeroUsize;
+            use typst::layout::PageRanges;
+
+            let ranges = indices
+                .iter()
+                .map(|&i| {
+                    let n = Some(NonZeroUsize::new(i + 1).unwrap());
+                    n..=n
+                })
+                .collect();
+            options.page_ranges = Some(PageRanges::new(ranges));
+
+            // --- Single safety net: page selection forces tagging off ---
+            //
+            // Page selection sets `page_ranges`, which is incompatible with tagged
+            // PDF. typst-pdf does NOT error on `tagged: true` + `page_ranges`; it
+            // silently emits a structure tree referencing ALL pages while only a
+            // subset is exported, yielding a malformed/misaligned tag tree. See:
+            //   - typst/typst#7743 (tagged PDF incompatible with page ranges)
+            // So we defensively force tagging off here, overriding `PdfConfig.tagged`
+            // — but ONLY on the page-selection path. Full-document `tagged: false` is
+            // handled by `to_typst` and is unaffected.
+            //
+            // Bookmarks: the document outline (/Outlines) is independent of tagging
+            // (typst-pdf sets the outline unconditionally; the tag tree only when
+            // enabled), so disabling tagging keeps bookmarks. Bookmarks pointing at
+            // EXCLUDED pages are still dropped here — that loss is caused by
+            // `page_ranges`, not by tagging.
+            //
+            // Accessible standards (PDF/A-*a, PDF/UA-1) mandate tagging, so they
+            // cannot coexist with page selection; reject them explicitly rather than
+            // emit a non-conformant PDF.
+            if pdf_config.standard.requires_tagging() {
+                return Err(Error::InvalidPdfConfig(format!(
+                    "page selection is incompatible with {:?} (requires tagging)",
+                    pdf_config.standard
+                )));
+            }
+            options.tagged = false;
+        }
+
+        // Invariant backstop: tagged PDF + page ranges must never escape together.
+        debug_assert!(!(options.tagged && options.page_ranges.is_some()));
+
+        let pdf = typst_pdf::pdf(compiled, &options)
+            .map_err(|e| Error::PdfGeneration(format!("{e:?}")))?;
+        self.check_output_size(pdf.len())?;
+        Ok(pdf)
+    }
+
+    /// One PDF of this document's pages followed by the pages of `rest`, with this
+    /// document's settings.
+    #[cfg(feature = "pdf")]
+    pub(crate) fn render_merged_pdf_inner(&self, rest: &[CompiledDocument]) -> Result<Vec<u8>> {
+        let parts = || std::iter::once(self).chain(rest);
+        if let Some(part) = parts().position(|part| frames::has_attachments(&part.document)) {
+            return Err(Error::InvalidPdfConfig(format!(
+                "merging would drop the PDF attachments of document {}",
+                part + 1
+            )));
+        }
+
+        let pdf_config = &self.settings.pdf_config;
+        if pdf_config.standard.requires_tagging() {
+            return Err(Error::InvalidPdfConfig(format!(
+                "merging is incompatible with {:?} (requires tagging)",
+                pdf_config.standard
+            )));
+        }
+        let mut options = pdf_config.to_typst()?;
+        options.tagged = false;
+
+        let pages = parts()
+            .flat_map(|compiled| compiled.document.pages.iter().cloned())
+            .enumerate()
+            .map(|(i, mut page)| {
+                page.frame = frames::strip_locations(&page.frame);
+                page.number = i as u64 + 1;
+                page
+            })
+            .collect();
+        let merged = PagedDocument {
+            pages,
+            info: self.document.info.clone(),
+            introspector: Default::default(),
+        };
+
+        let pdf = typst_pdf::pdf(&merged, &options)
+            .map_err(|e| Error::PdfGeneration(format!("{e:?}")))?;
+        self.check_output_size(pdf.len())?;
+        Ok(pdf)
+    }
+
+    #[cfg(feature = "pdf")]
+    pub(crate) fn render_pdf_pages_inner(&self) -> Result<Vec<Vec<u8>>> {
+        let mut total = 0;
+        (0..self.page_count())
+            .map(|i| {
+                let pdf = self.render_pdf_inner(Some(&BTreeSet::from([i])))?;
+                total += pdf.len();
+                self.check_output_size(total)?;
+                Ok(pdf)
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "svg")]
+    pub(crate) fn render_svg_inner(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+    ) -> Result<Vec<String>> {
+        let export = |_, page: &Page| Ok(typst_svg::svg(page));
+        self.export_pages(selected, export, String::len)
+    }
+
+    #[cfg(feature = "png")]
+    pub(crate) fn render_png_inner(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        options: &PngOptions,
+    ) -> Result<Vec<Vec<u8>>> {
+        let export = |index: usize, page: &Page| {
+            let page = pixmap::with_background(page, options.background);
+            typst_render::render(&page, options.pixel_per_pt(index))
+                .encode_png()
+                .map_err(|e| Error::PngEncoding(e.to_string()))
+        };
+        self.export_pages(selected, export, Vec::len)
+    }
+
+    #[cfg(feature = "png")]
+    pub(crate) fn render_pixmaps_inner(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        dpi: f32,
+    ) -> Result<Vec<RgbaImage>> {
+        let pixel_per_pt = dpi / 72.0;
+        let export = |_, page: &Page| Ok(pixmap::render(page, pixel_per_pt));
+        self.export_pages(selected, export, |image| image.data.len())
+    }
+
+    #[cfg(feature = "jpeg")]
+    pub(crate) fn render_jpeg_inner(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        dpi: f32,
+        quality: u8,
+    ) -> Result<Vec<Vec<u8>>> {
+        let pixel_per_pt = dpi / 72.0;
+        let export = |_, page: &Page| pixmap::render_jpeg(page, pixel_per_pt, quality);
+        self.export_pages(selected, export, Vec::len)
+    }
+
+    /// Export the selected pages one by one, failing as soon as their total size exceeds
+    /// the output limit. `export` receives each page with its index in the document.
+    ///
+    /// With the `rayon` feature, pages are exported in parallel on the global thread pool.
+    /// Results keep page order either way.
+    #[cfg(any(feature = "svg", feature = "png"))]
+    fn export_pages<T: Send>(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        export: impl Fn(usize, &Page) -> Result<T> + Sync,
+        size: impl Fn(&T) -> usize + Sync,
+    ) -> Result<Vec<T>> {
+        let compiled = &*self.document;
+
+        let indices = validate_page_selection(selected, compiled.pages.len())?;
+        let indices = indices.unwrap_or_else(|| (0..compiled.pages.len()).collect());
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let total = AtomicUsize::new(0);
+            indices
+                .into_par_iter()
+                .map(|index| {
+                    let output = export(index, &compiled.pages[index])?;
+                    let size = size(&output);
+                    self.check_output_size(total.fetch_add(size, Ordering::Relaxed) + size)?;
+                    Ok(output)
+                })
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut total = 0;
+            indices
+                .into_iter()
+                .map(|index| {
+                    let output = export(index, &compiled.pages[index])?;
+                    total += size(&output);
+                    self.check_output_size(total)?;
+                    Ok(output)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Iterator over the pages of a document as PNG, rendering each page only when it is
+/// reached. Returned by [`Document::render_pages`](crate::Document::render_pages).
+///
+/// Holds on to the compiled document, not to the [`Document`](crate::Document), so it can
+/// be moved to another thread.
+#[cfg(feature = "png")]
+#[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+pub struct RenderPages {
+    compiled: CompiledDocument,
+    options: PngOptions,
+    next: usize,
+    /// Bytes yielded so far, checked against the output limit.
+    total: usize,
+}
+
+#[cfg(feature = "png")]
+impl Iterator for RenderPages {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next;
+        let page = self.compiled.document.pages.get(index)?;
+        self.next += 1;
+        let result = observed("png", Vec::len, || {
+            let page = pixmap::with_background(page, self.options.background);
+            let png = typst_render::render(&page, self.options.pixel_per_pt(index))
+                .encode_png()
+                .map_err(|e| Error::PngEncoding(e.to_string()))?;
+            self.total += png.len();
+            self.compiled.check_output_size(self.total)?;
+            Ok(png)
+        });
+        if result.is_err() {
+            // Stop after the first failure, like the eager exports.
+            self.next = self.compiled.page_count();
+        }
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.compiled.page_count() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(feature = "png")]
+impl ExactSizeIterator for RenderPages {}
+
+/// Validate page selection and return indices to render.
+/// Returns `None` if no selection (= all pages).
+fn validate_page_selection(
+    selected: Option<&BTreeSet<usize>>,
+    total_pages: usize,
+) -> Result<Option<Vec<usize>>> {
+    if total_pages == 0 {
+        return Err(Error::InvalidPageSelection("document has no pages".into()));
+    }
+    match selected {
+        None => Ok(None),
+        Some(pages) => {
+            if pages.is_empty() {
+                return Err(Error::InvalidPageSelection(
+                    "page selection is empty".into(),
+                ));
+            }
+            if let Some(&max) = pages.last() {
+                if max >= total_pages {
+                    return Err(Error::InvalidPageSelection(format!(
+                        "page index {max} out of range (valid: 0..={})",
+                        total_pages - 1
+                    )));
+                }
+            }
+            Ok(Some(pages.iter().copied().collect()))
+        }
+    }
+}
+
+/// Run one export, recording it in the render metrics.
+#[cfg(all(
+    feature = "metrics",
+    any(feature = "pdf", feature = "svg", feature = "png")
+))]
+pub(crate) fn observed<T>(
+    format: &'static str,
+    size: impl Fn(&T) -> usize,
+    render: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    trace_span!(INFO, "export", format);
+    let start = std::time::Instant::now();
+    let result = render();
+    metrics::record_render(format, start.elapsed(), result.as_ref().map(size));
+    result
+}
+
+/// Run one export. Without the `metrics` feature there is nothing to record.
+#[cfg(all(
+    not(feature = "metrics"),
+    any(feature = "pdf", feature = "svg", feature = "png")
+))]
+pub(crate) fn observed<T>(
+    _format: &'static str,
+    _size: impl Fn(&T) -> usize,
+    render: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    trace_span!(INFO, "export", format = _format);
+    render()
+}
@@ -0,0 +1,104 @@
+//! Runtime data files for Typst's data-loading functions.
+
+use crate::document::Document;
+#[cfg(feature = "serde")]
+use crate::error::Error;
+use crate::error::Result;
+
+impl Document {
+    /// Add a runtime JSON file serialized from `value`, readable with `json(path)`.
+    ///
+    /// Suits templates written around data files, where [`with_inputs`](Self::with_inputs)
+    /// would mean rewriting them to use `sys.inputs`. JSON is also valid YAML, so the same
+    /// file can be loaded with `yaml(path)`. Otherwise this behaves like
+    /// [`add_file`](Self::add_file).
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidInputs`] if `value` can't be serialized, and
+    /// [`Error::InvalidFilePath`] for paths rejected by `add_file`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("report.typ")
+    ///     .with_json("data/report.json", &report)?
+    ///     .to_pdf()?;
+    /// ```
+    ///
+    /// ```typ
+    /// #let report = json("data/report.json")
+    /// ```
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn with_json<T: serde::Serialize + ?Sized>(
+        self,
+        path: impl Into<String>,
+        value: &T,
+    ) -> Result<Self> {
+        let json = serde_json::to_vec(value).map_err(|e| Error::InvalidInputs(e.to_string()))?;
+        self.add_file(path, json)
+    }
+
+    /// Add a runtime CSV file built from `rows`, readable with `csv(path)`.
+    ///
+    /// Fields containing commas, quotes, or line breaks are quoted. Include a header row
+    /// if the template reads the file with `csv(path, row-type: dictionary)`. Otherwise
+    /// this behaves like [`add_file`](Self::add_file).
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidFilePath`](crate::Error::InvalidFilePath) for paths
+    /// rejected by `add_file`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let rows = std::iter::once(vec!["name".to_string(), "amount".to_string()])
+    ///     .chain(items.iter().map(|i| vec![i.name.clone(), i.amount.to_string()]));
+    /// let pdf = typst_bake::document!("report.typ")
+    ///     .with_csv("data/items.csv", rows)?
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_csv<R, F>(self, path: impl Into<String>, rows: R) -> Result<Self>
+    where
+        R: IntoIterator,
+        R::Item: IntoIterator<Item = F>,
+        F: AsRef<str>,
+    {
+        self.add_file(path, to_csv(rows))
+    }
+}
+
+fn to_csv<R, F>(rows: R) -> String
+where
+    R: IntoIterator,
+    R::Item: IntoIterator<Item = F>,
+    F: AsRef<str>,
+{
+    let mut out = String::new();
+    for row in rows {
+        for (i, field) in row.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                out.push('"');
+                out.push_str(&field.replace('"', "\"\""));
+                out.push('"');
+            } else {
+                out.push_str(field);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        let rows = [vec!["name", "note"], vec!["Ada", "says \"hi\", twice"]];
+        assert_eq!(to_csv(rows), "name,note\nAda,\"says \"\"hi\"\", twice\"\n");
+    }
+}
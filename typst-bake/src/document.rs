@@ -1,39 +1,181 @@
 //! Self-contained document for Typst template rendering.
 
-use crate::error::{Diagnostic, Error, Result, SourceLocation};
+use crate::clock::FixedClock;
+#[cfg(any(feature = "pdf", feature = "svg", feature = "png"))]
+use crate::compiled::observed;
+#[cfg(feature = "png")]
+use crate::compiled::RenderPages;
+use crate::compiled::{CompiledDocument, ExportSettings};
+use crate::error::{Diagnostic, Error, Result, Severity, SourceLocation};
+use crate::font_alias;
+use crate::frames;
+use crate::inventory::{self, EmbeddedPackage, FontFace, TemplateFile};
+use crate::library::WithLibrary;
+use crate::limits::{DecompressionBudget, DecompressionLimits};
+use crate::markup;
+#[cfg(feature = "metrics")]
+use crate::metrics;
 #[cfg(feature = "pdf")]
 use crate::pdf_config::PdfConfig;
+#[cfg(feature = "png")]
+use crate::pixmap::{PngOptions, RgbaImage};
+use crate::render_cache::RenderCache;
 use crate::resolver::{file_id_to_path, normalize_file_path, EmbeddedResolver};
 use crate::stats::EmbedStats;
-use crate::util::decompress;
+use crate::typed::RequiresInputs;
+use crate::util::{trace_span, typst_array, typst_str};
 use include_dir::{Dir, File};
 use std::collections::{BTreeSet, HashMap};
-use std::sync::{Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
 use typst::diag::SourceDiagnostic;
-use typst::foundations::Dict;
+use typst::foundations::{Bytes, Dict, IntoValue, Str};
 use typst::layout::PagedDocument;
 use typst::syntax::{FileId, Span};
-use typst::{World, WorldExt};
+use typst::text::Font;
+use typst::{Feature, World, WorldExt};
 use typst_as_lib::{TypstEngine, TypstWorld};
 
+/// Runtime directory holding the data of [`Document::with_pdf_attachment`] files.
+const ATTACHMENT_DIR: &str = ".typst-bake/attachments";
+
+/// `errors` formatted for display, one paragraph each.
+fn error_report(errors: &[Diagnostic]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Typst source of the page shown by [`Document::with_error_document`].
+fn error_source(errors: &[Diagnostic]) -> String {
+    format!(
+        "#set page(margin: 2cm)\n#set text(size: 10pt)\n= Compilation failed\n#raw(block: true, {})\n",
+        typst_str(&error_report(errors))
+    )
+}
+
+/// What [`Document::with_overlay`] and [`Document::with_overlay_template`] draw.
+#[derive(Clone)]
+enum Overlay {
+    Text(String),
+    /// Path of a template file, relative to the root.
+    Template(String),
+}
+
+/// Normalize a runtime file path, rejecting empty, absolute, and `..` paths, and paths
+/// in the directory reserved for typst-bake's own files.
+fn validate_file_path(raw: &str) -> Result<String> {
+    let normalized = normalize_file_path(raw);
+
+    if normalized.is_empty() {
+        return Err(Error::InvalidFilePath("path is empty".into()));
+    }
+    if normalized.starts_with('/') {
+        return Err(Error::InvalidFilePath(format!(
+            "absolute path not allowed: {normalized}"
+        )));
+    }
+    if normalized.split('/').any(|s| s == "..") {
+        return Err(Error::InvalidFilePath(format!(
+            "path with '..' not allowed: {normalized}"
+        )));
+    }
+    if normalized.split('/').next() == Some(markup::RESERVED_DIR) {
+        return Err(Error::InvalidFilePath(format!(
+            "path reserved for typst-bake: {normalized}"
+        )));
+    }
+    Ok(normalized)
+}
+
 /// A fully self-contained document ready for rendering.
 ///
 /// Created by the [`document!`](crate::document!) macro with embedded templates, fonts,
 /// and packages. All resources are compressed with zstd and decompressed lazily at runtime.
+///
+/// `Document` is `Send + Sync`. Builder methods take `self` by value, so once configured it
+/// can be shared, e.g. in an `Arc`, and rendered from many threads at once: the first
+/// render compiles, and every export after that runs in parallel on the shared result.
+/// For different inputs per render, see [`render_each`](Self::render_each).
 pub struct Document {
     templates: &'static Dir<'static>,
     packages: &'static Dir<'static>,
     fonts: &'static Dir<'static>,
-    entry: &'static str,
+    entry: String,
+    /// Hash of the entry and every embedded file, computed by the macro.
+    content_hash: &'static str,
     inputs: Mutex<Option<Dict>>,
     runtime_files: Mutex<HashMap<String, Vec<u8>>>,
     stats: EmbedStats,
-    compiled_cache: Mutex<Option<PagedDocument>>,
+    /// The compiled document. Shared through an `Arc` so exports run without holding the
+    /// lock, letting threads render the same `Document` concurrently.
+    compiled_cache: Mutex<Option<Arc<PagedDocument>>>,
+    /// Decompressed embedded fonts and their aliased copies, filled by the first compile.
+    /// Shared with forks, so a batch prepares fonts once rather than once per document.
+    font_cache: Arc<Mutex<FontCache>>,
+    /// Warnings of the compile that produced `compiled_cache`.
+    warnings: Mutex<Vec<Diagnostic>>,
     /// PDF export options. Set by [`Document::with_pdf_config`]. A plain field (no
     /// `Mutex`): the builder takes `self` by value to write it, and rendering reads it
     /// through `&self`. Affects PDF export only, so it never invalidates `compiled_cache`.
     #[cfg(feature = "pdf")]
     pdf_config: PdfConfig,
+    /// Upper bound on the compiled page count. Set by [`Document::with_max_pages`].
+    max_pages: Option<usize>,
+    /// Upper bound on exported bytes. Set by [`Document::with_max_output_size`].
+    max_output_size: Option<usize>,
+    /// Set by [`Document::with_decompression_limits`].
+    decompression_limits: DecompressionLimits,
+    /// `(alias, target family)` pairs. Set by [`Document::with_font_alias`].
+    font_aliases: Vec<(String, String)>,
+    /// Typst code evaluated before the entry. Set by [`Document::with_prelude`].
+    preludes: Vec<String>,
+    /// Text language. Set by [`Document::with_lang`].
+    lang: Option<String>,
+    /// Text region. Set by [`Document::with_region`].
+    region: Option<String>,
+    /// Document title. Set by [`Document::with_title`].
+    title: Option<String>,
+    /// Document authors. Set by [`Document::with_author`].
+    authors: Vec<String>,
+    /// Document keywords. Set by [`Document::with_keywords`].
+    keywords: Vec<String>,
+    /// Content drawn over every page. Set by [`Document::with_overlay`] and
+    /// [`Document::with_overlay_template`].
+    overlay: Option<Overlay>,
+    /// Whether headings become PDF bookmarks. Set by [`Document::with_bookmarks`].
+    bookmarks: Option<bool>,
+    /// Instant `datetime.today()` resolves to. Set by [`Document::with_now`].
+    now: Option<SystemTime>,
+    /// `(name, MIME type)` of PDF attachments, whose data is stored as a runtime file
+    /// under [`ATTACHMENT_DIR`]. Set by [`Document::with_pdf_attachment`].
+    attachments: Vec<(String, String)>,
+    /// Environment variables exposed as `sys.inputs.env`. Set by
+    /// [`Document::with_env_inputs`].
+    env_inputs: Option<Dict>,
+    /// Subdirectory of `templates` serving as the root. Set by
+    /// [`Document::with_template_set`].
+    template_set: Option<String>,
+    /// Names of the template sets the macro embedded; empty without `template-sets`.
+    template_sets: &'static [&'static str],
+    /// Age passed to `comemo::evict` after each compile; `None` skips eviction. Set by
+    /// [`Document::with_memo_retention`]; `None` for batch workers, which evict once when
+    /// the batch is done.
+    memo_retention: Option<usize>,
+    /// Whether warnings fail compilation. Set by [`Document::with_strict`].
+    strict: bool,
+    /// Typst features enabled in the standard library. Set by [`Document::with_feature`].
+    features: Vec<Feature>,
+    /// Entry compiled when `entry` fails. Set by [`Document::with_fallback`].
+    fallback: Option<String>,
+    /// Whether compilation errors render as an error page. Set by
+    /// [`Document::with_error_document`].
+    error_document: bool,
+    /// Export output shared by documents from the same bundle. Set by
+    /// [`Document::with_cache`].
+    render_cache: Option<Arc<RenderCache>>,
 }
 
 impl Document {
@@ -46,18 +188,102 @@ impl Document {
         fonts: &'static Dir<'static>,
         entry: &'static str,
         stats: EmbedStats,
+        content_hash: &'static str,
     ) -> Self {
         Self {
             templates,
             packages,
             fonts,
-            entry,
+            entry: entry.to_owned(),
+            content_hash,
             inputs: Mutex::new(None),
             runtime_files: Mutex::new(HashMap::new()),
             stats,
             compiled_cache: Mutex::new(None),
+            font_cache: Arc::default(),
+            warnings: Mutex::new(Vec::new()),
             #[cfg(feature = "pdf")]
             pdf_config: PdfConfig::default(),
+            max_pages: None,
+            max_output_size: None,
+            decompression_limits: DecompressionLimits::default(),
+            font_aliases: Vec::new(),
+            preludes: Vec::new(),
+            lang: None,
+            region: None,
+            title: None,
+            authors: Vec::new(),
+            keywords: Vec::new(),
+            overlay: None,
+            bookmarks: None,
+            now: None,
+            attachments: Vec::new(),
+            env_inputs: None,
+            template_set: None,
+            template_sets: &[],
+            memo_retention: Some(0),
+            strict: false,
+            features: Vec::new(),
+            fallback: None,
+            error_document: false,
+            render_cache: None,
+        }
+    }
+
+    /// Internal setup for `template-sets`, used by the macro: the embedded sets and the
+    /// `default-template-set` selected until [`with_template_set`](Self::with_template_set)
+    /// picks another. Do not use directly.
+    #[doc(hidden)]
+    pub fn __with_template_sets(
+        mut self,
+        sets: &'static [&'static str],
+        default: &'static str,
+    ) -> Self {
+        self.template_sets = sets;
+        self.template_set = Some(default.to_owned());
+        self
+    }
+
+    /// A copy with the same embedded content and builder settings, but no inputs, no
+    /// compiled document, and eviction deferred to the caller.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            templates: self.templates,
+            packages: self.packages,
+            fonts: self.fonts,
+            entry: self.entry.clone(),
+            content_hash: self.content_hash,
+            inputs: Mutex::new(None),
+            runtime_files: Mutex::new(self.lock_runtime_files().clone()),
+            stats: self.stats.clone(),
+            compiled_cache: Mutex::new(None),
+            font_cache: self.font_cache.clone(),
+            warnings: Mutex::new(Vec::new()),
+            #[cfg(feature = "pdf")]
+            pdf_config: self.pdf_config.clone(),
+            max_pages: self.max_pages,
+            max_output_size: self.max_output_size,
+            decompression_limits: self.decompression_limits,
+            font_aliases: self.font_aliases.clone(),
+            preludes: self.preludes.clone(),
+            lang: self.lang.clone(),
+            region: self.region.clone(),
+            title: self.title.clone(),
+            authors: self.authors.clone(),
+            keywords: self.keywords.clone(),
+            overlay: self.overlay.clone(),
+            bookmarks: self.bookmarks,
+            now: self.now,
+            attachments: self.attachments.clone(),
+            env_inputs: self.env_inputs.clone(),
+            template_set: self.template_set.clone(),
+            template_sets: self.template_sets,
+            memo_retention: None,
+            strict: self.strict,
+            features: self.features.clone(),
+            fallback: self.fallback.clone(),
+            error_document: self.error_document,
+            render_cache: self.render_cache.clone(),
         }
     }
 
@@ -69,7 +295,7 @@ impl Document {
         self.runtime_files.lock().expect("lock poisoned")
     }
 
-    fn lock_cache(&self) -> MutexGuard<'_, Option<PagedDocument>> {
+    fn lock_cache(&self) -> MutexGuard<'_, Option<Arc<PagedDocument>>> {
         self.compiled_cache.lock().expect("lock poisoned")
     }
 
@@ -85,6 +311,9 @@ impl Document {
     /// = #inputs.title
     /// ```
     ///
+    /// String inputs are plain text values and are never evaluated as Typst code; see
+    /// [Input Safety](crate#input-safety).
+    ///
     /// # Example
     ///
     /// ```rust,ignore
@@ -119,6 +348,51 @@ impl Document {
         self
     }
 
+    /// Require inputs of type `T` before the document can be rendered.
+    ///
+    /// Returns a [`RequiresInputs`] wrapper without rendering methods, so a missing
+    /// [`with_inputs`](RequiresInputs::with_inputs) call fails to compile.
+    /// `document!("main.typ", inputs = T)` is shorthand for this.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("invoice.typ").require_inputs::<Invoice>();
+    /// let pdf = doc.with_inputs(invoice).to_pdf()?;
+    /// ```
+    pub fn require_inputs<T: Into<Dict>>(self) -> RequiresInputs<T> {
+        RequiresInputs::new(self)
+    }
+
+    /// Expose selected environment variables to templates as `sys.inputs.env`.
+    ///
+    /// Values are read when this is called, so build or deployment details (version,
+    /// commit) can be stamped into documents without adding them to the inputs struct.
+    /// Variables that are unset or not valid Unicode are left out; check with
+    /// `"APP_VERSION" in sys.inputs.env`. Only the listed names are exposed, and the
+    /// `env` key replaces any `env` field of the regular inputs.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ")
+    ///     .with_inputs(inputs)
+    ///     .with_env_inputs(&["APP_VERSION", "GIT_SHA"]);
+    /// ```
+    ///
+    /// ```typ
+    /// #set page(footer: [Version #sys.inputs.env.at("APP_VERSION", default: "dev")])
+    /// ```
+    pub fn with_env_inputs<S: AsRef<str>>(mut self, names: &[S]) -> Self {
+        let env = self.env_inputs.get_or_insert_with(Dict::new);
+        for name in names {
+            let name = name.as_ref();
+            if let Ok(value) = std::env::var(name) {
+                env.insert(name.into(), value.into_value());
+            }
+        }
+        *self.lock_cache() = None;
+        self
+    }
+
     /// Add or replace a runtime file at the given path.
     ///
     /// The file becomes available to Typst templates via `#image("path")`,
@@ -126,8 +400,9 @@ impl Document {
     /// with the same path.
     ///
     /// # Errors
-    /// Returns [`Error::InvalidFilePath`] if the path is empty, absolute, or
-    /// contains `..` segments.
+    /// Returns [`Error::InvalidFilePath`] if the path is empty, absolute, contains
+    /// `..` segments, or is under `.typst-bake/`, which is reserved for the files
+    /// typst-bake provides.
     ///
     /// # Example
     /// ```rust,ignore
@@ -136,23 +411,7 @@ impl Document {
     ///     .to_pdf()?;
     /// ```
     pub fn add_file(self, path: impl Into<String>, data: impl Into<Vec<u8>>) -> Result<Self> {
-        let raw = path.into();
-        let normalized = normalize_file_path(&raw);
-
-        if normalized.is_empty() {
-            return Err(Error::InvalidFilePath("path is empty".into()));
-        }
-        if normalized.starts_with('/') {
-            return Err(Error::InvalidFilePath(format!(
-                "absolute path not allowed: {normalized}"
-            )));
-        }
-        if normalized.split('/').any(|s| s == "..") {
-            return Err(Error::InvalidFilePath(format!(
-                "path with '..' not allowed: {normalized}"
-            )));
-        }
-
+        let normalized = validate_file_path(&path.into())?;
         self.lock_runtime_files().insert(normalized, data.into());
         *self.lock_cache() = None;
         Ok(self)
@@ -192,301 +451,1545 @@ impl Document {
         self
     }
 
-    /// Check if a file exists at the given path.
+    /// Attach a file to the exported PDF.
     ///
-    /// Checks both embedded (compile-time) and runtime files.
-    pub fn has_file(&self, path: impl AsRef<str>) -> bool {
-        let normalized = normalize_file_path(path.as_ref());
-
-        // Check runtime files first.
-        if self.lock_runtime_files().contains_key(&normalized) {
-            return true;
-        }
-
-        // Check embedded templates.
-        if find_entry(self.templates, &normalized).is_some() {
-            return true;
-        }
-
-        false
+    /// The PDF carries `data` as an embedded file named `name`, which viewers list in
+    /// their attachments panel; e-invoicing formats such as ZUGFeRD/Factur-X use this for
+    /// the machine-readable XML. Equivalent to calling `pdf.attach` in the template, but
+    /// the data comes from Rust. Attaching the same name again replaces the file.
+    ///
+    /// Attachments are part of the compiled document, so this invalidates the compiled
+    /// cache. SVG and PNG output ignore them. PDF/A-1 and PDF/A-2 forbid attachments;
+    /// exporting with those standards fails. To set the attachment's relationship to the
+    /// document (e.g. `"alternative"` for Factur-X), call `pdf.attach` in the template
+    /// instead, reading the data from a file added with [`add_file`](Self::add_file).
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidFilePath`] if `name` is not a valid relative path. An
+    /// invalid MIME type fails compilation with a diagnostic in `<prelude>`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_pdf_attachment("factur-x.xml", xml, "text/xml")?
+    ///     .to_pdf()?;
+    /// ```
+    #[cfg(feature = "pdf")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+    pub fn with_pdf_attachment(
+        mut self,
+        name: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+        mime_type: impl Into<String>,
+    ) -> Result<Self> {
+        let name = validate_file_path(&name.into())?;
+        // Under the reserved directory, which `add_file` refuses.
+        self.lock_runtime_files()
+            .insert(format!("{ATTACHMENT_DIR}/{name}"), data.into());
+        *self.lock_cache() = None;
+        self.attachments.retain(|(existing, _)| *existing != name);
+        self.attachments.push((name, mime_type.into()));
+        Ok(self)
     }
 
-    /// Select specific pages for output, returning a [`Pages`] view.
+    /// Limit the number of pages the document may lay out.
     ///
-    /// Pages are 0-indexed. Duplicates are removed and pages are always
-    /// output in document order regardless of input order.
+    /// When compilation produces more than `max_pages` pages, rendering fails with
+    /// [`Error::TooManyPages`] and the oversized layout is discarded instead of being
+    /// cached or exported. This guards against templates that explode on unexpectedly
+    /// large inputs.
     ///
-    /// # Errors
-    /// Returns [`Error::InvalidPageSelection`] at render time if any index
-    /// is out of range or the selection is empty.
+    /// Typst lays out the whole document in one pass, so the check runs right after
+    /// layout and before any export work (PDF writing, rasterization), which is where
+    /// most of the memory is spent for very long documents.
     ///
     /// # Example
     /// ```rust,ignore
-    /// // Select specific pages
     /// let pdf = typst_bake::document!("main.typ")
-    ///     .select_pages([0, 2, 4])
+    ///     .with_inputs(inputs)
+    ///     .with_max_pages(500)
     ///     .to_pdf()?;
+    /// ```
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Limit the size of the exported output, in bytes.
     ///
-    /// // Works with ranges too
-    /// let svgs = typst_bake::document!("main.typ")
-    ///     .select_pages(0..3)
-    ///     .to_svg()?;
+    /// Applies to the total produced by one export call: the PDF file, or the sum of
+    /// all SVG/PNG pages. Exceeding it fails with [`Error::OutputTooLarge`]. Multi-page
+    /// SVG/PNG exports stop at the first page that crosses the limit, so the remaining
+    /// pages are never rendered; a PDF is written in one piece and checked afterwards.
     ///
-    /// // Reuse with different selections
-    /// let doc = typst_bake::document!("main.typ");
-    /// let cover = doc.select_pages([0]).to_pdf()?;
-    /// let body = doc.select_pages(1..5).to_pdf()?;
+    /// This only affects export, so it does not invalidate the compiled cache.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // Never hand out more than 25 MB.
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .with_max_output_size(25 * 1024 * 1024)
+    ///     .to_pdf()?;
     /// ```
-    pub fn select_pages(&self, pages: impl IntoIterator<Item = usize>) -> Pages<'_> {
-        Pages {
-            doc: self,
-            indices: pages.into_iter().collect(),
-        }
+    pub fn with_max_output_size(mut self, bytes: usize) -> Self {
+        self.max_output_size = Some(bytes);
+        self
     }
 
-    /// Get the total number of pages in the compiled document.
+    /// Limit how much embedded content a compilation may decompress.
     ///
-    /// Compiles the document if not already compiled.
-    /// Returns the total page count regardless of `select_pages`.
+    /// Every embedded file (entry, fonts, templates, packages) is checked against
+    /// [`DecompressionLimits::per_file`], and the running total of one compilation
+    /// against [`DecompressionLimits::total`]. Decompression stops as soon as a limit is
+    /// crossed, and rendering fails with [`Error::DecompressionLimit`].
     ///
     /// # Example
     /// ```rust,ignore
-    /// let doc = typst_bake::document!("main.typ");
-    /// let count = doc.page_count()?;
-    /// let last_page = doc.select_pages([count - 1]).to_pdf()?;
+    /// use typst_bake::DecompressionLimits;
+    ///
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .with_decompression_limits(DecompressionLimits {
+    ///         per_file: Some(32 * 1024 * 1024),
+    ///         total: Some(256 * 1024 * 1024),
+    ///     })
+    ///     .to_pdf()?;
     /// ```
-    pub fn page_count(&self) -> Result<usize> {
-        self.with_compiled(|compiled| Ok(compiled.pages.len()))
+    pub fn with_decompression_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.decompression_limits = limits;
+        *self.lock_cache() = None;
+        self
     }
 
-    /// Get compression statistics for embedded content.
-    pub fn stats(&self) -> &EmbedStats {
-        &self.stats
+    /// Treat Typst warnings as errors.
+    ///
+    /// With `strict` set, a compile that produces warnings fails with
+    /// [`Error::Compilation`] listing them (with [`Severity::Warning`]), so problems such
+    /// as unknown font families or deprecated functions fail a CI run instead of
+    /// scrolling by. Without it, warnings are available from
+    /// [`warnings`](Self::warnings).
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// #[test]
+    /// fn invoice_compiles_cleanly() {
+    ///     let doc = typst_bake::document!("invoice.typ")
+    ///         .with_inputs(sample_invoice())
+    ///         .with_strict(true);
+    ///     doc.compile().unwrap();
+    /// }
+    /// ```
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        *self.lock_cache() = None;
+        self
     }
 
-    /// Compile the document, reusing the cached result if available.
-    fn compile_cached(&self) -> Result<()> {
-        if self.lock_cache().is_some() {
-            return Ok(());
-        }
-
-        // Read main template content (compressed)
-        let main_file =
-            find_entry(self.templates, self.entry).ok_or(Error::EntryNotFound(self.entry))?;
-
-        let main_bytes = decompress(main_file.contents())?;
-        let main_content = std::str::from_utf8(&main_bytes).map_err(|_| Error::InvalidUtf8)?;
-
-        let mut resolver = EmbeddedResolver::new(self.templates, self.packages);
-        for (path, data) in self.lock_runtime_files().iter() {
-            resolver.insert_runtime_file(path.clone(), data.clone());
-        }
-
-        // Collect and decompress fonts from the embedded fonts directory
-        let font_data: Vec<Vec<u8>> = self
-            .fonts
-            .files()
-            .map(|f| decompress(f.contents()).map_err(Error::from))
-            .collect::<Result<Vec<_>>>()?;
-
-        let font_refs: Vec<&[u8]> = font_data.iter().map(Vec::as_slice).collect();
-
-        let engine = TypstEngine::builder()
-            .main_file((self.entry, main_content))
-            .add_file_resolver(resolver)
-            .fonts(font_refs)
-            .build();
-
-        // Clone inputs (preserve for retry on failure)
-        let inputs = self.lock_inputs().clone();
-
-        // Drive the world directly (mirrors typst-as-lib's internal `do_compile`) so the
-        // `World` stays in scope to resolve diagnostic spans into source locations.
-        let mut world_builder = engine.world_builder();
-        if let Some(inputs) = inputs {
-            world_builder = world_builder.with_inputs(inputs);
+    /// Enable an experimental Typst feature, such as [`Feature::Html`] for the `html`
+    /// module.
+    ///
+    /// typst-as-lib builds Typst's standard library with no features enabled. With any
+    /// feature set, the document compiles against its own library instead, with the same
+    /// `sys.inputs`. Documents are still laid out as pages, so features that only affect
+    /// HTML export change what templates can call, not the output format.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// use typst_bake::Feature;
+    ///
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .with_feature(Feature::Html)
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_feature(mut self, feature: Feature) -> Self {
+        if !self.features.contains(&feature) {
+            self.features.push(feature);
         }
-        // A build failure is an input-injection error, not a source diagnostic; preserve its
-        // message in a location-less diagnostic.
-        let world = world_builder.build().map_err(|e| {
-            Error::Compilation(vec![Diagnostic {
-                location: None,
-                message: e.to_string(),
-                hints: Vec::new(),
-                trace: Vec::new(),
-            }])
-        })?;
-
-        let warned = typst::compile::<PagedDocument>(&world);
-        // Replicate the engine's default eviction policy (`Some(0)`); `world_builder` does not
-        // evict automatically. The comemo cache is global, so don't enlarge this blindly.
-        typst::comemo::evict(0);
-
-        let main = world.main();
-        let compiled = warned.output.map_err(|diagnostics| {
-            Error::Compilation(
-                diagnostics
-                    .iter()
-                    .map(|d| diagnostic_from(&world, self.entry, main, d))
-                    .collect(),
-            )
-        })?;
-
-        *self.lock_cache() = Some(compiled);
-
-        Ok(())
+        *self.lock_cache() = None;
+        self
     }
 
-    /// Compile if needed, then call `f` with a reference to the compiled document.
-    fn with_compiled<F, T>(&self, f: F) -> Result<T>
-    where
-        F: FnOnce(&PagedDocument) -> Result<T>,
-    {
-        self.compile_cached()?;
-        let cache = self.lock_cache();
-        let compiled = cache
-            .as_ref()
-            .expect("compiled_cache must be Some after successful compile_cached()");
-        f(compiled)
+    /// Render another embedded file when the entry fails to compile.
+    ///
+    /// The fallback gets the same inputs and builder settings as the entry, plus
+    /// `sys.inputs.error`: the formatted diagnostics as a string. It can show a polite
+    /// "document unavailable" page with whatever inputs are still usable, so production
+    /// never serves an empty response. The entry's errors are returned first by
+    /// [`warnings`](Self::warnings), with [`Severity::Error`]. Strict mode
+    /// ([`with_strict`](Self::with_strict)) failures also switch to the fallback. If the
+    /// fallback fails too, its errors are returned, or shown by
+    /// [`with_error_document`](Self::with_error_document) if enabled.
+    ///
+    /// # Errors
+    /// Returns [`Error::EntryNotFound`] if no such file is embedded.
+    ///
+    /// # Example
+    /// ```typ
+    /// // error.typ
+    /// = Invoice #sys.inputs.at("number", default: "")
+    /// This invoice could not be generated. Please contact support.
+    /// ```
+    ///
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_inputs(invoice)
+    ///     .with_fallback("error.typ")?
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_fallback(mut self, entry: impl Into<String>) -> Result<Self> {
+        let entry = entry.into();
+        if find_entry(self.template_root(), &entry).is_none() {
+            return Err(Error::EntryNotFound(entry));
+        }
+        self.fallback = Some(entry);
+        *self.lock_cache() = None;
+        Ok(self)
     }
 
-    /// Compile the document and generate PDF.
+    /// Render compilation errors as a document instead of failing.
     ///
-    /// # Returns
-    /// PDF data as bytes.
+    /// With `enabled` set, a template that fails to compile produces a page listing the
+    /// diagnostics, in every output format, so a pipeline that must always deliver a file
+    /// gives operators something to look at. The errors are also returned first by
+    /// [`warnings`](Self::warnings), with [`Severity::Error`], to detect the case in code.
+    /// Errors outside the template, such as [`Error::EntryNotFound`] or decompression
+    /// limits, still fail as usual.
     ///
-    /// # Errors
-    /// Returns an error if compilation or PDF generation fails.
-    #[cfg(feature = "pdf")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
-    pub fn to_pdf(&self) -> Result<Vec<u8>> {
-        self.render_pdf(None)
+    /// The page is set in the embedded fonts; without any, its text is invisible.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("report.typ")
+    ///     .with_inputs(inputs)
+    ///     .with_error_document(true);
+    /// let pdf = doc.to_pdf()?;
+    /// if doc.warnings()?.iter().any(|d| d.severity == typst_bake::Severity::Error) {
+    ///     alert_operators();
+    /// }
+    /// ```
+    pub fn with_error_document(mut self, enabled: bool) -> Self {
+        self.error_document = enabled;
+        *self.lock_cache() = None;
+        self
     }
 
-    /// Compile the document and generate SVG for each page.
+    /// Keep Typst's memoized work for reuse by later compiles.
     ///
-    /// # Returns
-    /// A vector of SVG strings, one per page.
+    /// Typst memoizes evaluation and layout. By default the cache is cleared after every
+    /// compile, which keeps memory flat but makes each render start cold. With
+    /// `max_age > 0`, results stay cached until they have gone unused for `max_age`
+    /// compiles, so a service rendering the same template with different inputs reuses
+    /// everything that does not depend on them (page backgrounds, fixed text, package
+    /// code). The compiled output is identical either way.
     ///
-    /// # Errors
-    /// Returns an error if compilation fails.
-    #[cfg(feature = "svg")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "svg")))]
-    pub fn to_svg(&self) -> Result<Vec<String>> {
-        self.render_svg(None)
+    /// This includes decoded raster images: Typst memoizes decoding by the image's bytes,
+    /// so a large background image is decoded once and reused by every later compile
+    /// that shows it, across all documents from the same bundle.
+    ///
+    /// The memoization cache is process-global and shared by all documents, and so is
+    /// eviction: every compile evicts with its own document's setting. Any other
+    /// [`Document`] left on the default of 0 therefore clears the whole cache, including
+    /// this document's results, each time it compiles. Set the same retention on every
+    /// document in the process to keep results across compiles. Memory grows with
+    /// `max_age`; measure with your own templates to pick a value.
+    /// In [`render_each`](Self::render_each), which evicts once per round of documents
+    /// rather than per compile, this sets how many rounds unused results survive.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_memo_retention(5)
+    ///     .with_inputs(invoice)
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_memo_retention(mut self, max_age: usize) -> Self {
+        self.memo_retention = Some(max_age);
+        self
     }
 
-    /// Compile the document and generate PNG for each page.
+    /// Serve repeated exports from a cache of up to `capacity` outputs.
     ///
-    /// # Arguments
-    /// * `dpi` - Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print)
+    /// [`to_pdf`](Self::to_pdf), [`to_svg`](Self::to_svg), [`to_png`](Self::to_png), and
+    /// [`to_png_with`](Self::to_png_with) store their output under the document's
+    /// [`fingerprint`](Self::fingerprint) and export options. A later export with the same
+    /// key returns a copy without compiling, so a web service answering identical requests
+    /// renders each one once.
     ///
-    /// # Returns
-    /// A vector of PNG bytes, one per page.
+    /// The cache is process-wide and shared by every document created from the same
+    /// embedded bundle, including documents created by later `document!` calls; the least
+    /// recently used output is dropped when it is full. `capacity` counts outputs, not
+    /// bytes, and the most recent call sets it for the whole bundle. `0` disables caching.
+    ///
+    /// Without [`with_now`](Self::with_now), the date a template reads with
+    /// `datetime.today()` is not part of the key, so cached output keeps the date of its
+    /// first render for as long as it stays in the cache. Set the clock, e.g. to the
+    /// current day, when templates print the date.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // In a request handler: the second identical request skips compilation.
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_cache(256)
+    ///     .with_inputs(invoice)
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        let cache = RenderCache::shared(self.content_hash, capacity);
+        self.render_cache = (capacity > 0).then_some(cache);
+        self
+    }
+
+    /// Run `render` unless the render cache already holds its output for `format`.
+    ///
+    /// Output limits and strict mode are part of the key: a document with tighter limits,
+    /// or one that fails on warnings, must not be served output that only passed looser
+    /// checks.
+    #[cfg(any(feature = "pdf", feature = "svg", feature = "png"))]
+    fn cached<T: Clone + Send + Sync + 'static>(
+        &self,
+        format: String,
+        render: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let Some(cache) = &self.render_cache else {
+            return render();
+        };
+        let key = (
+            self.fingerprint(),
+            format!(
+                "{format} {:?} {:?} {}",
+                self.max_pages, self.max_output_size, self.strict
+            ),
+        );
+        if let Some(output) = cache.get(&key) {
+            return Ok(output);
+        }
+        let output = render()?;
+        cache.insert(key, output.clone());
+        Ok(output)
+    }
+
+    /// Decompress the embedded fonts and build their aliased copies into the cache shared
+    /// with forks, so the first compile of every fork skips it.
+    pub(crate) fn warm_fonts(&self) -> Result<()> {
+        let budget = DecompressionBudget::new(self.decompression_limits);
+        let fonts = self.font_data(&budget)?;
+        self.aliased_fonts(&fonts);
+        Ok(())
+    }
+
+    /// The eviction age to apply once a batch rendered from this document is done.
+    pub(crate) fn batch_retention(&self) -> usize {
+        self.memo_retention.unwrap_or(0)
+    }
+
+    /// Make an embedded font family available under another name.
+    ///
+    /// Templates asking for `alias` (e.g. a proprietary font like "Helvetica" that cannot
+    /// be embedded) render with the embedded `family` instead of an arbitrary fallback.
+    /// Names are matched case-insensitively. Aliases configured with `font-aliases` in
+    /// `Cargo.toml` are applied automatically by the [`document!`](crate::document!)
+    /// macro. Font collections (`.ttc`) cannot be alias targets.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("legacy.typ")
+    ///     .with_font_alias("Helvetica", "Inter")
+    ///     .with_font_alias("Times New Roman", "Source Serif 4");
+    /// ```
+    pub fn with_font_alias(mut self, alias: impl Into<String>, family: impl Into<String>) -> Self {
+        self.font_aliases.push((alias.into(), family.into()));
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Evaluate Typst code before the entry file.
+    ///
+    /// The prelude is placed in front of the entry's source, so its `#set` and `#show`
+    /// rules apply to the whole document and its `#let` bindings are visible in the
+    /// entry. Small per-render style tweaks then don't need a separate template file per
+    /// variant. Calling this again appends another prelude. Diagnostics inside a prelude
+    /// are reported with the file name `<prelude>`.
+    ///
+    /// Unlike [inputs](Self::with_inputs), a prelude is Typst code: never build it from
+    /// untrusted strings.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ")
+    ///     .with_prelude("#set text(lang: \"de\")\n#let brand-color = rgb(\"#0a5\")");
+    /// ```
+    pub fn with_prelude(mut self, code: impl Into<String>) -> Self {
+        self.preludes.push(code.into());
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Set the document language, as `#set text(lang: ..)` would.
+    ///
+    /// Takes an ISO 639-1/2/3 code such as `"ko"` or `"de"`. Language drives hyphenation,
+    /// smart quotes, and localized terms (e.g. "Figure"), so multi-locale services can
+    /// pick it per request. Applied before the entry and any [prelude](Self::with_prelude),
+    /// so `set text(lang: ..)` in the template still takes precedence. An invalid code
+    /// fails compilation with a diagnostic in `<prelude>`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_lang("de")
+    ///     .with_region("AT")
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Set the document region, as `#set text(region: ..)` would.
+    ///
+    /// Takes an ISO 3166-1 alpha-2 code such as `"KR"` or `"CH"`, refining
+    /// language-dependent behavior. See [`with_lang`](Self::with_lang).
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Set the document title, as `#set document(title: ..)` would.
+    ///
+    /// The title goes into the PDF metadata, where viewers show it in place of the file
+    /// name, so a template shared by many documents doesn't have to read it from inputs
+    /// just to call `set document`. Like [`with_lang`](Self::with_lang), it is applied
+    /// before the entry, so a `set document` rule in the template takes precedence.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_title(format!("Invoice {number}"))
+    ///     .with_author("ACME Corp.")
+    ///     .with_keywords(["invoice", "2026"])
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Add a document author, as `#set document(author: ..)` would.
+    ///
+    /// Calling this again adds another author. See [`with_title`](Self::with_title).
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.authors.push(author.into());
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Set the document keywords, as `#set document(keywords: ..)` would.
+    ///
+    /// Replaces previously set keywords. See [`with_title`](Self::with_title).
+    pub fn with_keywords<S: Into<String>>(mut self, keywords: impl IntoIterator<Item = S>) -> Self {
+        self.keywords = keywords.into_iter().map(Into::into).collect();
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Stamp `text` diagonally across every page, e.g. `"DRAFT"` or `"CONFIDENTIAL"`.
+    ///
+    /// The stamp is large, bold, and translucent gray, drawn over the page content, so
+    /// per-environment markings need no change to the baked templates. For a custom
+    /// design, use [`with_overlay_template`](Self::with_overlay_template). Replaces any
+    /// previous overlay.
+    ///
+    /// The overlay is set as the page foreground before the entry runs, so a template that
+    /// sets its own `page(foreground: ..)` replaces it.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let mut doc = typst_bake::document!("contract.typ");
+    /// if env != "production" {
+    ///     doc = doc.with_overlay("DRAFT");
+    /// }
+    /// ```
+    pub fn with_overlay(mut self, text: impl Into<String>) -> Self {
+        self.overlay = Some(Overlay::Text(text.into()));
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Draw an embedded Typst file over every page.
+    ///
+    /// The file's content becomes the page foreground, positioned at the top-left corner
+    /// of the page; use `place` inside it to position the stamp. The path is resolved
+    /// like [`read_template`](Self::read_template) paths, and runtime files added with
+    /// [`add_file`](Self::add_file) work too. Otherwise this behaves like
+    /// [`with_overlay`](Self::with_overlay).
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidFilePath`] if the path is empty, absolute, or contains
+    /// `..` segments. A missing file fails compilation.
+    ///
+    /// # Example
+    /// ```typ
+    /// // stamp.typ
+    /// #place(top + right, dx: -1cm, dy: 1cm, text(red, size: 14pt)[CONFIDENTIAL])
+    /// ```
+    ///
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("report.typ")
+    ///     .with_overlay_template("stamp.typ")?
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_overlay_template(mut self, path: impl AsRef<str>) -> Result<Self> {
+        let path = validate_file_path(path.as_ref())?;
+        self.overlay = Some(Overlay::Template(path));
+        *self.lock_cache() = None;
+        Ok(self)
+    }
+
+    /// Choose whether headings become PDF bookmarks, as `#set heading(bookmarked: ..)`
+    /// would.
+    ///
+    /// By default a heading is bookmarked if it appears in the table of contents
+    /// (`outlined: true`, the default). `false` produces a PDF without an outline panel,
+    /// e.g. for one-page documents whose viewer shouldn't open the sidebar; `true`
+    /// bookmarks every heading, including those kept out of the table of contents. Like
+    /// [`with_lang`](Self::with_lang), this applies before the entry, so the template's
+    /// own `set heading` rules and per-heading `bookmarked` arguments still take
+    /// precedence.
+    ///
+    /// To add bookmarks, add headings in the template; `#show heading: none` on a heading
+    /// keeps its bookmark without printing it.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("letter.typ")
+    ///     .with_bookmarks(false)
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_bookmarks(mut self, bookmarks: bool) -> Self {
+        self.bookmarks = Some(bookmarks);
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Make `datetime.today()` in templates return the date of `now`.
+    ///
+    /// By default `datetime.today()` reads the wall clock, so the same inputs render
+    /// differently from one day to the next. A fixed instant makes renders reproducible
+    /// (e.g. in snapshot tests) and lets you backdate documents. The date is taken in UTC,
+    /// or at the offset passed as `datetime.today(offset: ..)`.
+    ///
+    /// This only affects the template; for the PDF creation date, see
+    /// `PdfConfig::timestamp`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let issued = UNIX_EPOCH + Duration::from_secs(1_767_225_600); // 2026-01-01
+    /// let pdf = typst_bake::document!("invoice.typ")
+    ///     .with_now(issued)
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_now(mut self, now: SystemTime) -> Self {
+        self.now = Some(now);
+        *self.lock_cache() = None;
+        self
+    }
+
+    /// Render a different embedded file as the entry.
+    ///
+    /// Every `.typ` file in the template directory is embedded, so one `document!` bundle
+    /// can serve several top-level templates (an invoice, a reminder, a receipt) that
+    /// share assets and packages, instead of one macro call per entry embedding it all
+    /// again. The path is resolved like [`read_template`](Self::read_template) paths.
+    /// Other builder settings, including the inputs, are kept.
+    ///
+    /// # Errors
+    /// Returns [`Error::EntryNotFound`] if no such file is embedded. Runtime files added
+    /// with [`add_file`](Self::add_file) can't serve as the entry.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let bundle = typst_bake::document!("invoice.typ").with_inputs(customer);
+    /// let reminder = bundle.with_entry("reminder.typ")?.to_pdf()?;
+    /// ```
+    pub fn with_entry(mut self, entry: impl Into<String>) -> Result<Self> {
+        let entry = entry.into();
+        if find_entry(self.template_root(), &entry).is_none() {
+            return Err(Error::EntryNotFound(entry));
+        }
+        self.entry = entry;
+        *self.lock_cache() = None;
+        Ok(self)
+    }
+
+    /// Use one of several embedded template sets.
+    ///
+    /// With `template-sets = true` in `[package.metadata.typst-bake]`, every subdirectory
+    /// of `template-dir` is a template set holding its own copy of the entry file, e.g.
+    /// `tenants/acme/main.typ` and `tenants/globex/main.typ` with `template-dir =
+    /// "./tenants"`. The macro checks that each set has the entry. This method picks the
+    /// set to render: its directory becomes the root for the entry, `/`-prefixed paths,
+    /// [`has_file`](Self::has_file), and [`read_template`](Self::read_template). Until it
+    /// is called, the set named by `default-template-set` is used.
+    ///
+    /// Calling it again switches sets; names are always relative to `template-dir`.
+    ///
+    /// # Errors
+    /// Returns [`Error::TemplateSetNotFound`] if `name` is not one of the template sets
+    /// embedded by the macro, including whenever `template-sets` is off.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .with_template_set(&tenant.slug)?
+    ///     .to_pdf()?;
+    /// ```
+    pub fn with_template_set(mut self, name: impl Into<String>) -> Result<Self> {
+        let name = normalize_file_path(&name.into());
+        if !self.template_sets.contains(&name.as_str()) {
+            return Err(Error::TemplateSetNotFound(name));
+        }
+        self.template_set = Some(name);
+        *self.lock_cache() = None;
+        Ok(self)
+    }
+
+    /// The directory templates resolve from: the selected template set, if any.
+    fn template_root(&self) -> &'static Dir<'static> {
+        self.template_set
+            .as_deref()
+            .and_then(|name| find_dir(self.templates, name))
+            .unwrap_or(self.templates)
+    }
+
+    /// Check if a file exists at the given path.
+    ///
+    /// Checks both embedded (compile-time) and runtime files.
+    pub fn has_file(&self, path: impl AsRef<str>) -> bool {
+        let normalized = normalize_file_path(path.as_ref());
+
+        // Check runtime files first.
+        if self.lock_runtime_files().contains_key(&normalized) {
+            return true;
+        }
+
+        // Check embedded templates.
+        if find_entry(self.template_root(), &normalized).is_some() {
+            return true;
+        }
+
+        false
+    }
+
+    /// Read an embedded template file, decompressed.
+    ///
+    /// Paths are relative to `template-dir`, like the entry file. Useful for
+    /// configuration kept next to the `.typ` files that the Rust side also needs.
+    /// Runtime files added with [`add_file`](Self::add_file) are not consulted.
+    ///
+    /// # Errors
+    /// Returns [`Error::FileNotFound`] if no such file was embedded.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ");
+    /// let fields: serde_json::Value = serde_json::from_slice(&doc.read_template("schema/fields.json")?)?;
+    /// ```
+    pub fn read_template(&self, path: impl AsRef<str>) -> Result<Vec<u8>> {
+        let normalized = normalize_file_path(path.as_ref());
+        let file = find_entry(self.template_root(), &normalized)
+            .ok_or_else(|| Error::FileNotFound(normalized.clone()))?;
+        DecompressionBudget::new(self.decompression_limits).decompress(&normalized, file.contents())
+    }
+
+    /// Select specific pages for output, returning a [`Pages`] view.
+    ///
+    /// Pages are 0-indexed. Duplicates are removed and pages are always
+    /// output in document order regardless of input order.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidPageSelection`] at render time if any index
+    /// is out of range or the selection is empty.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // Select specific pages
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .select_pages([0, 2, 4])
+    ///     .to_pdf()?;
+    ///
+    /// // Works with ranges too
+    /// let svgs = typst_bake::document!("main.typ")
+    ///     .select_pages(0..3)
+    ///     .to_svg()?;
+    ///
+    /// // Reuse with different selections
+    /// let doc = typst_bake::document!("main.typ");
+    /// let cover = doc.select_pages([0]).to_pdf()?;
+    /// let body = doc.select_pages(1..5).to_pdf()?;
+    /// ```
+    pub fn select_pages(&self, pages: impl IntoIterator<Item = usize>) -> Pages<'_> {
+        Pages {
+            doc: self,
+            indices: pages.into_iter().collect(),
+        }
+    }
+
+    /// Get the total number of pages in the compiled document.
+    ///
+    /// Compiles the document if not already compiled.
+    /// Returns the total page count regardless of `select_pages`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ");
+    /// let count = doc.page_count()?;
+    /// let last_page = doc.select_pages([count - 1]).to_pdf()?;
+    /// ```
+    pub fn page_count(&self) -> Result<usize> {
+        self.with_compiled(|compiled| Ok(compiled.pages.len()))
+    }
+
+    /// Warnings Typst reported while compiling the document.
+    ///
+    /// Compiles the document if not already compiled. Warnings don't stop rendering, so
+    /// they are easy to miss: unknown font families, unused labels, and similar. Log them,
+    /// or fail a CI check when the list is not empty.
+    ///
+    /// When [`with_fallback`](Self::with_fallback) or
+    /// [`with_error_document`](Self::with_error_document) replaced a failed compile, the
+    /// list also holds its errors, with [`Severity::Error`].
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ").with_inputs(inputs);
+    /// for warning in doc.warnings()? {
+    ///     log::warn!("{warning}");
+    /// }
+    /// let pdf = doc.to_pdf()?; // reuses the compiled document
+    /// ```
+    pub fn warnings(&self) -> Result<Vec<Diagnostic>> {
+        self.compile_cached()?;
+        Ok(self.warnings.lock().expect("lock poisoned").clone())
+    }
+
+    /// Extract the plain text of each page.
+    ///
+    /// Compiles the document if not already compiled. Text is read from the laid-out
+    /// pages, line by line in reading order, so it matches what the PDF shows, including
+    /// page numbers and headers, without running a PDF text extractor. Text inside images
+    /// and formatting such as bold or links are not included.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("invoice.typ").with_inputs(invoice);
+    /// let pdf = doc.to_pdf()?;
+    /// search_index.add(&invoice.id, &doc.to_text()?.join("\n"));
+    /// ```
+    pub fn to_text(&self) -> Result<Vec<String>> {
+        self.with_compiled(|compiled| {
+            Ok(compiled
+                .pages
+                .iter()
+                .map(|page| frames::plain_text(&page.frame))
+                .collect())
+        })
+    }
+
+    /// List embedded font files that contributed no glyphs to the compiled document.
+    ///
+    /// Compiles the document if not already compiled. Returns the paths of font files
+    /// (relative to `fonts-dir`) none of whose faces were used for any text, which makes
+    /// them candidates for removal. The result only reflects the current inputs: a font
+    /// unused here may still be needed by other data, so check with representative inputs.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ").with_inputs(sample_inputs());
+    /// for path in doc.unused_fonts()? {
+    ///     println!("unused font: {path}");
+    /// }
+    /// ```
+    pub fn unused_fonts(&self) -> Result<Vec<String>> {
+        let used = self.with_compiled(|compiled| Ok(frames::used_faces(compiled)))?;
+        let budget = DecompressionBudget::new(self.decompression_limits);
+
+        let mut unused = Vec::new();
+        for file in self.fonts.files() {
+            let path = file.path().display().to_string();
+            let data = budget.decompress(&path, file.contents())?;
+            let mut faces = Font::iter(Bytes::new(data));
+            let is_used = |font: Font| {
+                let (family, variant) = frames::face_key(&font);
+                used.contains(&(family.clone(), variant))
+                    || used.iter().any(|(alias, v)| {
+                        *v == variant && font_alias::is_alias_of(&self.font_aliases, alias, &family)
+                    })
+            };
+            if !faces.any(is_used) {
+                unused.push(path);
+            }
+        }
+        Ok(unused)
+    }
+
+    /// List the embedded template files.
+    ///
+    /// Covers the template root (the selected template set, if any) recursively, sorted by
+    /// path, without runtime files. Each file is decompressed to report its size, so this
+    /// is meant for startup checks, e.g. that expected logos and partials made it into the
+    /// binary, rather than per-request use.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let files = typst_bake::document!("main.typ").files()?;
+    /// assert!(files.iter().any(|f| f.path == "assets/logo.svg"));
+    /// ```
+    pub fn files(&self) -> Result<Vec<TemplateFile>> {
+        let budget = DecompressionBudget::new(self.decompression_limits);
+        inventory::files(self.template_root())
+            .into_iter()
+            .map(|(path, file)| {
+                let original_size = budget.decompress(&path, file.contents())?.len();
+                Ok(TemplateFile {
+                    path,
+                    original_size,
+                    compressed_size: file.contents().len(),
+                })
+            })
+            .collect()
+    }
+
+    /// List the embedded font faces.
+    ///
+    /// Reads the font files directly, without compiling the document, and reports each
+    /// face the way Typst's font book sees it: the family name to use in
+    /// `text(font: ..)`, style, weight, stretch, and how many code points it covers.
+    /// Files Typst cannot parse are left out.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// for face in typst_bake::document!("main.typ").fonts()? {
+    ///     println!("{} {:?} {} ({} code points)", face.family, face.style, face.weight, face.codepoints);
+    /// }
+    /// ```
+    pub fn fonts(&self) -> Result<Vec<FontFace>> {
+        let budget = DecompressionBudget::new(self.decompression_limits);
+
+        let mut faces = Vec::new();
+        for file in self.fonts.files() {
+            let path = file.path().display().to_string();
+            let data = budget.decompress(&path, file.contents())?;
+            faces.extend(
+                Font::iter(Bytes::new(data)).map(|font| FontFace::new(path.clone(), &font)),
+            );
+        }
+        Ok(faces)
+    }
+
+    /// List the embedded Typst packages.
+    ///
+    /// Covers every package the templates import, including transitive dependencies,
+    /// sorted by namespace, name, and version. License, authors, and repository are
+    /// read from each package's `typst.toml` for auditing third-party code in the binary.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// for pkg in typst_bake::document!("main.typ").packages()? {
+    ///     println!("{} ({})", pkg.spec(), pkg.license.as_deref().unwrap_or("unknown"));
+    /// }
+    /// ```
+    pub fn packages(&self) -> Result<Vec<EmbeddedPackage>> {
+        let budget = DecompressionBudget::new(self.decompression_limits);
+
+        let mut packages = Vec::new();
+        for namespace in self.packages.dirs() {
+            let namespace_name = inventory::dir_name(namespace);
+            for name in namespace.dirs() {
+                let package_name = inventory::dir_name(name);
+                for version in name.dirs() {
+                    let manifest = find_entry(version, "typst.toml")
+                        .map(|file| budget.decompress("typst.toml", file.contents()))
+                        .transpose()?;
+                    packages.push(EmbeddedPackage::new(
+                        &namespace_name,
+                        &package_name,
+                        version,
+                        manifest.as_deref(),
+                    ));
+                }
+            }
+        }
+        Ok(packages)
+    }
+
+    /// Get compression statistics for embedded content.
+    pub fn stats(&self) -> &EmbedStats {
+        &self.stats
+    }
+
+    /// A stable hash of everything that determines the compiled document.
+    ///
+    /// Covers the embedded templates, packages, and fonts, the entry file, inputs,
+    /// runtime files, preludes, font aliases, and every builder setting that affects
+    /// compilation: language and region, document metadata, overlays, bookmarks, PDF
+    /// attachments, the [fixed clock](Self::with_now), and what replaces a failed compile.
+    /// Two documents with the same fingerprint compile to the same pages, so the value can
+    /// serve as an ETag or cache key without rendering. That holds only with a fixed clock:
+    /// without [`with_now`](Self::with_now), a template calling `datetime.today()` reads the
+    /// system clock and can differ between compiles that share a fingerprint.
+    ///
+    /// Export options such as the PDF configuration or PNG resolution are not included; add
+    /// them to the key yourself when they vary. Fingerprints are stable across runs and
+    /// machines, but not across typst-bake versions.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let etag = doc.fingerprint();
+    /// if request_etag == etag {
+    ///     return not_modified();
+    /// }
+    /// ```
+    pub fn fingerprint(&self) -> String {
+        let runtime_files = self.lock_runtime_files();
+        let mut runtime_files: Vec<_> = runtime_files.iter().collect();
+        runtime_files.sort();
+
+        let hash = typst::utils::hash128(&(
+            self.content_hash,
+            &self.entry,
+            &*self.lock_inputs(),
+            &self.env_inputs,
+            runtime_files,
+            &self.preludes,
+            self.generated_prelude(),
+            &self.font_aliases,
+            &self.template_set,
+            &self.features,
+            self.now,
+            (&self.fallback, self.error_document),
+        ));
+        format!("{hash:032x}")
+    }
+
+    /// Typst code for builder options that map onto set rules, evaluated before the
+    /// user's preludes.
+    fn generated_prelude(&self) -> Vec<String> {
+        let mut text_args = Vec::new();
+        if let Some(lang) = &self.lang {
+            text_args.push(format!("lang: {}", typst_str(lang)));
+        }
+        if let Some(region) = &self.region {
+            text_args.push(format!("region: {}", typst_str(region)));
+        }
+
+        let mut document_args = Vec::new();
+        if let Some(title) = &self.title {
+            document_args.push(format!("title: {}", typst_str(title)));
+        }
+        if !self.authors.is_empty() {
+            document_args.push(format!("author: {}", typst_array(&self.authors)));
+        }
+        if !self.keywords.is_empty() {
+            document_args.push(format!("keywords: {}", typst_array(&self.keywords)));
+        }
+
+        let mut prelude = Vec::new();
+        if !text_args.is_empty() {
+            prelude.push(format!("#set text({})", text_args.join(", ")));
+        }
+        if !document_args.is_empty() {
+            prelude.push(format!("#set document({})", document_args.join(", ")));
+        }
+        match &self.overlay {
+            Some(Overlay::Text(text)) => prelude.push(format!(
+                "#set page(foreground: place(center + horizon, rotate(-45deg, \
+                 text(size: 72pt, weight: \"bold\", fill: luma(50%).transparentize(70%), {}))))",
+                typst_str(text)
+            )),
+            Some(Overlay::Template(path)) => prelude.push(format!(
+                "#set page(foreground: include {})",
+                typst_str(&format!("/{path}"))
+            )),
+            None => {}
+        }
+        if let Some(bookmarks) = self.bookmarks {
+            prelude.push(format!("#set heading(bookmarked: {bookmarks})"));
+        }
+        for (name, mime_type) in &self.attachments {
+            prelude.push(format!(
+                "#pdf.attach({}, read({}, encoding: none), mime-type: {})",
+                typst_str(name),
+                typst_str(&format!("/{ATTACHMENT_DIR}/{name}")),
+                typst_str(mime_type),
+            ));
+        }
+        prelude
+    }
+
+    /// Compile the document, reusing the cached result if available.
+    ///
+    /// The cache lock is held while compiling, so concurrent first renders compile once
+    /// and the others wait for the result.
+    fn compile_cached(&self) -> Result<()> {
+        let mut cache = self.lock_cache();
+        let cached = cache.is_some();
+        #[cfg(feature = "metrics")]
+        metrics::record_compile_cache(cached);
+        if cached {
+            return Ok(());
+        }
+
+        let mut result =
+            self.compile_entry(&self.entry, None, None)
+                .and_then(|(compiled, warnings)| {
+                    if self.strict && !warnings.is_empty() {
+                        return Err(Error::Compilation(warnings));
+                    }
+                    Ok((compiled, warnings))
+                });
+
+        // Errors that a fallback or the error page stands in for are kept in front of the
+        // warnings, so callers can tell the substitute from the real document.
+        let mut failures = Vec::new();
+        if let Some(fallback) = &self.fallback {
+            if let Err(Error::Compilation(errors)) = result {
+                result = self.compile_entry(fallback, None, Some(&error_report(&errors)));
+                failures.extend(errors);
+            }
+        }
+        if self.error_document {
+            if let Err(Error::Compilation(errors)) = result {
+                failures.extend(errors);
+                result = self.compile_entry(&self.entry, Some(&error_source(&failures)), None);
+            }
+        }
+        let (compiled, mut warnings) = result?;
+        warnings.splice(0..0, failures);
+
+        if let Some(limit) = self.max_pages {
+            let count = compiled.pages.len();
+            if count > limit {
+                return Err(Error::TooManyPages { count, limit });
+            }
+        }
+
+        *self.warnings.lock().expect("lock poisoned") = warnings;
+        *cache = Some(Arc::new(compiled));
+
+        Ok(())
+    }
+
+    /// Compile `entry` with the document's settings, returning the pages and warnings.
+    ///
+    /// `replacement` stands in for the entry's source, preludes included, e.g. for the
+    /// error page of [`Document::with_error_document`]. `error` is passed to the template
+    /// as `sys.inputs.error`, for [`Document::with_fallback`].
+    fn compile_entry(
+        &self,
+        entry: &str,
+        replacement: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<(PagedDocument, Vec<Diagnostic>)> {
+        trace_span!(INFO, "compile", entry);
+        let budget = Arc::new(DecompressionBudget::new(self.decompression_limits));
+        let (main_source, prelude_lines) = match replacement {
+            Some(source) => (source.to_owned(), 0),
+            None => {
+                // Read main template content (compressed)
+                let main_file = find_entry(self.template_root(), entry)
+                    .ok_or_else(|| Error::EntryNotFound(entry.to_owned()))?;
+                let main_bytes = budget.decompress(entry, main_file.contents())?;
+                let main_content =
+                    std::str::from_utf8(&main_bytes).map_err(|_| Error::InvalidUtf8)?;
+
+                // Preludes go in front of the entry's own source (one per line block), so
+                // their bindings are in scope and relative paths still resolve against the
+                // entry.
+                let prelude: String = self
+                    .generated_prelude()
+                    .iter()
+                    .chain(&self.preludes)
+                    .map(|p| format!("{p}\n"))
+                    .collect();
+                let prelude_lines = prelude.matches('\n').count();
+                (format!("{prelude}{main_content}"), prelude_lines)
+            }
+        };
+
+        // Clone inputs (preserve for retry on failure)
+        let mut inputs = self.lock_inputs().clone();
+        if let Some(env) = &self.env_inputs {
+            inputs
+                .get_or_insert_with(Dict::new)
+                .insert("env".into(), env.clone().into_value());
+        }
+        if let Some(error) = error {
+            inputs
+                .get_or_insert_with(Dict::new)
+                .insert("error".into(), Str::from(error).into_value());
+        }
+
+        let mut resolver =
+            EmbeddedResolver::new(self.template_root(), self.packages, budget.clone());
+        for (path, data) in self.lock_runtime_files().iter() {
+            resolver.insert_runtime_file(path.clone(), data.clone());
+        }
+        // Reserved files go last; `add_file` refuses their directory anyway.
+        resolver.insert_runtime_file(
+            markup::HELPER_PATH.to_owned(),
+            markup::HELPER_SOURCE.as_bytes().to_vec(),
+        );
+        for (path, data) in inputs.iter().flat_map(markup::verified_sources) {
+            resolver.insert_runtime_file(path, data);
+        }
+
+        let font_data = self.font_data(&budget)?;
+        let aliased = self.aliased_fonts(&font_data);
+
+        let font_refs: Vec<&[u8]> = font_data
+            .iter()
+            .map(|(_, data)| data.as_slice())
+            .chain(aliased.iter().map(Vec::as_slice))
+            .collect();
+
+        let engine = TypstEngine::builder()
+            .main_file((entry, main_source.as_str()))
+            .add_file_resolver(resolver)
+            .fonts(font_refs)
+            .build();
+
+        // Drive the world directly (mirrors typst-as-lib's internal `do_compile`) so the
+        // `World` stays in scope to resolve diagnostic spans into source locations.
+        let mut world_builder = engine.world_builder();
+        // typst-as-lib builds the library without features; with any set, it is rebuilt
+        // below with the same inputs.
+        let library_inputs =
+            (!self.features.is_empty()).then(|| inputs.clone().unwrap_or_default());
+        if let Some(inputs) = inputs {
+            world_builder = world_builder.with_inputs(inputs);
+        }
+        // A build failure is an input-injection error, not a source diagnostic; preserve its
+        // message in a location-less diagnostic.
+        let world = world_builder.build().map_err(|e| {
+            Error::Compilation(vec![Diagnostic {
+                severity: Severity::Error,
+                location: None,
+                message: e.to_string(),
+                hints: Vec::new(),
+                trace: Vec::new(),
+            }])
+        })?;
+
+        let with_library =
+            library_inputs.map(|inputs| WithLibrary::new(&world, inputs, &self.features));
+        let compiled_world: &dyn World = match &with_library {
+            Some(with_library) => with_library,
+            None => &world,
+        };
+        let warned = match self.now {
+            Some(now) => typst::compile::<PagedDocument>(&FixedClock {
+                world: compiled_world,
+                now,
+            }),
+            None => typst::compile::<PagedDocument>(compiled_world),
+        };
+        // `world_builder` does not evict automatically. The default (`Some(0)`) replicates
+        // the engine's own policy; the comemo cache is global, so larger retentions are
+        // opt-in via `with_memo_retention`.
+        if let Some(max_age) = self.memo_retention {
+            typst::comemo::evict(max_age);
+        }
+
+        // A limit hit inside the resolver only reaches Typst as a file error; report the
+        // typed error instead of the resulting diagnostics.
+        if let Some(err) = budget.exceeded() {
+            return Err(err);
+        }
+
+        let main = MainFile {
+            path: entry,
+            id: world.main(),
+            prelude_lines,
+        };
+        let compiled = warned.output.map_err(|diagnostics| {
+            Error::Compilation(
+                diagnostics
+                    .iter()
+                    .map(|d| diagnostic_from(&world, &main, d))
+                    .collect(),
+            )
+        })?;
+
+        let warnings = warned
+            .warnings
+            .iter()
+            .map(|d| diagnostic_from(&world, &main, d))
+            .collect();
+        Ok((compiled, warnings))
+    }
+
+    /// The decompressed embedded fonts, from the cache if an earlier compile (of this
+    /// document or a fork) filled it. Reused fonts are charged to `budget` so the limits
+    /// apply the same either way.
+    fn font_data(&self, budget: &DecompressionBudget) -> Result<FontData> {
+        let mut cache = self.font_cache.lock().expect("lock poisoned");
+        if let Some(fonts) = &cache.fonts {
+            for (path, data) in fonts.iter() {
+                budget.charge(path, data.len())?;
+            }
+            return Ok(fonts.clone());
+        }
+
+        let fonts: FontData = Arc::new(
+            self.fonts
+                .files()
+                .map(|f| {
+                    let path = f.path().display().to_string();
+                    let data = budget.decompress(&path, f.contents())?;
+                    Ok((path, data))
+                })
+                .collect::<Result<_>>()?,
+        );
+        cache.fonts = Some(fonts.clone());
+        Ok(fonts)
+    }
+
+    /// Renamed copies of the fonts targeted by the font aliases. Built once per alias
+    /// list and cached next to the decompressed fonts, so compiles don't re-parse and
+    /// rewrite every font.
+    fn aliased_fonts(&self, fonts: &FontData) -> Arc<Vec<Vec<u8>>> {
+        if self.font_aliases.is_empty() {
+            return Arc::default();
+        }
+        let mut cache = self.font_cache.lock().expect("lock poisoned");
+        if let Some((aliases, renamed)) = &cache.aliased {
+            if *aliases == self.font_aliases {
+                return renamed.clone();
+            }
+        }
+        let data = fonts.iter().map(|(_, data)| data.as_slice());
+        let renamed = Arc::new(font_alias::aliased_fonts(data, &self.font_aliases));
+        cache.aliased = Some((self.font_aliases.clone(), renamed.clone()));
+        renamed
+    }
+
+    /// Compile if needed, then call `f` with a reference to the compiled document.
+    pub(crate) fn with_compiled<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&PagedDocument) -> Result<T>,
+    {
+        // `compile` releases the cache lock, so a slow `f` doesn't block other threads.
+        f(&self.compile()?.document)
+    }
+
+    /// Compile the document and generate PDF.
+    ///
+    /// # Returns
+    /// PDF data as bytes.
+    ///
+    /// # Errors
+    /// Returns an error if compilation or PDF generation fails.
+    #[cfg(feature = "pdf")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+    pub fn to_pdf(&self) -> Result<Vec<u8>> {
+        self.cached(format!("pdf {:?}", self.pdf_config), || {
+            self.render_pdf(None)
+        })
+    }
+
+    /// Compile the document and generate one standalone PDF per page.
+    ///
+    /// Each PDF is exported like [`select_pages`](Self::select_pages) with a single page,
+    /// so the same restrictions apply: tagging is off, and standards that require it are
+    /// rejected. The output size limit applies to the total of all PDFs.
+    ///
+    /// # Returns
+    /// A vector of PDF files, one per page.
+    ///
+    /// # Errors
+    /// Returns an error if compilation or PDF generation fails.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("main.typ");
+    /// for (i, pdf) in doc.to_pdf_pages()?.iter().enumerate() {
+    ///     std::fs::write(format!("page-{}.pdf", i + 1), pdf)?;
+    /// }
+    /// ```
+    #[cfg(feature = "pdf")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+    pub fn to_pdf_pages(&self) -> Result<Vec<Vec<u8>>> {
+        let size = |pdfs: &Vec<Vec<u8>>| pdfs.iter().map(Vec::len).sum();
+        observed("pdf", size, || self.compile()?.render_pdf_pages_inner())
+    }
+
+    /// Compile the document and generate SVG for each page.
+    ///
+    /// # Returns
+    /// A vector of SVG strings, one per page.
+    ///
+    /// # Errors
+    /// Returns an error if compilation fails.
+    #[cfg(feature = "svg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "svg")))]
+    pub fn to_svg(&self) -> Result<Vec<String>> {
+        self.cached("svg".into(), || self.render_svg(None))
+    }
+
+    /// Compile the document and generate PNG for each page.
+    ///
+    /// # Arguments
+    /// * `dpi` - Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print)
+    ///
+    /// # Returns
+    /// A vector of PNG bytes, one per page.
+    ///
+    /// With the `rayon` feature, pages are rasterized in parallel.
     ///
     /// # Errors
     /// Returns an error if compilation or PNG encoding fails.
     #[cfg(feature = "png")]
     #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
     pub fn to_png(&self, dpi: f32) -> Result<Vec<Vec<u8>>> {
-        self.render_png(None, dpi)
+        self.to_png_with(&PngOptions {
+            dpi,
+            ..Default::default()
+        })
     }
 
-    #[cfg(feature = "pdf")]
-    fn render_pdf(&self, selected: Option<&BTreeSet<usize>>) -> Result<Vec<u8>> {
-        self.with_compiled(|compiled| {
-            // Base options come from the stored config (incl. `tagged`, standard, ident,
-            // timestamp). `options` borrows `self.pdf_config.ident`; later reads of
-            // `self.pdf_config.standard` (Copy) are additional shared borrows, which is
-            // fine. We are inside the `compiled_cache` guard, but `pdf_config` is a
-            // distinct field accessed only by shared borrow — so this is sound. Nobody
-            // must take `&mut self.pdf_config` here.
-            let mut options = self.pdf_config.to_typst()?;
-
-            let indices = validate_page_selection(selected, compiled.pages.len())?;
-            if let Some(indices) = indices {
-                use std::num::NonZeroUsize;
-                use typst::layout::PageRanges;
-
-                let ranges = indices
-                    .iter()
-                    .map(|&i| {
-                        let n = Some(NonZeroUsize::new(i + 1).unwrap());
-                        n..=n
-                    })
-                    .collect();
-                options.page_ranges = Some(PageRanges::new(ranges));
-
-                // --- Single safety net: page selection forces tagging off ---
-                //
-                // Page selection sets `page_ranges`, which is incompatible with tagged
-                // PDF. typst-pdf does NOT error on `tagged: true` + `page_ranges`; it
-                // silently emits a structure tree referencing ALL pages while only a
-                // subset is exported, yielding a malformed/misaligned tag tree. See:
-                //   - typst/typst#7743 (tagged PDF incompatible with page ranges)
-                // So we defensively force tagging off here, overriding `PdfConfig.tagged`
-                // — but ONLY on the page-selection path. Full-document `tagged: false` is
-                // handled by `to_typst` and is unaffected.
-                //
-                // Bookmarks: the document outline (/Outlines) is independent of tagging
-                // (typst-pdf sets the outline unconditionally; the tag tree only when
-                // enabled), so disabling tagging keeps bookmarks. Bookmarks pointing at
-                // EXCLUDED pages are still dropped here — that loss is caused by
-                // `page_ranges`, not by tagging.
-                //
-                // Accessible standards (PDF/A-*a, PDF/UA-1) mandate tagging, so they
-                // cannot coexist with page selection; reject them explicitly rather than
-                // emit a non-conformant PDF.
-                if self.pdf_config.standard.requires_tagging() {
-                    return Err(Error::InvalidPdfConfig(format!(
-                        "page selection is incompatible with {:?} (requires tagging)",
-                        self.pdf_config.standard
-                    )));
-                }
-                options.tagged = false;
-            }
+    /// Compile the document and generate PNG for each page with the given options.
+    ///
+    /// Besides the resolution, [`PngOptions`] sets the page background, e.g. transparent
+    /// for compositing page images onto other content, and the resolution of individual
+    /// pages, so a cover page can be rendered sharply while the body stays at preview
+    /// resolution, saving time and memory.
+    ///
+    /// # Errors
+    /// Returns an error if compilation or PNG encoding fails.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// use typst_bake::{Background, PngOptions};
+    ///
+    /// let pngs = doc.to_png_with(&PngOptions {
+    ///     dpi: 96.0,
+    ///     page_dpi: [(0, 300.0)].into(),
+    ///     background: Background::Transparent,
+    /// })?;
+    /// ```
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_png_with(&self, options: &PngOptions) -> Result<Vec<Vec<u8>>> {
+        self.cached(format!("png {options:?}"), || {
+            self.render_png(None, options)
+        })
+    }
+
+    /// Compile the document and return an iterator that renders one page to PNG per step.
+    ///
+    /// [`to_png`](Self::to_png) holds every page image in memory at once, which adds up
+    /// for long documents at high resolution. Here each page is rendered only when the
+    /// iterator reaches it, so a caller writing pages out as they come keeps one image in
+    /// memory. Only compilation happens up front. The
+    /// [output size limit](Self::with_max_output_size) applies to the pages yielded so
+    /// far, and iteration stops after the first error.
+    ///
+    /// # Errors
+    /// Returns an error if compilation fails. Each item fails if PNG encoding fails or
+    /// the output grows too large.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// use typst_bake::PngOptions;
+    ///
+    /// let options = PngOptions { dpi: 300.0, ..Default::default() };
+    /// for (i, png) in doc.render_pages(&options)?.enumerate() {
+    ///     std::fs::write(format!("page-{i}.png"), png?)?;
+    /// }
+    /// ```
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn render_pages(&self, options: &PngOptions) -> Result<RenderPages> {
+        Ok(self.compile()?.render_pages(options))
+    }
+
+    /// Compile the document and generate PNG for each page, reporting progress.
+    ///
+    /// `progress` is called after each page with the number of pages done and the
+    /// total, so long renders can drive a progress bar or a job-queue heartbeat. Pages
+    /// are rendered as by [`render_pages`](Self::render_pages). PDF export runs as one
+    /// step inside typst-pdf, so it has no equivalent.
+    ///
+    /// # Errors
+    /// Returns an error if compilation or PNG encoding fails, or the output is too large.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let options = PngOptions { dpi: 300.0, ..Default::default() };
+    /// let pngs = doc.to_png_with_progress(&options, |done, total| {
+    ///     job.set_progress(done as f32 / total as f32);
+    /// })?;
+    /// ```
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_png_with_progress(
+        &self,
+        options: &PngOptions,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<Vec<u8>>> {
+        let pages = self.render_pages(options)?;
+        let total = pages.len();
+        pages
+            .enumerate()
+            .map(|(index, png)| {
+                let png = png?;
+                progress(index + 1, total);
+                Ok(png)
+            })
+            .collect()
+    }
+
+    /// Compile the document and generate JPEG for each page.
+    ///
+    /// JPEG files are typically several times smaller than PNG for page previews, at the
+    /// cost of slight blur around text. Transparent areas are rendered white.
+    ///
+    /// # Arguments
+    /// * `dpi` - Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print)
+    /// * `quality` - JPEG quality from 1 to 100; 75 to 85 suits previews
+    ///
+    /// # Errors
+    /// Returns an error if compilation or JPEG encoding fails, e.g. for pages wider or
+    /// taller than 65535 pixels.
+    #[cfg(feature = "jpeg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jpeg")))]
+    pub fn to_jpeg(&self, dpi: f32, quality: u8) -> Result<Vec<Vec<u8>>> {
+        self.render_jpeg(None, dpi, quality)
+    }
+
+    /// Compile the document and render each page to raw RGBA pixels.
+    ///
+    /// Like [`to_png`](Self::to_png) without the PNG encoding, for GUI apps that draw
+    /// pages straight into a texture or buffer. Each page takes `width * height * 4`
+    /// bytes, which [`with_max_output_size`](Self::with_max_output_size) counts in full.
+    ///
+    /// # Errors
+    /// Returns an error if compilation fails or the output is too large.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// for page in doc.to_pixmaps(144.0)? {
+    ///     texture.upload(page.width, page.height, &page.data);
+    /// }
+    /// ```
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_pixmaps(&self, dpi: f32) -> Result<Vec<RgbaImage>> {
+        self.render_pixmaps(None, dpi)
+    }
 
-            // Invariant backstop: tagged PDF + page ranges must never escape together.
-            debug_assert!(!(options.tagged && options.page_ranges.is_some()));
+    /// Compile the document, returning a handle to export from.
+    ///
+    /// Uses the cached result if the document was already compiled. The returned
+    /// [`CompiledDocument`] keeps the pages and the current export settings (PDF config,
+    /// output size limit), so exporting from it skips the cache lookup, and it can be
+    /// cloned cheaply and moved to other threads.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let compiled = typst_bake::document!("main.typ").with_inputs(inputs).compile()?;
+    /// let preview = std::thread::spawn({
+    ///     let compiled = compiled.clone();
+    ///     move || compiled.to_png(72.0)
+    /// });
+    /// let pdf = compiled.to_pdf()?;
+    /// ```
+    pub fn compile(&self) -> Result<CompiledDocument> {
+        self.compile_cached()?;
+        // Only `self`-consuming builders clear the cache, so it is still set here.
+        let compiled = self
+            .lock_cache()
+            .clone()
+            .expect("compiled_cache must be Some after successful compile_cached()");
+        Ok(CompiledDocument::new(
+            compiled,
+            ExportSettings {
+                #[cfg(feature = "pdf")]
+                pdf_config: self.pdf_config.clone(),
+                max_output_size: self.max_output_size,
+            },
+        ))
+    }
 
-            typst_pdf::pdf(compiled, &options).map_err(|e| Error::PdfGeneration(format!("{e:?}")))
+    #[cfg(feature = "pdf")]
+    fn render_pdf(&self, selected: Option<&BTreeSet<usize>>) -> Result<Vec<u8>> {
+        observed("pdf", Vec::len, || {
+            self.compile()?.render_pdf_inner(selected)
         })
     }
 
     #[cfg(feature = "svg")]
     fn render_svg(&self, selected: Option<&BTreeSet<usize>>) -> Result<Vec<String>> {
-        self.with_compiled(|compiled| {
-            let indices = validate_page_selection(selected, compiled.pages.len())?;
-            match indices {
-                Some(indices) => Ok(indices
-                    .iter()
-                    .map(|&i| typst_svg::svg(&compiled.pages[i]))
-                    .collect()),
-                None => Ok(compiled.pages.iter().map(typst_svg::svg).collect()),
-            }
+        let size = |svgs: &Vec<String>| svgs.iter().map(String::len).sum();
+        observed("svg", size, || self.compile()?.render_svg_inner(selected))
+    }
+
+    #[cfg(feature = "jpeg")]
+    fn render_jpeg(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        dpi: f32,
+        quality: u8,
+    ) -> Result<Vec<Vec<u8>>> {
+        let size = |jpegs: &Vec<Vec<u8>>| jpegs.iter().map(Vec::len).sum();
+        observed("jpeg", size, || {
+            self.compile()?.render_jpeg_inner(selected, dpi, quality)
         })
     }
 
     #[cfg(feature = "png")]
-    fn render_png(&self, selected: Option<&BTreeSet<usize>>, dpi: f32) -> Result<Vec<Vec<u8>>> {
-        self.with_compiled(|compiled| {
-            let pixel_per_pt = dpi / 72.0;
-            let indices = validate_page_selection(selected, compiled.pages.len())?;
-            let pages: Box<dyn Iterator<Item = &_>> = match &indices {
-                Some(indices) => Box::new(indices.iter().map(|&i| &compiled.pages[i])),
-                None => Box::new(compiled.pages.iter()),
-            };
-            pages
-                .map(|page| {
-                    typst_render::render(page, pixel_per_pt)
-                        .encode_png()
-                        .map_err(|e| Error::PngEncoding(e.to_string()))
-                })
-                .collect()
+    fn render_pixmaps(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        dpi: f32,
+    ) -> Result<Vec<RgbaImage>> {
+        let size = |images: &Vec<RgbaImage>| images.iter().map(|i| i.data.len()).sum();
+        observed("rgba", size, || {
+            self.compile()?.render_pixmaps_inner(selected, dpi)
+        })
+    }
+
+    #[cfg(feature = "png")]
+    fn render_png(
+        &self,
+        selected: Option<&BTreeSet<usize>>,
+        options: &PngOptions,
+    ) -> Result<Vec<Vec<u8>>> {
+        let size = |pngs: &Vec<Vec<u8>>| pngs.iter().map(Vec::len).sum();
+        observed("png", size, || {
+            self.compile()?.render_png_inner(selected, options)
         })
     }
 }
 
+/// Decompressed font files as `(path, data)` pairs.
+type FontData = Arc<Vec<(String, Vec<u8>)>>;
+
+/// Fonts prepared for compilation, shared by a document and its forks.
+#[derive(Default)]
+struct FontCache {
+    /// The embedded fonts.
+    fonts: Option<FontData>,
+    /// Aliased copies, with the `(alias, target family)` list they were built for. Forks
+    /// normally share the list; a different one replaces the entry.
+    aliased: Option<(Vec<(String, String)>, Arc<Vec<Vec<u8>>>)>,
+}
+
 /// A lightweight view into a [`Document`] with a page selection filter.
 ///
 /// Created by [`Document::select_pages`]. Holds a reference to the
@@ -507,81 +2010,98 @@ impl Pages<'_> {
         self.doc.render_pdf(Some(&self.indices))
     }
 
-    /// Compile the document and generate SVG for the selected pages.
+    /// Compile the document and generate SVG for the selected pages.
+    ///
+    /// # Errors
+    /// Returns an error if compilation or page selection fails.
+    #[cfg(feature = "svg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "svg")))]
+    pub fn to_svg(&self) -> Result<Vec<String>> {
+        self.doc.render_svg(Some(&self.indices))
+    }
+
+    /// Compile the document and generate PNG for the selected pages.
+    ///
+    /// # Arguments
+    /// * `dpi` - Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print)
+    ///
+    /// # Errors
+    /// Returns an error if compilation, PNG encoding, or page selection fails.
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_png(&self, dpi: f32) -> Result<Vec<Vec<u8>>> {
+        self.to_png_with(&PngOptions {
+            dpi,
+            ..Default::default()
+        })
+    }
+
+    /// Compile the document and generate PNG for the selected pages with the given
+    /// options.
+    ///
+    /// [`PngOptions::page_dpi`] is keyed by the page's index in the whole document, not in
+    /// the selection.
+    ///
+    /// # Errors
+    /// Returns an error if compilation, PNG encoding, or page selection fails.
+    #[cfg(feature = "png")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+    pub fn to_png_with(&self, options: &PngOptions) -> Result<Vec<Vec<u8>>> {
+        self.doc.render_png(Some(&self.indices), options)
+    }
+
+    /// Compile the document and generate JPEG for the selected pages.
+    ///
+    /// See [`Document::to_jpeg`].
     ///
     /// # Errors
-    /// Returns an error if compilation or page selection fails.
-    #[cfg(feature = "svg")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "svg")))]
-    pub fn to_svg(&self) -> Result<Vec<String>> {
-        self.doc.render_svg(Some(&self.indices))
+    /// Returns an error if compilation, JPEG encoding, or page selection fails.
+    #[cfg(feature = "jpeg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jpeg")))]
+    pub fn to_jpeg(&self, dpi: f32, quality: u8) -> Result<Vec<Vec<u8>>> {
+        self.doc.render_jpeg(Some(&self.indices), dpi, quality)
     }
 
-    /// Compile the document and generate PNG for the selected pages.
-    ///
-    /// # Arguments
-    /// * `dpi` - Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print)
+    /// Compile the document and render the selected pages to raw RGBA pixels.
     ///
     /// # Errors
-    /// Returns an error if compilation, PNG encoding, or page selection fails.
+    /// Returns an error if compilation or page selection fails, or the output is too large.
     #[cfg(feature = "png")]
     #[cfg_attr(docsrs, doc(cfg(feature = "png")))]
-    pub fn to_png(&self, dpi: f32) -> Result<Vec<Vec<u8>>> {
-        self.doc.render_png(Some(&self.indices), dpi)
+    pub fn to_pixmaps(&self, dpi: f32) -> Result<Vec<RgbaImage>> {
+        self.doc.render_pixmaps(Some(&self.indices), dpi)
     }
 }
 
-/// Validate page selection and return indices to render.
-/// Returns `None` if no selection (= all pages).
-fn validate_page_selection(
-    selected: Option<&BTreeSet<usize>>,
-    total_pages: usize,
-) -> Result<Option<Vec<usize>>> {
-    if total_pages == 0 {
-        return Err(Error::InvalidPageSelection("document has no pages".into()));
-    }
-    match selected {
-        None => Ok(None),
-        Some(pages) => {
-            if pages.is_empty() {
-                return Err(Error::InvalidPageSelection(
-                    "page selection is empty".into(),
-                ));
-            }
-            if let Some(&max) = pages.last() {
-                if max >= total_pages {
-                    return Err(Error::InvalidPageSelection(format!(
-                        "page index {max} out of range (valid: 0..={})",
-                        total_pages - 1
-                    )));
-                }
-            }
-            Ok(Some(pages.iter().copied().collect()))
-        }
-    }
+/// The entry file as compiled: its user-facing path, `FileId`, and the number of
+/// prelude lines placed in front of its source.
+struct MainFile<'a> {
+    path: &'a str,
+    id: FileId,
+    prelude_lines: usize,
 }
 
 /// Resolve a span into a [`SourceLocation`] using the compilation world.
 ///
 /// The entry file's `FileId` is mapped back to the user-facing entry path so it
-/// matches exactly what was requested (including nested entries).
-fn span_to_location(
-    world: &TypstWorld,
-    entry: &str,
-    main: FileId,
-    span: Span,
-) -> Option<SourceLocation> {
+/// matches exactly what was requested (including nested entries). Lines of the entry
+/// are counted without the prelude; spans inside the prelude are reported as
+/// `<prelude>`.
+fn span_to_location(world: &TypstWorld, main: &MainFile, span: Span) -> Option<SourceLocation> {
     let id = span.id()?;
     let range = world.range(span)?;
     let source = world.source(id).ok()?;
     let (line, column) = source.lines().byte_to_line_column(range.start)?;
-    let file = if id == main {
-        entry.to_string()
+    let (file, line) = if id != main.id {
+        (file_id_to_path(id), line)
+    } else if line < main.prelude_lines {
+        ("<prelude>".to_string(), line)
     } else {
-        file_id_to_path(id)
+        (main.path.to_string(), line - main.prelude_lines)
     };
     Some(SourceLocation {
         file,
+        package: id.package().map(ToString::to_string),
         line: line + 1,
         column: column + 1,
     })
@@ -591,18 +2111,21 @@ fn span_to_location(
 /// resolved source locations.
 fn diagnostic_from(
     world: &TypstWorld,
-    entry: &str,
-    main: FileId,
+    main: &MainFile,
     diagnostic: &SourceDiagnostic,
 ) -> Diagnostic {
     Diagnostic {
-        location: span_to_location(world, entry, main, diagnostic.span),
+        severity: match diagnostic.severity {
+            typst::diag::Severity::Error => Severity::Error,
+            typst::diag::Severity::Warning => Severity::Warning,
+        },
+        location: span_to_location(world, main, diagnostic.span),
         message: diagnostic.message.to_string(),
         hints: diagnostic.hints.iter().map(|h| h.to_string()).collect(),
         trace: diagnostic
             .trace
             .iter()
-            .filter_map(|t| span_to_location(world, entry, main, t.span))
+            .filter_map(|t| span_to_location(world, main, t.span))
             .collect(),
     }
 }
@@ -616,15 +2139,7 @@ fn find_entry<'a>(dir: &'a Dir<'a>, path: &str) -> Option<&'a File<'a>> {
     };
 
     let target_dir = match dir_path {
-        Some(dir_path) => {
-            let mut current = dir;
-            for segment in dir_path.split('/') {
-                current = current
-                    .dirs()
-                    .find(|d| d.path().file_name().and_then(|n| n.to_str()) == Some(segment))?;
-            }
-            current
-        }
+        Some(dir_path) => find_dir(dir, dir_path)?,
         None => dir,
     };
 
@@ -633,9 +2148,876 @@ fn find_entry<'a>(dir: &'a Dir<'a>, path: &str) -> Option<&'a File<'a>> {
         .find(|f| f.path().file_name().and_then(|n| n.to_str()) == Some(file_name))
 }
 
+/// Find a subdirectory of `dir` by `/`-separated path, with the same name-only
+/// nesting as [`find_entry`].
+fn find_dir<'a>(dir: &'a Dir<'a>, path: &str) -> Option<&'a Dir<'a>> {
+    let mut current = dir;
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        current = current
+            .dirs()
+            .find(|d| d.path().file_name().and_then(|n| n.to_str()) == Some(segment))?;
+    }
+    Some(current)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stats::{CategoryStats, DedupStats, PackageStats};
+    use include_dir::DirEntry;
+
+    /// Build a `Document` over an in-memory template tree (flat, no fonts or
+    /// packages), mirroring what the macro generates. Leaks its data, which is
+    /// fine for tests.
+    fn test_document(files: &[(&'static str, &str)]) -> Document {
+        test_document_with_fonts(files, &[])
+    }
+
+    /// Like [`test_document`], with the given font files embedded.
+    fn test_document_with_fonts(
+        files: &[(&'static str, &str)],
+        fonts: &[(&'static str, &[u8])],
+    ) -> Document {
+        fn leak_dir(entries: Vec<DirEntry<'static>>) -> &'static Dir<'static> {
+            Box::leak(Box::new(Dir::new(
+                "",
+                Box::leak(entries.into_boxed_slice()),
+            )))
+        }
+        fn leak_files(
+            files: impl Iterator<Item = (&'static str, Vec<u8>)>,
+        ) -> Vec<DirEntry<'static>> {
+            files
+                .map(|(name, data)| {
+                    let compressed = zstd::encode_all(&data[..], 1).expect("compress");
+                    DirEntry::File(File::new(name, Box::leak(compressed.into_boxed_slice())))
+                })
+                .collect()
+        }
+        let entries = leak_files(files.iter().map(|&(name, src)| (name, src.into())));
+        let font_entries = leak_files(fonts.iter().map(|&(name, data)| (name, data.into())));
+        let empty = CategoryStats {
+            original_size: 0,
+            compressed_size: 0,
+            file_count: 0,
+        };
+        let stats = EmbedStats {
+            templates: empty,
+            packages: PackageStats {
+                packages: Vec::new(),
+                original_size: 0,
+                compressed_size: 0,
+            },
+            fonts: empty,
+            dedup: DedupStats {
+                total_files: 0,
+                unique_blobs: 0,
+                duplicate_count: 0,
+                saved_bytes: 0,
+            },
+            compression_level: 1,
+        };
+        Document::__new(
+            leak_dir(entries),
+            leak_dir(Vec::new()),
+            leak_dir(font_entries),
+            files[0].0,
+            stats,
+            "",
+        )
+    }
+
+    #[test]
+    fn max_pages_rejects_long_documents() {
+        let src = "a #pagebreak() b #pagebreak() c";
+        let doc = test_document(&[("main.typ", src)]);
+        assert_eq!(doc.page_count().unwrap(), 3);
+
+        let doc = test_document(&[("main.typ", src)]).with_max_pages(2);
+        assert!(matches!(
+            doc.page_count(),
+            Err(Error::TooManyPages { count: 3, limit: 2 })
+        ));
+    }
+
+    #[test]
+    fn string_inputs_are_not_evaluated() {
+        let payload = "#read(\"secret.txt\")";
+        let doc = test_document(&[
+            (
+                "main.typ",
+                "#assert.eq(type(sys.inputs.body), str)\n\
+                 #assert.eq(sys.inputs.body, \"#read(\\\"secret.txt\\\")\")\n\
+                 #sys.inputs.body",
+            ),
+            ("secret.txt", "TOPSECRET"),
+        ])
+        .with_inputs(typst::foundations::dict! { "body" => payload });
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn prelude_bindings_are_visible_and_lines_are_preserved() {
+        let doc = test_document(&[("main.typ", "#assert.eq(brand, \"blue\")\n#oops")])
+            .with_prelude("#let brand = \"blue\"")
+            .with_prelude("#set text(lang: \"de\")");
+        let Err(Error::Compilation(diagnostics)) = doc.page_count() else {
+            panic!("expected a compilation error");
+        };
+        let location = diagnostics[0].location.as_ref().unwrap();
+        assert_eq!((location.file.as_str(), location.line), ("main.typ", 2));
+
+        let doc = test_document(&[("main.typ", "ok")]).with_prelude("#oops");
+        let Err(Error::Compilation(diagnostics)) = doc.page_count() else {
+            panic!("expected a compilation error");
+        };
+        assert_eq!(diagnostics[0].location.as_ref().unwrap().file, "<prelude>");
+    }
+
+    #[test]
+    fn env_inputs_are_exposed_under_env() {
+        std::env::set_var("TYPST_BAKE_TEST_ENV_INPUT", "1.2.3");
+        let doc = test_document(&[(
+            "main.typ",
+            "#assert.eq(sys.inputs.title, \"T\")\n\
+             #assert.eq(sys.inputs.env.TYPST_BAKE_TEST_ENV_INPUT, \"1.2.3\")\n\
+             #assert(\"TYPST_BAKE_TEST_ENV_UNSET\" not in sys.inputs.env)",
+        )])
+        .with_inputs(typst::foundations::dict! { "title" => "T" })
+        .with_env_inputs(&["TYPST_BAKE_TEST_ENV_INPUT", "TYPST_BAKE_TEST_ENV_UNSET"]);
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn lang_and_region_are_applied() {
+        let doc = test_document(&[(
+            "main.typ",
+            "#context assert.eq((text.lang, text.region), (\"ko\", \"KR\"))",
+        )])
+        .with_lang("ko")
+        .with_region("KR");
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn with_now_fixes_today() {
+        let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(86_400 + 23 * 3600);
+        let doc = test_document(&[(
+            "main.typ",
+            "#assert.eq(datetime.today(), datetime(year: 1970, month: 1, day: 2))\n\
+             #assert.eq(datetime.today(offset: 2), datetime(year: 1970, month: 1, day: 3))",
+        )])
+        .with_now(now);
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn overlays_draw_on_every_page() {
+        let doc = test_document(&[("main.typ", "a #pagebreak() b"), ("stamp.typ", "STAMP")]);
+        let doc = doc.with_overlay("DRAFT");
+        let text = doc.to_text().unwrap();
+        assert!(text.iter().all(|page| page.contains("DRAFT")));
+
+        let doc = doc.with_overlay_template("stamp.typ").unwrap();
+        let text = doc.to_text().unwrap();
+        assert!(text
+            .iter()
+            .all(|page| page.contains("STAMP") && !page.contains("DRAFT")));
+    }
+
+    #[test]
+    fn bookmarks_can_be_disabled() {
+        let doc = test_document(&[(
+            "main.typ",
+            "= Intro\n#context assert.eq(query(heading).first().bookmarked, false)",
+        )])
+        .with_bookmarks(false);
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn document_metadata_is_applied() {
+        let doc = test_document(&[(
+            "main.typ",
+            "#context assert.eq(document.title.text, \"Q3 \\\"Report\\\"\")\n\
+             #context assert.eq(document.author, (\"Ann\", \"Bo\"))\n\
+             #context assert.eq(document.keywords, (\"a\", \"b\"))",
+        )])
+        .with_title("Q3 \"Report\"")
+        .with_author("Ann")
+        .with_author("Bo")
+        .with_keywords(["a", "b"]);
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn pdf_attachments_are_embedded() {
+        let doc = test_document(&[("main.typ", "hi")])
+            .with_pdf_attachment("factur-x.xml", "<Invoice/>", "text/xml")
+            .unwrap();
+        let pdf = doc.to_pdf().unwrap();
+        assert!(pdf.windows(12).any(|w| w == b"factur-x.xml"));
+
+        assert!(matches!(
+            test_document(&[("main.typ", "hi")]).with_pdf_attachment("../x", "", "text/plain"),
+            Err(Error::InvalidFilePath(_))
+        ));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn render_cache_serves_matching_fingerprints() {
+        // Test documents share an empty content hash, so these two have the same
+        // fingerprint despite different sources: the second can only get the first's
+        // output from the cache.
+        let doc = |src: &str, title: &str| {
+            test_document(&[("cached.typ", src)])
+                .with_cache(8)
+                .with_inputs(typst::foundations::dict! { "title" => title })
+        };
+        let first = doc("first", "a").to_pdf().unwrap();
+        assert_eq!(doc("second", "a").to_pdf().unwrap(), first);
+        assert_ne!(doc("second", "b").to_pdf().unwrap(), first);
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn render_cache_keeps_strict_documents_apart() {
+        let doc = || {
+            test_document(&[("strict-cache.typ", "#text(font: \"No Such Font\")[hi]")])
+                .with_cache(8)
+        };
+        doc().to_pdf().unwrap();
+        let Err(Error::Compilation(_)) = doc().with_strict(true).to_pdf() else {
+            panic!("a strict document was served a non-strict document's output");
+        };
+    }
+
+    #[test]
+    fn fingerprint_tracks_inputs() {
+        let doc = || test_document(&[("main.typ", "hi")]);
+        assert_eq!(doc().fingerprint(), doc().fingerprint());
+
+        let with_title = |title: &str| {
+            doc()
+                .with_inputs(typst::foundations::dict! { "title" => title })
+                .fingerprint()
+        };
+        assert_eq!(with_title("A"), with_title("A"));
+        assert_ne!(with_title("A"), with_title("B"));
+        assert_ne!(doc().fingerprint(), doc().with_lang("de").fingerprint());
+    }
+
+    #[test]
+    fn required_inputs_reach_the_template() {
+        let doc = test_document(&[("main.typ", "#assert.eq(sys.inputs.title, \"T\")")])
+            .require_inputs::<Dict>()
+            .configure(|doc| doc.with_max_pages(1))
+            .with_inputs(typst::foundations::dict! { "title" => "T" });
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn render_each_passes_every_result_to_the_sink() {
+        use crate::BatchOptions;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let doc = test_document(&[(
+            "main.typ",
+            "#for _ in range(sys.inputs.n) [x #pagebreak(weak: true)]",
+        )]);
+        let progress = Arc::new(AtomicUsize::new(0));
+        let seen = progress.clone();
+        let options = BatchOptions {
+            threads: 3,
+            on_progress: Some(Box::new(move |done| {
+                seen.fetch_max(done, Ordering::Relaxed);
+            })),
+        };
+        let inputs = (1..=5).map(|n| (n, typst::foundations::dict! { "n" => n as i64 }));
+
+        let mut pages = Vec::new();
+        doc.render_each(inputs, &options, Document::page_count, |n, count| {
+            pages.push((n, count.unwrap()));
+        });
+        // Several threads finish in any order; every key is paired with its own result.
+        pages.sort();
+        assert_eq!(pages, [(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+        assert_eq!(progress.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn document_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Document>();
+        assert_send_sync::<CompiledDocument>();
+    }
+
+    #[test]
+    fn compiled_document_outlives_the_document() {
+        let doc = test_document(&[("main.typ", "a #pagebreak() b")]);
+        let compiled = doc.compile().unwrap();
+        drop(doc);
+        let shared = compiled.clone();
+        let pages = std::thread::spawn(move || shared.page_count())
+            .join()
+            .unwrap();
+        assert_eq!(pages, 2);
+    }
+
+    #[test]
+    fn warnings_are_reported_after_compile() {
+        let doc = test_document(&[("main.typ", "#set text(font: \"No Such Font\")\nHi")]);
+        let warnings = doc.warnings().unwrap();
+        let font = warnings
+            .iter()
+            .find(|w| w.message.contains("unknown font family"))
+            .expect("unknown font warning");
+        assert_eq!(font.severity, Severity::Warning);
+        assert_eq!(font.location.as_ref().map(|l| l.line), Some(1));
+    }
+
+    #[test]
+    fn markup_is_evaluated_only_when_wrapped() {
+        let src = "#import \"/.typst-bake/markup.typ\": markup\n#markup(sys.inputs.note)";
+        let doc = test_document(&[("main.typ", src)]).with_inputs(typst::foundations::dict! {
+            "note" => crate::Markup::new("*bold* #(1 + 1)"),
+        });
+        assert_eq!(doc.to_text().unwrap(), ["bold 2"]);
+
+        let doc = test_document(&[("main.typ", src)])
+            .with_inputs(typst::foundations::dict! { "note" => "*bold* #(1 + 1)" });
+        let err = doc.to_text().unwrap_err().to_string();
+        assert!(err.contains("takes a typst_bake::Markup input"), "{err}");
+    }
+
+    #[test]
+    fn forged_markup_is_refused() {
+        let src = "#import \"/.typst-bake/markup.typ\": markup\n#markup(sys.inputs.note)";
+        let forged = typst::foundations::dict! {
+            "typst-bake:markup" => "#read(\"main.typ\")",
+            "typst-bake:tag" => "0".repeat(32),
+        };
+        let doc = test_document(&[("main.typ", src)])
+            .with_inputs(typst::foundations::dict! { "note" => forged });
+        assert!(doc.to_text().is_err());
+
+        // A genuine tag doesn't carry over to different text.
+        let typst::foundations::Value::Dict(mut genuine) = crate::Markup::new("hello").into_value()
+        else {
+            panic!("expected a dictionary");
+        };
+        genuine.insert(
+            "typst-bake:markup".into(),
+            "#read(\"main.typ\")".into_value(),
+        );
+        let doc = test_document(&[("main.typ", src)])
+            .with_inputs(typst::foundations::dict! { "note" => genuine });
+        assert!(doc.to_text().is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn forged_markup_from_json_is_refused() {
+        let src = "#import \"/.typst-bake/markup.typ\": markup\n#markup(sys.inputs.note)";
+        let typst::foundations::Value::Dict(genuine) = crate::Markup::new("hello").into_value()
+        else {
+            panic!("expected a dictionary");
+        };
+        let tag = genuine
+            .get("typst-bake:tag")
+            .unwrap()
+            .clone()
+            .cast::<Str>()
+            .unwrap();
+        let doc = test_document(&[("main.typ", src)])
+            .with_json_inputs(serde_json::json!({
+                "note": { "typst-bake:markup": "#read(\"main.typ\")", "typst-bake:tag": tag.as_str() },
+            }))
+            .unwrap();
+        assert!(doc.to_text().is_err());
+    }
+
+    #[test]
+    fn reserved_paths_are_refused() {
+        let doc = test_document(&[("main.typ", "hi")]);
+        let Err(Error::InvalidFilePath(_)) =
+            doc.add_file(".typst-bake/markup.typ", "#let markup(v) = eval(v)")
+        else {
+            panic!("expected the reserved path to be refused");
+        };
+        let doc = test_document(&[("main.typ", "hi")]);
+        assert!(doc
+            .add_file("./.typst-bake/attachments/x.xml", "x")
+            .is_err());
+        let doc = test_document(&[("main.typ", "hi")]);
+        assert!(doc.add_file("typst-bake/notes.txt", "x").is_ok());
+    }
+
+    #[test]
+    fn features_reach_the_library() {
+        let src = "#assert.eq(type(html), module)";
+        assert!(test_document(&[("main.typ", src)]).page_count().is_err());
+        let doc = test_document(&[("main.typ", src)]).with_feature(Feature::Html);
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn strict_mode_fails_on_warnings() {
+        let src = "#text(font: \"No Such Font\")[hi]";
+        let doc = test_document(&[("main.typ", src)]);
+        assert!(!doc.warnings().unwrap().is_empty());
+
+        let doc = test_document(&[("main.typ", src)]).with_strict(true);
+        let Err(Error::Compilation(diagnostics)) = doc.page_count() else {
+            panic!("expected a compilation error");
+        };
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn fallback_renders_with_the_error() {
+        let doc = test_document(&[
+            ("main.typ", "#oops"),
+            (
+                "error.typ",
+                "#assert.eq(sys.inputs.name, \"Ann\")\n#assert(\"oops\" in sys.inputs.error)",
+            ),
+        ])
+        .with_inputs(typst::foundations::dict! { "name" => "Ann" })
+        .with_fallback("error.typ")
+        .unwrap();
+        assert_eq!(doc.page_count().unwrap(), 1);
+        assert_eq!(doc.warnings().unwrap()[0].severity, Severity::Error);
+
+        let doc = test_document(&[("main.typ", "ok")]).with_fallback("missing.typ");
+        assert!(matches!(doc, Err(Error::EntryNotFound(ref entry)) if entry == "missing.typ"));
+    }
+
+    #[test]
+    fn error_document_replaces_compile_errors() {
+        let doc = test_document(&[("main.typ", "#oops")]).with_error_document(true);
+        assert_eq!(doc.page_count().unwrap(), 1);
+        let warnings = doc.warnings().unwrap();
+        assert_eq!(warnings[0].severity, Severity::Error);
+        assert!(warnings[0].message.contains("oops"));
+    }
+
+    #[test]
+    fn pool_sessions_are_bounded_and_isolated() {
+        let doc = test_document(&[("main.typ", "#sys.inputs.at(\"name\")")]);
+        let pool = crate::DocumentPool::new(doc, 2).unwrap();
+
+        let a = pool
+            .session()
+            .with_inputs(typst::foundations::dict! { "name" => "Alice" });
+        let b = pool
+            .try_session()
+            .unwrap()
+            .with_inputs(typst::foundations::dict! { "name" => "Bob" });
+        assert!(pool.try_session().is_none());
+
+        assert_eq!(a.to_text().unwrap(), ["Alice"]);
+        assert_eq!(b.to_text().unwrap(), ["Bob"]);
+
+        drop(a);
+        assert!(pool.try_session().is_some());
+    }
+
+    #[test]
+    fn to_text_reads_pages_in_order() {
+        let doc = test_document(&[(
+            "main.typ",
+            "#set page(width: 10cm, height: auto, footer: [Page 1])\n= Hello *bold* world\nSecond line",
+        )]);
+        let pages = doc.to_text().unwrap();
+        assert_eq!(pages, ["Hello bold world\nSecond line\nPage 1\n"]);
+    }
+
+    #[test]
+    fn unused_fonts_lists_fonts_without_glyphs() {
+        let fonts: &[(&str, &[u8])] = &[
+            (
+                "SourceSerif4-Regular.ttf",
+                include_bytes!("../../examples/fonts/SourceSerif4-Regular.ttf"),
+            ),
+            (
+                "JetBrainsMono-Regular.otf",
+                include_bytes!("../../examples/fonts/JetBrainsMono-Regular.otf"),
+            ),
+        ];
+        let src = "#set text(font: \"Source Serif 4\")\nHello";
+        let doc = test_document_with_fonts(&[("main.typ", src)], fonts);
+        assert_eq!(doc.unused_fonts().unwrap(), ["JetBrainsMono-Regular.otf"]);
+
+        // A font used under an alias counts as used.
+        let src = "#set text(font: \"Source Serif 4\")\nHello #text(font: \"Courier\")[code]";
+        let doc = test_document_with_fonts(&[("main.typ", src)], fonts)
+            .with_font_alias("Courier", "JetBrains Mono");
+        assert!(doc.unused_fonts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn query_returns_labelled_metadata() {
+        let doc = test_document(&[(
+            "main.typ",
+            "= Summary <toc>\n#metadata(3 * 14) <total>\n#metadata(\"x\") <total>",
+        )]);
+        let totals: Vec<typst::foundations::Value> = doc.query("<total>").unwrap();
+        assert_eq!(totals, [42i64.into_value(), "x".into_value()]);
+        assert_eq!(
+            doc.query::<typst::foundations::Content>("toc")
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches!(
+            doc.query::<i64>("total"),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn with_entry_switches_the_compiled_file() {
+        let doc = test_document(&[("main.typ", "one"), ("two.typ", "a #pagebreak() b")]);
+        assert_eq!(doc.page_count().unwrap(), 1);
+        // Chosen at runtime, e.g. from a request parameter.
+        let name = String::from("two");
+        let doc = doc.with_entry(format!("{name}.typ")).unwrap();
+        assert_eq!(doc.page_count().unwrap(), 2);
+        assert!(matches!(
+            doc.with_entry("missing.typ"),
+            Err(Error::EntryNotFound(ref entry)) if entry == "missing.typ"
+        ));
+    }
+
+    #[test]
+    fn template_set_must_be_one_the_macro_embedded() {
+        let doc = test_document(&[("main.typ", "hi")]).__with_template_sets(&["acme"], "acme");
+        assert_eq!(doc.template_set.as_deref(), Some("acme"));
+        let doc = doc.with_template_set("./acme").unwrap();
+        for name in ["", "globex", "acme/fonts"] {
+            let doc = test_document(&[("main.typ", "hi")]).__with_template_sets(&["acme"], "acme");
+            assert!(
+                matches!(
+                    doc.with_template_set(name),
+                    Err(Error::TemplateSetNotFound(_))
+                ),
+                "{name:?} was accepted"
+            );
+        }
+        assert_eq!(doc.page_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn unknown_template_set_is_rejected() {
+        let result = test_document(&[("main.typ", "hi")]).with_template_set("acme");
+        assert!(matches!(
+            result,
+            Err(Error::TemplateSetNotFound(ref name)) if name == "acme"
+        ));
+    }
+
+    #[test]
+    fn read_template_returns_decompressed_bytes() {
+        let doc = test_document(&[("main.typ", "hi"), ("fields.json", "{\"a\": 1}")]);
+        assert_eq!(doc.read_template("./fields.json").unwrap(), b"{\"a\": 1}");
+        assert!(matches!(
+            doc.read_template("missing.json"),
+            Err(Error::FileNotFound(ref path)) if path == "missing.json"
+        ));
+    }
+
+    #[test]
+    fn decompression_limit_surfaces_typed_error() {
+        let doc = test_document(&[
+            ("main.typ", "#read(\"data.txt\")"),
+            ("data.txt", &"x".repeat(4096)),
+        ])
+        .with_decompression_limits(DecompressionLimits {
+            per_file: Some(1024),
+            total: None,
+        });
+        assert!(matches!(
+            doc.page_count(),
+            Err(Error::DecompressionLimit { ref path, limit: 1024 }) if path == "data.txt"
+        ));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn max_output_size_rejects_large_pdf() {
+        let doc = test_document(&[("main.typ", "Hello")]).with_max_output_size(16);
+        assert!(matches!(
+            doc.to_pdf(),
+            Err(Error::OutputTooLarge { limit: 16 })
+        ));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn pdf_pages_are_split() {
+        let doc = test_document(&[("main.typ", "a #pagebreak() b #pagebreak() c")]);
+        let pdfs = doc.to_pdf_pages().unwrap();
+        assert_eq!(pdfs.len(), 3);
+        assert!(pdfs.iter().all(|pdf| pdf.starts_with(b"%PDF")));
+    }
+
+    #[test]
+    fn layout_info_reports_page_sizes() {
+        let doc = test_document(&[(
+            "main.typ",
+            "#set page(width: 1in, height: 2in)\na\n#set page(flipped: true)\nb",
+        )]);
+        let info = doc.layout_info().unwrap();
+        assert_eq!(info.page_count(), 2);
+        assert_eq!((info.pages[0].width, info.pages[0].height), (72.0, 144.0));
+        assert_eq!((info.pages[1].width, info.pages[1].height), (144.0, 72.0));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn render_pages_yields_each_page_lazily() {
+        let doc = test_document(&[("main.typ", "a #pagebreak() b #pagebreak() c")]);
+        let options = PngOptions {
+            dpi: 36.0,
+            ..Default::default()
+        };
+        let mut pages = doc.render_pages(&options).unwrap();
+        assert_eq!(pages.len(), 3);
+        assert!(pages.next().unwrap().unwrap().starts_with(b"\x89PNG"));
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages.count(), 2);
+
+        let doc = test_document(&[("main.typ", "a #pagebreak() b")]).with_max_output_size(1);
+        let results: Vec<_> = doc.render_pages(&options).unwrap().collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(
+            results[0],
+            Err(Error::OutputTooLarge { limit: 1 })
+        ));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_background_replaces_default_fill_only() {
+        use crate::pixmap::{with_background, Background};
+
+        let doc = test_document(&[("main.typ", "a\n#set page(fill: blue)\nb")]);
+        doc.with_compiled(|compiled| {
+            let [first, second] = &compiled.pages[..] else {
+                panic!("expected two pages");
+            };
+            assert!(with_background(first, Background::Default).fill.is_auto());
+            assert_eq!(
+                with_background(first, Background::Transparent).fill,
+                typst::foundations::Smart::Custom(None)
+            );
+            assert_eq!(
+                with_background(second, Background::Transparent).fill,
+                second.fill
+            );
+            Ok(())
+        })
+        .unwrap();
+
+        let pngs = doc
+            .to_png_with(&PngOptions {
+                dpi: 36.0,
+                background: Background::Transparent,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(pngs.len(), 2);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_dpi_is_chosen_per_page() {
+        use crate::pixmap::Background;
+
+        let doc = test_document(&[(
+            "main.typ",
+            "#set page(width: 1in, height: 1in)\na #pagebreak() b #pagebreak() c",
+        )]);
+        let width = |png: &Vec<u8>| u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let options = PngOptions {
+            dpi: 72.0,
+            page_dpi: [(0, 144.0), (2, 36.0)].into(),
+            background: Background::Transparent,
+        };
+        let widths: Vec<_> = doc
+            .to_png_with(&options)
+            .unwrap()
+            .iter()
+            .map(width)
+            .collect();
+        assert_eq!(widths, [144, 72, 36]);
+
+        let widths: Vec<_> = doc
+            .select_pages([1, 2])
+            .to_png_with(&options)
+            .unwrap()
+            .iter()
+            .map(width)
+            .collect();
+        assert_eq!(widths, [72, 36]);
+
+        // The lazy and progress-reporting exports take the same options.
+        let lazy: Vec<_> = doc
+            .render_pages(&options)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(lazy, doc.to_png_with(&options).unwrap());
+        let reported = doc.to_png_with_progress(&options, |_, _| {}).unwrap();
+        assert_eq!(reported, lazy);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_progress_counts_pages() {
+        let doc = test_document(&[("main.typ", "a #pagebreak() b #pagebreak() c")]);
+        let mut calls = Vec::new();
+        let pngs = doc
+            .to_png_with_progress(&PngOptions::default(), |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+        assert_eq!(pngs.len(), 3);
+        assert_eq!(calls, [(1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn pixmaps_are_unpremultiplied_rgba() {
+        let doc = test_document(&[(
+            "main.typ",
+            "#set page(width: 1in, height: 2in, fill: rgb(255, 0, 0, 128))",
+        )]);
+        let pages = doc.to_pixmaps(72.0).unwrap();
+        assert_eq!((pages[0].width, pages[0].height), (72, 144));
+        assert_eq!(pages[0].data.len(), 72 * 144 * 4);
+        assert_eq!(pages[0].data[..4], [255, 0, 0, 128]);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn jpeg_quality_trades_size() {
+        let doc = test_document(&[("main.typ", "#lorem(200)")]);
+        let high = doc.to_jpeg(144.0, 95).unwrap();
+        let low = doc.to_jpeg(144.0, 30).unwrap();
+        assert!(high[0].starts_with(&[0xFF, 0xD8]));
+        assert!(low[0].len() < high[0].len());
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn documents_merge_into_one_pdf() {
+        let letter = test_document(&[("letter.typ", "Dear customer")]);
+        let report = test_document(&[("report.typ", "a #pagebreak() b")]);
+        let pdf = crate::merge(&[&letter, &report]).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        assert!(matches!(
+            crate::merge(&[]),
+            Err(Error::InvalidPageSelection(_))
+        ));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn merging_refuses_attachments_and_strips_internal_links() {
+        fn links(frame: &typst::layout::Frame) -> usize {
+            use typst::layout::FrameItem;
+            frame
+                .items()
+                .map(|(_, item)| match item {
+                    FrameItem::Link(..) => 1,
+                    FrameItem::Group(group) => links(&group.frame),
+                    _ => 0,
+                })
+                .sum()
+        }
+
+        let letter = test_document(&[("letter.typ", "Dear customer")]);
+        let invoice = test_document(&[("invoice.typ", "Invoice")])
+            .with_pdf_attachment("factur-x.xml", "<Invoice/>", "text/xml")
+            .unwrap();
+        let Err(Error::InvalidPdfConfig(message)) = crate::merge(&[&letter, &invoice]) else {
+            panic!("expected the attachment to be refused");
+        };
+        assert!(message.contains("document 2"), "{message}");
+
+        let doc = test_document(&[(
+            "links.typ",
+            "= Intro <intro>\n#link(<intro>)[back] #link(\"https://typst.app\")[site]",
+        )]);
+        doc.with_compiled(|compiled| {
+            let page = &compiled.pages[0].frame;
+            let stripped = frames::strip_locations(page);
+            assert_eq!(links(&stripped), 1);
+            assert!(links(page) > links(&stripped));
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn imposition_arranges_pages_on_sheets() {
+        use crate::imposition::{impose, Imposition};
+        use typst::layout::{Abs, Size};
+
+        let doc = test_document(&[(
+            "main.typ",
+            "#set page(width: 100pt, height: 200pt)\na #pagebreak() b #pagebreak() c",
+        )]);
+        doc.with_compiled(|compiled| {
+            let two_up = impose(
+                compiled,
+                &[0, 1, 2],
+                Imposition::NUp {
+                    columns: 2,
+                    rows: 1,
+                },
+            )?;
+            assert_eq!(two_up.pages.len(), 2);
+            assert_eq!(
+                two_up.pages[0].frame.size(),
+                Size::new(Abs::pt(200.0), Abs::pt(200.0))
+            );
+
+            // Three pages pad to one booklet sheet: front and back.
+            let booklet = impose(compiled, &[0, 1, 2], Imposition::Booklet)?;
+            assert_eq!(booklet.pages.len(), 2);
+            Ok(())
+        })
+        .unwrap();
+
+        let pdf = test_document(&[("main.typ", "a #pagebreak() b")])
+            .with_pdf_config(PdfConfig {
+                imposition: Some(Imposition::NUp {
+                    columns: 2,
+                    rows: 1,
+                }),
+                ..Default::default()
+            })
+            .to_pdf()
+            .unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        let attached = test_document(&[("main.typ", "a")])
+            .with_pdf_attachment("factur-x.xml", "<Invoice/>", "text/xml")
+            .unwrap()
+            .with_pdf_config(PdfConfig {
+                imposition: Some(Imposition::Booklet),
+                ..Default::default()
+            });
+        assert!(matches!(attached.to_pdf(), Err(Error::InvalidPdfConfig(_))));
+    }
 
     /// Compile a self-contained broken source and resolve its diagnostics. No
     /// embedded resolver or fonts are needed for an eval-time error.
@@ -644,11 +3026,15 @@ mod tests {
         let world = engine.world_builder().build().expect("world builds");
         let warned = typst::compile::<PagedDocument>(&world);
         typst::comemo::evict(0);
-        let main = world.main();
+        let main = MainFile {
+            path: entry,
+            id: world.main(),
+            prelude_lines: 0,
+        };
         let diagnostics = warned.output.expect_err("source should fail to compile");
         diagnostics
             .iter()
-            .map(|d| diagnostic_from(&world, entry, main, d))
+            .map(|d| diagnostic_from(&world, &main, d))
             .collect()
     }
 
@@ -663,6 +3049,7 @@ mod tests {
             .expect("diagnostic carries a source location");
         // The entry file path matches exactly what was requested.
         assert_eq!(loc.file, "test.typ");
+        assert_eq!(loc.package, None);
         assert_eq!(loc.line, 2);
         assert!(loc.column >= 1);
         assert!(!diagnostics[0].message.is_empty());
@@ -679,8 +3066,10 @@ mod tests {
     #[test]
     fn diagnostic_display_with_location_hints_and_trace() {
         let diagnostic = Diagnostic {
+            severity: Severity::Error,
             location: Some(SourceLocation {
                 file: "report.typ".to_string(),
+                package: None,
                 line: 42,
                 column: 12,
             }),
@@ -688,6 +3077,7 @@ mod tests {
             hints: vec!["try wrapping it".to_string()],
             trace: vec![SourceLocation {
                 file: "main.typ".to_string(),
+                package: None,
                 line: 5,
                 column: 1,
             }],
@@ -701,6 +3091,7 @@ mod tests {
     #[test]
     fn diagnostic_display_without_location() {
         let diagnostic = Diagnostic {
+            severity: Severity::Error,
             location: None,
             message: "boom".to_string(),
             hints: Vec::new(),
@@ -708,4 +3099,16 @@ mod tests {
         };
         assert_eq!(diagnostic.to_string(), "error: boom");
     }
+
+    #[test]
+    fn warning_display_uses_severity() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            location: None,
+            message: "boom".to_string(),
+            hints: Vec::new(),
+            trace: Vec::new(),
+        };
+        assert_eq!(diagnostic.to_string(), "warning: boom");
+    }
 }
@@ -10,8 +10,12 @@ use thiserror::Error;
 /// the line, matching the Typst CLI's reporting.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SourceLocation {
-    /// Path of the source file, e.g. `reports/event_report/report.typ`.
+    /// Path of the source file, e.g. `reports/event_report/report.typ`. Files inside a
+    /// package are prefixed with `namespace/name/version/`.
     pub file: String,
+    /// The package containing the file, e.g. `@preview/cetz:0.3.4`, or `None` for
+    /// template files. Lets error reports tell template bugs from package bugs.
+    pub package: Option<String>,
     /// 1-based line number.
     pub line: usize,
     /// 1-based column number (character count within the line).
@@ -24,9 +28,33 @@ impl fmt::Display for SourceLocation {
     }
 }
 
+/// Whether a [`Diagnostic`] stopped compilation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Compilation failed. Reported through [`Error::Compilation`], or, when
+    /// [`with_fallback`](crate::Document::with_fallback) or
+    /// [`with_error_document`](crate::Document::with_error_document) rendered something
+    /// else in its place, by [`Document::warnings`](crate::Document::warnings).
+    Error,
+    /// Compilation succeeded; reported by
+    /// [`Document::warnings`](crate::Document::warnings).
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
 /// A single Typst compilation diagnostic with resolved source location.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Diagnostic {
+    /// Whether this is an error or a warning.
+    pub severity: Severity,
     /// Where the error occurred, if it points into a source file.
     pub location: Option<SourceLocation>,
     /// The diagnostic message.
@@ -40,8 +68,8 @@ pub struct Diagnostic {
 impl fmt::Display for Diagnostic {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.location {
-            Some(loc) => write!(f, "{loc}: error: {}", self.message)?,
-            None => write!(f, "error: {}", self.message)?,
+            Some(loc) => write!(f, "{loc}: {}: {}", self.severity, self.message)?,
+            None => write!(f, "{}: {}", self.severity, self.message)?,
         }
         for hint in &self.hints {
             write!(f, "\n  hint: {hint}")?;
@@ -63,11 +91,14 @@ fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
 }
 
 /// Errors that can occur during document compilation and rendering.
+///
+/// New variants may be added in minor releases, so `match`es on it need a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Entry file was not found in the embedded templates.
     #[error("entry file not found: {0}")]
-    EntryNotFound(&'static str),
+    EntryNotFound(String),
 
     /// Entry file content is not valid UTF-8.
     #[error("entry file is not valid UTF-8")]
@@ -85,22 +116,164 @@ pub enum Error {
     #[error("PNG encoding failed: {0}")]
     PngEncoding(String),
 
+    /// JPEG encoding failed.
+    #[error("JPEG encoding failed: {0}")]
+    JpegEncoding(String),
+
+    /// Inputs could not be converted to a dictionary.
+    #[error("invalid inputs: {0}")]
+    InvalidInputs(String),
+
     /// Invalid file path provided for runtime file injection.
     #[error("invalid file path: {0}")]
     InvalidFilePath(String),
 
+    /// The requested file is not among the embedded templates.
+    #[error("template file not found: {0}")]
+    FileNotFound(String),
+
+    /// No template set with this name was embedded. See
+    /// [`Document::with_template_set`](crate::Document::with_template_set).
+    #[error("template set not found: {0}")]
+    TemplateSetNotFound(String),
+
     /// Invalid page selection (empty or out of range).
     #[error("invalid page selection: {0}")]
     InvalidPageSelection(String),
 
+    /// A [`Document::query`](crate::Document::query) label was invalid, or a result
+    /// could not be converted to the requested type.
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+
     /// Invalid PDF configuration (e.g. a standard/tagging conflict or bad timestamp).
     #[error("invalid PDF config: {0}")]
     InvalidPdfConfig(String),
 
+    /// The compiled document exceeds the limit set by
+    /// [`Document::with_max_pages`](crate::Document::with_max_pages).
+    #[error("document has {count} pages, exceeding the limit of {limit}")]
+    TooManyPages {
+        /// Number of pages the document laid out.
+        count: usize,
+        /// The configured maximum.
+        limit: usize,
+    },
+
+    /// The exported output exceeds the limit set by
+    /// [`Document::with_max_output_size`](crate::Document::with_max_output_size).
+    #[error("output exceeds the limit of {limit} bytes")]
+    OutputTooLarge {
+        /// The configured maximum, in bytes.
+        limit: usize,
+    },
+
+    /// Decompressing an embedded file would exceed a limit set by
+    /// [`Document::with_decompression_limits`](crate::Document::with_decompression_limits).
+    #[error("decompressing {path} exceeds the limit of {limit} bytes")]
+    DecompressionLimit {
+        /// Path of the embedded file being decompressed.
+        path: String,
+        /// The configured limit (per-file or total) that was hit, in bytes.
+        limit: usize,
+    },
+
     /// Decompression of embedded content failed.
     #[error("decompression failed")]
     Decompression(#[from] std::io::Error),
 }
 
+impl Error {
+    /// Whether the error comes from what the caller passed in rather than from the
+    /// embedded content or the renderer, e.g. to answer HTTP 400 instead of 500.
+    ///
+    /// Covers invalid inputs, runtime file paths, page selections, queries, and template
+    /// set names, and page count or output size limits exceeded by the requested document.
+    ///
+    /// Everything that can also come from the embedded content or from server code
+    /// counts as a server error:
+    /// - [`Compilation`](Self::Compilation): a compile error is reported where the
+    ///   template fails, even when bad inputs caused it, so a broken template and an
+    ///   unexpected value look alike. Inspect [`diagnostics`](Self::diagnostics) for
+    ///   messages your templates raise on purpose, e.g. with `panic` or `assert`.
+    /// - [`DecompressionLimit`](Self::DecompressionLimit): the oversized file is embedded.
+    /// - [`FileNotFound`](Self::FileNotFound): usually a wrong
+    ///   [`read_template`](crate::Document::read_template) path in the caller's code.
+    pub fn is_user_input_error(&self) -> bool {
+        match self {
+            Error::InvalidInputs(_)
+            | Error::InvalidFilePath(_)
+            | Error::TemplateSetNotFound(_)
+            | Error::InvalidPageSelection(_)
+            | Error::InvalidQuery(_)
+            | Error::TooManyPages { .. }
+            | Error::OutputTooLarge { .. } => true,
+            Error::Compilation(_)
+            | Error::FileNotFound(_)
+            | Error::DecompressionLimit { .. }
+            | Error::EntryNotFound(_)
+            | Error::InvalidUtf8
+            | Error::PdfGeneration(_)
+            | Error::PngEncoding(_)
+            | Error::JpegEncoding(_)
+            | Error::InvalidPdfConfig(_)
+            | Error::Decompression(_) => false,
+        }
+    }
+
+    /// Whether a configured resource limit stopped the render: page count, output size,
+    /// or decompressed size.
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self,
+            Error::TooManyPages { .. }
+                | Error::OutputTooLarge { .. }
+                | Error::DecompressionLimit { .. }
+        )
+    }
+
+    /// The diagnostics of a compilation failure; empty for every other error.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            Error::Compilation(diagnostics) => diagnostics,
+            _ => &[],
+        }
+    }
+}
+
 /// A specialized Result type for typst-bake operations.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_classified() {
+        let compile = Error::Compilation(vec![Diagnostic {
+            severity: Severity::Error,
+            location: None,
+            message: "unknown variable: total".into(),
+            hints: Vec::new(),
+            trace: Vec::new(),
+        }]);
+        assert!(!compile.is_user_input_error());
+        assert_eq!(compile.diagnostics().len(), 1);
+
+        let decompression = Error::DecompressionLimit {
+            path: "logo.png".into(),
+            limit: 1,
+        };
+        assert!(!decompression.is_user_input_error());
+        assert!(decompression.is_limit_exceeded());
+        assert!(!Error::FileNotFound("data.csv".into()).is_user_input_error());
+
+        let too_large = Error::OutputTooLarge { limit: 1 };
+        assert!(too_large.is_user_input_error());
+        assert!(too_large.is_limit_exceeded());
+        assert!(too_large.diagnostics().is_empty());
+
+        assert!(!Error::PdfGeneration("font subsetting".into()).is_user_input_error());
+        assert!(!Error::EntryNotFound("main.typ".into()).is_limit_exceeded());
+    }
+}
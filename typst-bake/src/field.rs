@@ -0,0 +1,601 @@
+//! Field conversions for the `IntoValue` / `IntoDict` and `FromValue` / `FromDict`
+//! derives.
+//!
+//! Typst's `IntoValue` can't be implemented for `std` types outside of Typst, so derived
+//! code wraps each field in [`Field`] and calls `into_field_value()`. Method resolution
+//! picks [`StdTimeField`] for `std::time` types, [`DateTimeField`] for `time` and
+//! `chrono` dates and times, [`DecimalField`] for `rust_decimal` and `bigdecimal`
+//! numbers, [`MapField`] for maps with `String` keys, [`StrField`] for shared and
+//! borrowed strings, UUIDs, and URLs, [`TupleField`] for tuples, and
+//! [`IntoValueField`] for everything else; the impls never overlap because none of
+//! those types implement `IntoValue`. A type no conversion covers falls through to
+//! [`UnsupportedField`], which turns the failure into a readable compile error.
+
+use crate::util::utc_datetime;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use typst::diag::{HintedStrResult, HintedString};
+use typst::foundations::{Bytes, Datetime, Dict, FromValue, IntoValue, Str, Value};
+
+/// A struct field on its way to a Typst value.
+pub struct Field<T>(pub T);
+
+/// Conversion for types implementing Typst's `IntoValue`.
+pub trait IntoValueField {
+    fn into_field_value(self) -> Value;
+}
+
+impl<T: IntoValue> IntoValueField for Field<T> {
+    fn into_field_value(self) -> Value {
+        self.0.into_value()
+    }
+}
+
+/// Fallback for field types no conversion covers. It is implemented for `&Field<T>`, so
+/// method resolution only reaches it after rejecting every by-value conversion in this
+/// module. Calling it requires `T: SupportedField`, which no type implements, so the
+/// build fails with the message of [`SupportedField`] on the offending field instead of
+/// an unresolved method.
+pub trait UnsupportedField {
+    type Inner;
+
+    fn into_field_value(self) -> Value
+    where
+        Self::Inner: SupportedField;
+}
+
+impl<T> UnsupportedField for &Field<T> {
+    type Inner = T;
+
+    fn into_field_value(self) -> Value
+    where
+        T: SupportedField,
+    {
+        unreachable!("no type implements `SupportedField`")
+    }
+}
+
+/// Reached only for unsupported field types; see [`UnsupportedField`].
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be converted to a Typst value",
+    label = "unsupported field type",
+    note = "derive `IntoValue` on `{Self}` (or the types it contains), or convert the field with `#[typst(with = \"path::to::function\")]`",
+    note = "see the `IntoValue` derive documentation for the supported field types"
+)]
+pub trait SupportedField {}
+
+/// Conversion for `std::time` types.
+pub trait StdTimeField {
+    fn into_field_value(self) -> Value;
+}
+
+/// A `Duration` becomes a Typst `duration`.
+impl StdTimeField for Field<Duration> {
+    fn into_field_value(self) -> Value {
+        duration_value(self.0)
+    }
+}
+
+/// A `SystemTime` becomes a Typst `datetime` in UTC, truncated to whole seconds like
+/// datetimes created in Typst.
+impl StdTimeField for Field<SystemTime> {
+    fn into_field_value(self) -> Value {
+        system_time_value(self.0)
+    }
+}
+
+/// An optional `Duration` becomes a `duration`, or `none`. Other `Option`s go through
+/// Typst's own `IntoValue` impl, which maps `None` to `none` the same way.
+impl StdTimeField for Field<Option<Duration>> {
+    fn into_field_value(self) -> Value {
+        self.0.map_or(Value::None, duration_value)
+    }
+}
+
+/// An optional `SystemTime` becomes a `datetime`, or `none`.
+impl StdTimeField for Field<Option<SystemTime>> {
+    fn into_field_value(self) -> Value {
+        self.0.map_or(Value::None, system_time_value)
+    }
+}
+
+/// Conversion for dates and times of the `time` crate and, with the `chrono` feature,
+/// of `chrono`. Each becomes a Typst `datetime` truncated to whole seconds, or `none` if
+/// it lies outside the range Typst supports.
+pub trait DateTimeField {
+    fn into_field_value(self) -> Value;
+}
+
+/// Implement [`DateTimeField`] for each type and an `Option` of it, converting with the
+/// given function.
+macro_rules! datetime_fields {
+    ($($ty:ty => $convert:expr,)*) => {$(
+        impl DateTimeField for Field<$ty> {
+            fn into_field_value(self) -> Value {
+                let convert: fn($ty) -> Option<Datetime> = $convert;
+                datetime_value(convert(self.0))
+            }
+        }
+
+        impl DateTimeField for Field<Option<$ty>> {
+            fn into_field_value(self) -> Value {
+                let convert: fn($ty) -> Option<Datetime> = $convert;
+                datetime_value(self.0.and_then(convert))
+            }
+        }
+    )*};
+}
+
+datetime_fields! {
+    time::Date => |date| Some(Datetime::Date(date)),
+    time::Time => |time: time::Time| -> Option<Datetime> {
+        Some(Datetime::Time(time.replace_nanosecond(0).ok()?))
+    },
+    time::PrimitiveDateTime => |datetime: time::PrimitiveDateTime| -> Option<Datetime> {
+        Some(Datetime::Datetime(datetime.replace_nanosecond(0).ok()?))
+    },
+    // Converted to UTC, like `SystemTime`.
+    time::OffsetDateTime => |datetime: time::OffsetDateTime| -> Option<Datetime> {
+        let utc = datetime.to_offset(time::UtcOffset::UTC).replace_nanosecond(0).ok()?;
+        Some(Datetime::Datetime(time::PrimitiveDateTime::new(utc.date(), utc.time())))
+    },
+}
+
+#[cfg(feature = "chrono")]
+datetime_fields! {
+    chrono::NaiveDate => |date| chrono_date(date).map(Datetime::Date),
+    chrono::NaiveTime => |time| chrono_time(time).map(Datetime::Time),
+    chrono::NaiveDateTime => chrono_datetime,
+}
+
+/// A zoned `chrono::DateTime` becomes a `datetime` in UTC, like `SystemTime`.
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> DateTimeField for Field<chrono::DateTime<Tz>> {
+    fn into_field_value(self) -> Value {
+        datetime_value(chrono_datetime(self.0.naive_utc()))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> DateTimeField for Field<Option<chrono::DateTime<Tz>>> {
+    fn into_field_value(self) -> Value {
+        datetime_value(
+            self.0
+                .and_then(|datetime| chrono_datetime(datetime.naive_utc())),
+        )
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_date(date: chrono::NaiveDate) -> Option<time::Date> {
+    use chrono::Datelike;
+    let month = time::Month::try_from(u8::try_from(date.month()).ok()?).ok()?;
+    time::Date::from_calendar_date(date.year(), month, u8::try_from(date.day()).ok()?).ok()
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_time(time: chrono::NaiveTime) -> Option<time::Time> {
+    use chrono::Timelike;
+    let [hour, minute, second] = [time.hour(), time.minute(), time.second()].map(|n| n as u8);
+    time::Time::from_hms(hour, minute, second).ok()
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_datetime(datetime: chrono::NaiveDateTime) -> Option<Datetime> {
+    let date = chrono_date(datetime.date())?;
+    let time = chrono_time(datetime.time())?;
+    Some(Datetime::Datetime(time::PrimitiveDateTime::new(date, time)))
+}
+
+fn datetime_value(datetime: Option<Datetime>) -> Value {
+    datetime.map_or(Value::None, IntoValue::into_value)
+}
+
+/// Conversion for decimal numbers: `rust_decimal::Decimal` with the `rust_decimal`
+/// feature, `bigdecimal::BigDecimal` with the `bigdecimal` feature. Each becomes a Typst
+/// `decimal` without passing through `f64`.
+///
+/// Typst's `decimal` is `rust_decimal` underneath, so a `rust_decimal::Decimal` always
+/// converts unchanged. A `BigDecimal` has no such limit: one with more than 28
+/// significant digits, or outside the range of a Typst `decimal`, is never rounded to
+/// fit and becomes `none` instead, which templates can't tell apart from a missing value.
+pub trait DecimalField {
+    fn into_field_value(self) -> Value;
+}
+
+#[cfg(feature = "rust_decimal")]
+impl DecimalField for Field<rust_decimal::Decimal> {
+    fn into_field_value(self) -> Value {
+        decimal_value(&self.0.to_string())
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl DecimalField for Field<Option<rust_decimal::Decimal>> {
+    fn into_field_value(self) -> Value {
+        self.0
+            .map_or(Value::None, |decimal| decimal_value(&decimal.to_string()))
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl DecimalField for Field<bigdecimal::BigDecimal> {
+    fn into_field_value(self) -> Value {
+        decimal_value(&self.0.to_plain_string())
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl DecimalField for Field<Option<bigdecimal::BigDecimal>> {
+    fn into_field_value(self) -> Value {
+        self.0.map_or(Value::None, |decimal| {
+            decimal_value(&decimal.to_plain_string())
+        })
+    }
+}
+
+/// Parse a plain decimal string, as both crates print them, into a Typst `decimal`.
+#[cfg(any(feature = "rust_decimal", feature = "bigdecimal"))]
+fn decimal_value(decimal: &str) -> Value {
+    decimal
+        .parse::<typst::foundations::Decimal>()
+        .map_or(Value::None, IntoValue::into_value)
+}
+
+/// Typst `bytes` from raw data, for fields marked `#[typst(bytes)]`.
+pub fn bytes_value<T: AsRef<[u8]> + Send + Sync + 'static>(data: T) -> Value {
+    Bytes::new(data).into_value()
+}
+
+/// Read the entry `key` of a dictionary for the `FromValue` / `FromDict` derives. A
+/// missing entry reads as `none`, so `Option` fields may be left out.
+pub fn dict_field<T: FromValue>(dict: &mut Dict, key: &str) -> HintedStrResult<T> {
+    match dict.take(key) {
+        Ok(value) => T::from_value(value)
+            .map_err(|err| HintedString::from(format!("field `{key}`: {}", err.message()))),
+        Err(_) => T::from_value(Value::None)
+            .map_err(|_| HintedString::from(format!("missing field `{key}`"))),
+    }
+}
+
+/// Like [`dict_field`], but a missing entry reads as `T::default()`, for fields that
+/// `#[typst(skip_if = "...")]` may have left out.
+pub fn dict_field_or_default<T: FromValue + Default>(
+    dict: &mut Dict,
+    key: &str,
+) -> HintedStrResult<T> {
+    if dict.contains(key) {
+        dict_field(dict, key)
+    } else {
+        Ok(T::default())
+    }
+}
+
+/// Conversion for maps with `String` keys.
+pub trait MapField {
+    fn into_field_value(self) -> Value;
+}
+
+/// A `BTreeMap` becomes a dictionary in key order.
+impl<V: IntoValue> MapField for Field<BTreeMap<String, V>> {
+    fn into_field_value(self) -> Value {
+        map_value(self.0)
+    }
+}
+
+/// A `HashMap` becomes a dictionary sorted by key, so the same data always renders the
+/// same way.
+impl<V: IntoValue> MapField for Field<HashMap<String, V>> {
+    fn into_field_value(self) -> Value {
+        let mut entries: Vec<_> = self.0.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        map_value(entries)
+    }
+}
+
+/// Conversion for types passed to templates as strings: string smart pointers, which
+/// Typst's `IntoValue` doesn't cover (only `String` and `&str`), and, with the `uuid` and
+/// `url` features, `uuid::Uuid` and `url::Url`.
+pub trait StrField {
+    fn into_field_value(self) -> Value;
+}
+
+/// Implement [`StrField`] for each string type and an `Option` of it.
+macro_rules! str_fields {
+    ($($ty:ty),*) => {$(
+        impl StrField for Field<$ty> {
+            fn into_field_value(self) -> Value {
+                Value::Str(Str::from(&*self.0))
+            }
+        }
+
+        impl StrField for Field<Option<$ty>> {
+            fn into_field_value(self) -> Value {
+                self.0.map_or(Value::None, |string| Value::Str(Str::from(&*string)))
+            }
+        }
+    )*};
+}
+
+str_fields!(Cow<'_, str>, Arc<str>, Rc<str>, Box<str>);
+
+/// A `Uuid` becomes its hyphenated, lowercase form.
+#[cfg(feature = "uuid")]
+impl StrField for Field<uuid::Uuid> {
+    fn into_field_value(self) -> Value {
+        Value::Str(Str::from(self.0.hyphenated().to_string()))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl StrField for Field<Option<uuid::Uuid>> {
+    fn into_field_value(self) -> Value {
+        self.0
+            .map_or(Value::None, |uuid| StrField::into_field_value(Field(uuid)))
+    }
+}
+
+#[cfg(feature = "url")]
+impl StrField for Field<url::Url> {
+    fn into_field_value(self) -> Value {
+        Value::Str(Str::from(self.0.as_str()))
+    }
+}
+
+#[cfg(feature = "url")]
+impl StrField for Field<Option<url::Url>> {
+    fn into_field_value(self) -> Value {
+        self.0
+            .map_or(Value::None, |url| StrField::into_field_value(Field(url)))
+    }
+}
+
+/// Conversion for tuples of two to six elements, each implementing `IntoValue`. A tuple
+/// becomes an array, like a tuple struct. Fixed-size arrays `[T; N]` need no help: Typst
+/// implements `IntoValue` for them.
+pub trait TupleField {
+    fn into_field_value(self) -> Value;
+}
+
+/// Implement [`TupleField`] for each tuple arity and an `Option` of it.
+macro_rules! tuple_fields {
+    ($(($($name:ident),+))*) => {$(
+        impl<$($name: IntoValue),+> TupleField for Field<($($name,)+)> {
+            #[allow(non_snake_case)]
+            fn into_field_value(self) -> Value {
+                let ($($name,)+) = self.0;
+                Value::Array([$($name.into_value()),+].into_iter().collect())
+            }
+        }
+
+        impl<$($name: IntoValue),+> TupleField for Field<Option<($($name,)+)>> {
+            fn into_field_value(self) -> Value {
+                self.0.map_or(Value::None, |tuple| TupleField::into_field_value(Field(tuple)))
+            }
+        }
+    )*};
+}
+
+tuple_fields! {
+    (A, B)
+    (A, B, C)
+    (A, B, C, D)
+    (A, B, C, D, E)
+    (A, B, C, D, E, F)
+}
+
+fn map_value<V: IntoValue>(entries: impl IntoIterator<Item = (String, V)>) -> Value {
+    let dict: Dict = entries
+        .into_iter()
+        .map(|(key, value)| (Str::from(key), value.into_value()))
+        .collect();
+    Value::Dict(dict)
+}
+
+fn duration_value(duration: Duration) -> Value {
+    // Saturates past ~292 billion years, far beyond anything a template can display.
+    let duration = time::Duration::try_from(duration).unwrap_or(time::Duration::MAX);
+    typst::foundations::Duration::from(duration).into_value()
+}
+
+fn system_time_value(time: SystemTime) -> Value {
+    match utc_datetime(time) {
+        Some(utc) => {
+            Datetime::Datetime(time::PrimitiveDateTime::new(utc.date(), utc.time())).into_value()
+        }
+        None => Value::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn std_time_fields_convert() {
+        let value = Field(Duration::from_secs(90)).into_field_value();
+        assert_eq!(
+            value.ty(),
+            typst::foundations::Type::of::<typst::foundations::Duration>()
+        );
+
+        let value = Field(UNIX_EPOCH + Duration::from_millis(86_400_500)).into_field_value();
+        let Value::Datetime(datetime) = value else {
+            panic!("expected a datetime, got {value:?}");
+        };
+        assert_eq!(datetime.ordinal(), Some(2));
+        assert_eq!(datetime.second(), Some(0));
+
+        let value = Field("plain".to_string()).into_field_value();
+        assert_eq!(value, "plain".into_value());
+    }
+
+    #[test]
+    fn time_crate_types_convert() {
+        let date = time::Date::from_calendar_date(2024, time::Month::May, 1).unwrap();
+        let value = Field(date).into_field_value();
+        assert_eq!(value, Datetime::Date(date).into_value());
+
+        let noon = time::Time::from_hms_milli(12, 30, 15, 500).unwrap();
+        let datetime = time::PrimitiveDateTime::new(date, noon)
+            .assume_offset(time::UtcOffset::from_hms(2, 0, 0).unwrap());
+        let Value::Datetime(datetime) = Field(Some(datetime)).into_field_value() else {
+            panic!("expected a datetime");
+        };
+        assert_eq!(datetime.hour(), Some(10));
+        assert_eq!(datetime.second(), Some(15));
+        assert_eq!(Field(None::<time::Date>).into_field_value(), Value::None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_types_convert() {
+        use chrono::TimeZone;
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let expected = time::Date::from_calendar_date(2024, time::Month::May, 1).unwrap();
+        let value = Field(date).into_field_value();
+        assert_eq!(value, Datetime::Date(expected).into_value());
+
+        let zoned = chrono::FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 5, 1, 12, 30, 15)
+            .unwrap();
+        let Value::Datetime(datetime) = Field(zoned).into_field_value() else {
+            panic!("expected a datetime");
+        };
+        assert_eq!(datetime.hour(), Some(10));
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn rust_decimals_convert_exactly() {
+        let amount = rust_decimal::Decimal::new(-199_999_999_999_999, 2);
+        let expected: typst::foundations::Decimal = "-1999999999999.99".parse().unwrap();
+        assert_eq!(Field(amount).into_field_value(), expected.into_value());
+        assert_eq!(
+            Field(None::<rust_decimal::Decimal>).into_field_value(),
+            Value::None
+        );
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn bigdecimals_convert_or_become_none() {
+        let amount: bigdecimal::BigDecimal = "12.50".parse().unwrap();
+        let expected: typst::foundations::Decimal = "12.50".parse().unwrap();
+        assert_eq!(Field(amount).into_field_value(), expected.into_value());
+
+        let huge: bigdecimal::BigDecimal = "1e40".parse().unwrap();
+        assert_eq!(Field(huge).into_field_value(), Value::None);
+
+        // 29 significant digits: not rounded to 28.
+        let precise: bigdecimal::BigDecimal = "0.12345678901234567890123456789".parse().unwrap();
+        assert_eq!(Field(precise).into_field_value(), Value::None);
+    }
+
+    #[test]
+    fn maps_convert_to_sorted_dicts() {
+        let map = HashMap::from([("b".to_string(), 2_i64), ("a".to_string(), 1_i64)]);
+        let Value::Dict(dict) = Field(map).into_field_value() else {
+            panic!("expected a dictionary");
+        };
+        let keys: Vec<_> = dict.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["a", "b"]);
+
+        let map = BTreeMap::from([("total".to_string(), 3.5)]);
+        let Value::Dict(dict) = Field(map).into_field_value() else {
+            panic!("expected a dictionary");
+        };
+        assert_eq!(dict.get("total").unwrap(), &3.5.into_value());
+    }
+
+    #[test]
+    fn bytes_stay_bytes() {
+        let value = bytes_value(vec![0x89, b'P', b'N', b'G']);
+        assert_eq!(value, Bytes::new(b"\x89PNG".to_vec()).into_value());
+        assert!(matches!(bytes_value(&b"GIF89a"[..]), Value::Bytes(_)));
+    }
+
+    #[test]
+    fn dict_fields_are_read_by_key() {
+        let mut dict = typst::foundations::dict! { "total" => 42, "note" => "paid" };
+        assert_eq!(dict_field::<i64>(&mut dict, "total").unwrap(), 42);
+        assert_eq!(
+            dict_field::<Option<i64>>(&mut dict, "discount").unwrap(),
+            None
+        );
+
+        let err = dict_field::<i64>(&mut dict, "note").unwrap_err();
+        assert!(err.message().starts_with("field `note`"));
+        let err = dict_field::<i64>(&mut dict, "pages").unwrap_err();
+        assert_eq!(err.message(), "missing field `pages`");
+        let pages = dict_field_or_default::<Vec<i64>>(&mut dict, "pages").unwrap();
+        assert!(pages.is_empty());
+    }
+
+    #[test]
+    fn string_pointers_become_strings() {
+        let expected = "Acme".into_value();
+        assert_eq!(Field(Cow::Borrowed("Acme")).into_field_value(), expected);
+        assert_eq!(
+            Field(Cow::<str>::Owned("Acme".into())).into_field_value(),
+            expected
+        );
+        assert_eq!(Field(Arc::<str>::from("Acme")).into_field_value(), expected);
+        assert_eq!(Field(Rc::<str>::from("Acme")).into_field_value(), expected);
+        assert_eq!(Field(Box::<str>::from("Acme")).into_field_value(), expected);
+        assert_eq!(Field("Acme").into_field_value(), expected);
+        assert_eq!(Field(None::<Arc<str>>).into_field_value(), Value::None);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuids_become_strings() {
+        let uuid = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        assert_eq!(
+            Field(uuid).into_field_value(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8".into_value()
+        );
+        assert_eq!(Field(None::<uuid::Uuid>).into_field_value(), Value::None);
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn urls_become_strings() {
+        let url = url::Url::parse("https://example.com/invoices?id=7").unwrap();
+        assert_eq!(
+            Field(url).into_field_value(),
+            "https://example.com/invoices?id=7".into_value()
+        );
+    }
+
+    #[test]
+    fn tuples_and_arrays_become_arrays() {
+        let rgb = [255, 128, 0].into_value();
+        assert_eq!(Field((255, 128, 0)).into_field_value(), rgb);
+        assert_eq!(Field([255, 128, 0]).into_field_value(), rgb);
+
+        let pair = Field((1.5, "mm")).into_field_value();
+        assert_eq!(
+            pair,
+            Value::Array([1.5.into_value(), "mm".into_value()].into_iter().collect())
+        );
+        assert_eq!(Field(None::<(f64, f64)>).into_field_value(), Value::None);
+    }
+
+    #[test]
+    fn options_convert_to_none() {
+        assert_eq!(Field(None::<Duration>).into_field_value(), Value::None);
+        assert_eq!(Field(None::<SystemTime>).into_field_value(), Value::None);
+        assert_eq!(Field(None::<String>).into_field_value(), Value::None);
+
+        let value = Field(Some(Duration::from_secs(90))).into_field_value();
+        assert_eq!(value, Field(Duration::from_secs(90)).into_field_value());
+        assert_eq!(Field(Some(3_i64)).into_field_value(), 3_i64.into_value());
+    }
+}
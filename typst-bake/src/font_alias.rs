@@ -0,0 +1,286 @@
+//! Font family aliases.
+//!
+//! Typst looks up `text(font: ..)` by the family name stored in each font's `name` table.
+//! An alias is realized by adding a copy of the target font whose family name is rewritten
+//! to the alias, so templates naming an unavailable font (e.g. "Helvetica") render with
+//! the embedded substitute instead of falling back to an arbitrary font.
+
+use typst::foundations::Bytes;
+use typst::text::Font;
+
+const NAME_FAMILY: u16 = 1;
+const NAME_POSTSCRIPT: u16 = 6;
+const NAME_TYPOGRAPHIC_FAMILY: u16 = 16;
+
+/// Windows platform, Unicode BMP encoding, US English.
+const WINDOWS_UNICODE_EN_US: (u16, u16, u16) = (3, 1, 0x0409);
+
+/// Renamed copies of every font in `fonts` whose family is the target of an alias.
+///
+/// Families are compared case-insensitively, as Typst does. Font collections (`.ttc`) are
+/// not supported as alias targets and are skipped.
+pub(crate) fn aliased_fonts<'a>(
+    fonts: impl IntoIterator<Item = &'a [u8]>,
+    aliases: &[(String, String)],
+) -> Vec<Vec<u8>> {
+    let mut renamed = Vec::new();
+    for data in fonts {
+        let Some(font) = Font::new(Bytes::new(data.to_vec()), 0) else {
+            continue;
+        };
+        let family = &font.info().family;
+        for (alias, target) in aliases {
+            if family.eq_ignore_ascii_case(target) {
+                renamed.extend(rename_family(data, alias));
+            }
+        }
+    }
+    renamed
+}
+
+/// Whether `family` is reachable under `alias` according to `aliases`.
+pub(crate) fn is_alias_of(aliases: &[(String, String)], alias: &str, family: &str) -> bool {
+    aliases
+        .iter()
+        .any(|(a, t)| a.eq_ignore_ascii_case(alias) && t.eq_ignore_ascii_case(family))
+}
+
+/// Rewrite the family name of a single-face font file.
+///
+/// Replaces the family, typographic family, and PostScript name records; everything else
+/// is copied unchanged. The PostScript name is prefixed with the alias so the copy stays
+/// distinguishable from the original in exported PDFs. Returns `None` for collections and
+/// malformed files.
+pub(crate) fn rename_family(data: &[u8], family: &str) -> Option<Vec<u8>> {
+    if data.starts_with(b"ttcf") {
+        return None;
+    }
+
+    let num_tables = usize::from(read_u16(data, 4)?);
+    let mut tables = Vec::with_capacity(num_tables);
+    for i in 0..num_tables {
+        let record = 12 + 16 * i;
+        let tag: [u8; 4] = data.get(record..record + 4)?.try_into().ok()?;
+        let checksum = read_u32(data, record + 4)?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let len = read_u32(data, record + 12)? as usize;
+        let table = data.get(offset..offset.checked_add(len)?)?.to_vec();
+        tables.push((tag, checksum, table));
+    }
+
+    let (_, checksum, name) = tables.iter_mut().find(|(tag, ..)| tag == b"name")?;
+    *name = rename_name_table(name, family)?;
+    *checksum = table_checksum(name);
+
+    // Offset table is unchanged: same version and table count.
+    let mut out = data.get(..12)?.to_vec();
+    let mut offset = 12 + 16 * num_tables;
+    let mut body = Vec::new();
+    for (tag, checksum, table) in &tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum.to_be_bytes());
+        out.extend_from_slice(&u32::try_from(offset).ok()?.to_be_bytes());
+        out.extend_from_slice(&u32::try_from(table.len()).ok()?.to_be_bytes());
+
+        let padded = table.len().next_multiple_of(4);
+        body.extend_from_slice(table);
+        body.resize(body.len() + padded - table.len(), 0);
+        offset += padded;
+    }
+    out.extend(body);
+    Some(out)
+}
+
+/// A `name` table record: (platform, encoding, language, name ID, string bytes).
+type NameRecord = (u16, u16, u16, u16, Vec<u8>);
+
+/// Parse the records of a `name` table. Records using language tags are skipped.
+fn name_records(table: &[u8]) -> Option<Vec<NameRecord>> {
+    let count = usize::from(read_u16(table, 2)?);
+    let storage = usize::from(read_u16(table, 4)?);
+
+    let mut records = Vec::with_capacity(count);
+    for i in 0..count {
+        let r = 6 + 12 * i;
+        let language = read_u16(table, r + 4)?;
+        if language >= 0x8000 {
+            continue;
+        }
+        let len = usize::from(read_u16(table, r + 8)?);
+        let start = storage + usize::from(read_u16(table, r + 10)?);
+        records.push((
+            read_u16(table, r)?,
+            read_u16(table, r + 2)?,
+            language,
+            read_u16(table, r + 6)?,
+            table.get(start..start + len)?.to_vec(),
+        ));
+    }
+    Some(records)
+}
+
+/// Build a format 0 `name` table with the family records replaced.
+fn rename_name_table(table: &[u8], family: &str) -> Option<Vec<u8>> {
+    let mut records = name_records(table)?;
+
+    let original_ps = records
+        .iter()
+        .find(|r| r.3 == NAME_POSTSCRIPT)
+        .map(|(platform, .., bytes)| decode_name(*platform, bytes))
+        .unwrap_or_default();
+    let postscript: String = ascii_alnum(family)
+        .chain(['-'])
+        .chain(ascii_alnum(&original_ps))
+        .take(63)
+        .collect();
+
+    records.retain(|r| ![NAME_FAMILY, NAME_TYPOGRAPHIC_FAMILY, NAME_POSTSCRIPT].contains(&r.3));
+    let (platform, encoding, language) = WINDOWS_UNICODE_EN_US;
+    records.push((
+        platform,
+        encoding,
+        language,
+        NAME_FAMILY,
+        encode_utf16(family),
+    ));
+    records.push((
+        platform,
+        encoding,
+        language,
+        NAME_POSTSCRIPT,
+        encode_utf16(&postscript),
+    ));
+    records.sort_by_key(|r| (r.0, r.1, r.2, r.3));
+
+    let count = u16::try_from(records.len()).ok()?;
+    let mut out = Vec::new();
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend_from_slice(&12u16.checked_mul(count)?.checked_add(6)?.to_be_bytes());
+
+    let mut storage = Vec::new();
+    for (platform, encoding, language, name_id, bytes) in &records {
+        for value in [*platform, *encoding, *language, *name_id] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        out.extend_from_slice(&u16::try_from(bytes.len()).ok()?.to_be_bytes());
+        out.extend_from_slice(&u16::try_from(storage.len()).ok()?.to_be_bytes());
+        storage.extend_from_slice(bytes);
+    }
+    out.extend(storage);
+    Some(out)
+}
+
+/// Decode a name string: UTF-16BE for Unicode and Windows platforms, bytes otherwise.
+fn decode_name(platform: u16, bytes: &[u8]) -> String {
+    if platform == 0 || platform == 3 {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|&b| char::from(b)).collect()
+    }
+}
+
+fn encode_utf16(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+fn ascii_alnum(s: &str) -> impl Iterator<Item = char> + '_ {
+    s.chars().filter(char::is_ascii_alphanumeric)
+}
+
+fn table_checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal sfnt holding only a `name` table with the given records.
+    fn font_with_names(names: &[(u16, &str)]) -> Vec<u8> {
+        let mut name = Vec::new();
+        name.extend_from_slice(&0u16.to_be_bytes());
+        name.extend_from_slice(&(names.len() as u16).to_be_bytes());
+        name.extend_from_slice(&(6 + 12 * names.len() as u16).to_be_bytes());
+        let mut storage = Vec::new();
+        for (id, value) in names {
+            let bytes = encode_utf16(value);
+            for v in [3, 1, 0x0409, *id, bytes.len() as u16, storage.len() as u16] {
+                name.extend_from_slice(&v.to_be_bytes());
+            }
+            storage.extend(bytes);
+        }
+        name.extend(storage);
+
+        let mut font = vec![0, 1, 0, 0, 0, 1, 0, 16, 0, 0, 0, 0];
+        font.extend_from_slice(b"name");
+        font.extend_from_slice(&table_checksum(&name).to_be_bytes());
+        font.extend_from_slice(&28u32.to_be_bytes());
+        font.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        font.extend(name);
+        font
+    }
+
+    fn names_of(font: &[u8]) -> Vec<(u16, String)> {
+        let offset = read_u32(font, 12 + 8).unwrap() as usize;
+        let len = read_u32(font, 12 + 12).unwrap() as usize;
+        name_records(&font[offset..offset + len])
+            .unwrap()
+            .into_iter()
+            .map(|(platform, .., id, bytes)| (id, decode_name(platform, &bytes)))
+            .collect()
+    }
+
+    #[test]
+    fn rename_replaces_family_and_postscript_names() {
+        let font = font_with_names(&[
+            (1, "Inter"),
+            (2, "Bold"),
+            (4, "Inter Bold"),
+            (6, "Inter-Bold"),
+            (16, "Inter"),
+        ]);
+        let renamed = rename_family(&font, "Helvetica").unwrap();
+        assert_eq!(
+            names_of(&renamed),
+            vec![
+                (1, "Helvetica".to_owned()),
+                (2, "Bold".to_owned()),
+                (4, "Inter Bold".to_owned()),
+                (6, "Helvetica-InterBold".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn collections_are_not_renamed() {
+        assert!(rename_family(b"ttcf\0\x01\0\0", "Helvetica").is_none());
+    }
+
+    #[test]
+    fn alias_lookup_ignores_case() {
+        let aliases = vec![("Helvetica".to_owned(), "Inter".to_owned())];
+        assert!(is_alias_of(&aliases, "helvetica", "INTER"));
+        assert!(!is_alias_of(&aliases, "Inter", "Helvetica"));
+    }
+}
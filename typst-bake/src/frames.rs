@@ -0,0 +1,125 @@
+//! Helpers for walking the frames of a compiled document.
+
+use std::collections::HashSet;
+#[cfg(feature = "pdf")]
+use typst::foundations::NativeElement;
+use typst::layout::{Abs, Frame, FrameItem, PagedDocument, Point};
+#[cfg(feature = "pdf")]
+use typst::model::Destination;
+#[cfg(feature = "pdf")]
+use typst::pdf::AttachElem;
+use typst::text::{Font, FontVariant, TextItem};
+
+/// Call `f` for every text run in `frame`, including those nested in groups.
+///
+/// Positions are relative to the top-left corner of `frame`. Group transforms other
+/// than the translation given by the group's position are not applied.
+pub(crate) fn for_each_text(frame: &Frame, f: &mut impl FnMut(Point, &TextItem)) {
+    walk(frame, Point::zero(), f);
+}
+
+fn walk(frame: &Frame, origin: Point, f: &mut impl FnMut(Point, &TextItem)) {
+    for (pos, item) in frame.items() {
+        let pos = origin + *pos;
+        match item {
+            FrameItem::Group(group) => walk(&group.frame, pos, f),
+            FrameItem::Text(text) => f(pos, text),
+            _ => {}
+        }
+    }
+}
+
+/// The text of `frame` in reading order: one line per baseline, top to bottom, runs left
+/// to right.
+///
+/// Runs whose baselines are less than half a font size apart share a line. A space is
+/// inserted between runs separated by a visible gap, since Typst emits each word or
+/// style change as its own run and the spaces between them only exist as positions.
+pub(crate) fn plain_text(frame: &Frame) -> String {
+    let mut runs: Vec<(Point, Abs, &str, Abs)> = Vec::new();
+    for_each_text(frame, &mut |pos, text| {
+        runs.push((pos, text.width(), text.text.as_str(), text.size));
+    });
+    runs.sort_by(|a, b| a.0.y.cmp(&b.0.y).then(a.0.x.cmp(&b.0.x)));
+
+    // Group into lines, then order each line horizontally.
+    let mut lines: Vec<Vec<(Point, Abs, &str, Abs)>> = Vec::new();
+    for run in runs {
+        match lines.last_mut() {
+            Some(line) if (run.0.y - line[0].0.y).abs() < line[0].3 / 2.0 => line.push(run),
+            _ => lines.push(vec![run]),
+        }
+    }
+
+    let mut out = String::new();
+    for mut line in lines {
+        line.sort_by(|a, b| a.0.x.cmp(&b.0.x));
+        let mut end: Option<Abs> = None;
+        for (pos, width, text, size) in line {
+            if end.is_some_and(|end| pos.x - end > size * 0.1) {
+                out.push(' ');
+            }
+            out.push_str(text);
+            end = Some(pos.x + width);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Identity of a font face as Typst's font book sees it.
+pub(crate) type FaceKey = (String, FontVariant);
+
+/// The key identifying `font` in [`used_faces`].
+pub(crate) fn face_key(font: &Font) -> FaceKey {
+    let info = font.info();
+    (info.family.clone(), info.variant)
+}
+
+/// All font faces that contributed at least one glyph to `document`.
+pub(crate) fn used_faces(document: &PagedDocument) -> HashSet<FaceKey> {
+    let mut used = HashSet::new();
+    for page in &document.pages {
+        for_each_text(&page.frame, &mut |_, text| {
+            if !text.glyphs.is_empty() {
+                used.insert(face_key(&text.font));
+            }
+        });
+    }
+    used
+}
+
+/// A copy of `frame` without the items that refer to its document's introspector:
+/// introspection tags and links to a location or page inside the document.
+///
+/// Needed when pages are laid out into a new document, whose introspector knows none of
+/// those locations and whose page numbers differ. External links are kept.
+#[cfg(feature = "pdf")]
+pub(crate) fn strip_locations(frame: &Frame) -> Frame {
+    let mut stripped = frame.clone();
+    stripped.clear();
+    for (pos, item) in frame.items() {
+        let item = match item {
+            FrameItem::Tag(_) => continue,
+            FrameItem::Link(Destination::Location(_) | Destination::Position(_), _) => continue,
+            FrameItem::Group(group) => {
+                let mut group = group.clone();
+                group.frame = strip_locations(&group.frame);
+                FrameItem::Group(group)
+            }
+            item => item.clone(),
+        };
+        stripped.push(*pos, item);
+    }
+    stripped
+}
+
+/// Whether `document` has PDF attachments, from `pdf.attach` in the template or
+/// [`Document::with_pdf_attachment`](crate::Document::with_pdf_attachment).
+#[cfg(feature = "pdf")]
+pub(crate) fn has_attachments(document: &PagedDocument) -> bool {
+    !document
+        .introspector
+        .query(&AttachElem::ELEM.select())
+        .is_empty()
+}
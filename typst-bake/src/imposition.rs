@@ -0,0 +1,140 @@
+//! Page imposition for print workflows.
+//!
+//! Imposition arranges the pages of a compiled document onto larger sheets. Sheets are
+//! sized to hold their pages at 100% (two A4 pages side by side make an A3 landscape
+//! sheet), so the output can go straight to a printer or print shop.
+
+use crate::error::{Error, Result};
+use crate::frames;
+use typst::foundations::Smart;
+use typst::layout::{Frame, FrameItem, Page, PagedDocument, Point, Size};
+use typst::syntax::Span;
+use typst::visualize::Geometry;
+
+/// How to arrange pages on sheets. Set via [`PdfConfig::imposition`](crate::PdfConfig::imposition).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Imposition {
+    /// Place pages in reading order on a grid of `columns` × `rows` per sheet, e.g.
+    /// `NUp { columns: 2, rows: 1 }` for 2-up proofs. The last sheet may be partly empty.
+    NUp {
+        /// Pages per row. Must be at least 1.
+        columns: usize,
+        /// Rows per sheet. Must be at least 1.
+        rows: usize,
+    },
+    /// Saddle-stitch booklet: two pages per sheet side, ordered so that the printed
+    /// sheets, stacked, folded in the middle, and stapled, read in order.
+    ///
+    /// Output alternates front and back sides of each sheet for duplex printing (flip on
+    /// the short edge). Blank pages are appended to reach a multiple of four.
+    Booklet,
+}
+
+/// Lay out `pages` (indices into `document`) according to `imposition`.
+///
+/// Each cell of a sheet is as large as the largest page. Page fills are drawn into the
+/// sheet. Links to locations inside the document and the outline are removed, since
+/// their targets no longer correspond to sheet pages. A document with PDF attachments is
+/// rejected rather than silently losing them.
+pub(crate) fn impose(
+    document: &PagedDocument,
+    pages: &[usize],
+    imposition: Imposition,
+) -> Result<PagedDocument> {
+    if frames::has_attachments(document) {
+        return Err(Error::InvalidPdfConfig(
+            "imposition would drop the document's PDF attachments".into(),
+        ));
+    }
+    let sources: Vec<&Page> = pages.iter().map(|&i| &document.pages[i]).collect();
+    let Some(&first) = sources.first() else {
+        return Err(Error::InvalidPageSelection("no pages to impose".into()));
+    };
+
+    let cell = sources
+        .iter()
+        .fold(Size::zero(), |size, page| size.max(page.frame.size()));
+
+    let (columns, rows, order) = match imposition {
+        Imposition::NUp { columns, rows } => {
+            if columns == 0 || rows == 0 {
+                return Err(Error::InvalidPdfConfig(
+                    "n-up imposition needs at least one column and one row".into(),
+                ));
+            }
+            (
+                columns,
+                rows,
+                (0..sources.len()).map(Some).collect::<Vec<_>>(),
+            )
+        }
+        Imposition::Booklet => (2, 1, booklet_order(sources.len())),
+    };
+
+    let sheet_size = Size::new(cell.x * columns as f64, cell.y * rows as f64);
+    let sheets = order
+        .chunks(columns * rows)
+        .enumerate()
+        .map(|(number, slots)| {
+            let mut sheet = Frame::hard(sheet_size);
+            for (slot, source) in slots.iter().enumerate() {
+                let Some(page) = source.map(|i| sources[i]) else {
+                    continue;
+                };
+                let pos = Point::new(
+                    cell.x * (slot % columns) as f64,
+                    cell.y * (slot / columns) as f64,
+                );
+                if let Smart::Custom(Some(paint)) = &page.fill {
+                    let background = Geometry::Rect(page.frame.size()).filled(paint.clone());
+                    sheet.push(pos, FrameItem::Shape(background, Span::detached()));
+                }
+                sheet.push_frame(pos, frames::strip_locations(&page.frame));
+            }
+
+            let mut page = first.clone();
+            page.frame = sheet;
+            page.fill = Smart::Auto;
+            page.number = number as u64 + 1;
+            page
+        })
+        .collect();
+
+    Ok(PagedDocument {
+        pages: sheets,
+        info: document.info.clone(),
+        introspector: Default::default(),
+    })
+}
+
+/// Slot order for a saddle-stitch booklet of `count` pages, two slots per sheet side.
+///
+/// `None` marks a blank page added to pad the count to a multiple of four.
+fn booklet_order(count: usize) -> Vec<Option<usize>> {
+    let padded = count.next_multiple_of(4);
+    let page = |i: usize| (i < count).then_some(i);
+
+    let mut order = Vec::with_capacity(padded);
+    for sheet in 0..padded / 4 {
+        // Front: last unplaced page on the left, first on the right; back: the reverse.
+        order.extend([page(padded - 1 - 2 * sheet), page(2 * sheet)]);
+        order.extend([page(2 * sheet + 1), page(padded - 2 - 2 * sheet)]);
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn booklet_order_for_eight_pages() {
+        let order: Vec<_> = booklet_order(8).into_iter().map(Option::unwrap).collect();
+        assert_eq!(order, [7, 0, 1, 6, 5, 2, 3, 4]);
+    }
+
+    #[test]
+    fn booklet_order_pads_with_blanks() {
+        assert_eq!(booklet_order(3), [None, Some(0), Some(1), Some(2)]);
+    }
+}
@@ -0,0 +1,223 @@
+//! Runtime listings of embedded content.
+
+use include_dir::{Dir, File};
+use typst::text::{Font, FontFlags, FontStyle};
+
+/// A font face embedded in the document, as Typst's font book sees it.
+///
+/// Returned by [`Document::fonts`](crate::Document::fonts).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    /// Font file path relative to `fonts-dir`.
+    pub path: String,
+    /// Index of the face within the file (non-zero only for collections).
+    pub index: u32,
+    /// Family name used by `text(font: ..)`.
+    pub family: String,
+    /// Face style.
+    pub style: FaceStyle,
+    /// Weight, from 100 (thin) to 900 (black).
+    pub weight: u16,
+    /// Width relative to normal, from 0.5 (ultra-condensed) to 2.0 (ultra-expanded).
+    pub stretch: f64,
+    /// Whether all glyphs have the same advance width.
+    pub monospace: bool,
+    /// Number of Unicode code points the face has glyphs for.
+    pub codepoints: usize,
+}
+
+/// Style of a [`FontFace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaceStyle {
+    /// Upright.
+    Normal,
+    /// Cursive italic.
+    Italic,
+    /// Slanted upright.
+    Oblique,
+}
+
+impl FontFace {
+    pub(crate) fn new(path: String, font: &Font) -> Self {
+        let info = font.info();
+        Self {
+            path,
+            index: font.index(),
+            family: info.family.clone(),
+            style: match info.variant.style {
+                FontStyle::Normal => FaceStyle::Normal,
+                FontStyle::Italic => FaceStyle::Italic,
+                FontStyle::Oblique => FaceStyle::Oblique,
+            },
+            weight: info.variant.weight.to_number(),
+            stretch: info.variant.stretch.to_ratio().get(),
+            monospace: info.flags.contains(FontFlags::MONOSPACE),
+            codepoints: info.coverage.iter().count(),
+        }
+    }
+}
+
+/// A template file embedded in the document.
+///
+/// Returned by [`Document::files`](crate::Document::files).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateFile {
+    /// Path relative to the template root, with `/` separators, e.g. `assets/logo.svg`.
+    pub path: String,
+    /// Decompressed size in bytes.
+    pub original_size: usize,
+    /// Size in bytes as stored in the binary. Files with identical content share storage,
+    /// so sizes of duplicates are counted once in [`EmbedStats`](crate::EmbedStats) but
+    /// for every copy here.
+    pub compressed_size: usize,
+}
+
+/// A Typst package embedded in the document.
+///
+/// Returned by [`Document::packages`](crate::Document::packages). Includes every package
+/// the templates import, directly or through other packages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedPackage {
+    /// Package namespace, e.g. `preview` or `local`.
+    pub namespace: String,
+    /// Package name, e.g. `cetz`.
+    pub name: String,
+    /// Package version, e.g. `0.3.4`.
+    pub version: String,
+    /// Number of embedded files.
+    pub file_count: usize,
+    /// `license` from the package's `typst.toml`, if present.
+    pub license: Option<String>,
+    /// `authors` from the package's `typst.toml`.
+    pub authors: Vec<String>,
+    /// `repository` from the package's `typst.toml`, if present.
+    pub repository: Option<String>,
+}
+
+impl EmbeddedPackage {
+    /// The package spec as written in an import, e.g. `@preview/cetz:0.3.4`.
+    pub fn spec(&self) -> String {
+        format!("@{}/{}:{}", self.namespace, self.name, self.version)
+    }
+
+    /// Build the entry for the package stored in `dir`, given its decompressed
+    /// `typst.toml` if the package has one.
+    pub(crate) fn new(namespace: &str, name: &str, dir: &Dir<'_>, manifest: Option<&[u8]>) -> Self {
+        let package = manifest
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .and_then(|text| text.parse::<toml::Table>().ok())
+            .and_then(|mut table| match table.remove("package") {
+                Some(toml::Value::Table(package)) => Some(package),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let string = |key: &str| {
+            package
+                .get(key)
+                .and_then(toml::Value::as_str)
+                .map(str::to_owned)
+        };
+
+        Self {
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+            version: dir_name(dir),
+            file_count: count_files(dir),
+            license: string("license"),
+            authors: package
+                .get("authors")
+                .and_then(toml::Value::as_array)
+                .map(|authors| {
+                    authors
+                        .iter()
+                        .filter_map(toml::Value::as_str)
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            repository: string("repository"),
+        }
+    }
+}
+
+/// The last path component of `dir`.
+pub(crate) fn dir_name(dir: &Dir<'_>) -> String {
+    dir.path()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Every file in `dir` and its subdirectories with its `/`-separated path relative to
+/// `dir`, sorted by path.
+pub(crate) fn files<'a>(dir: &'a Dir<'a>) -> Vec<(String, &'a File<'a>)> {
+    fn walk<'a>(dir: &'a Dir<'a>, prefix: &str, out: &mut Vec<(String, &'a File<'a>)>) {
+        for file in dir.files() {
+            let name = file.path().to_string_lossy();
+            out.push((format!("{prefix}{name}"), file));
+        }
+        for sub in dir.dirs() {
+            walk(sub, &format!("{prefix}{}/", dir_name(sub)), out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(dir, "", &mut out);
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    out
+}
+
+/// Number of files in `dir` and its subdirectories.
+fn count_files(dir: &Dir<'_>) -> usize {
+    dir.files().count() + dir.dirs().map(count_files).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use include_dir::{DirEntry, File};
+
+    static VERSION_DIR: Dir<'static> = Dir::new(
+        "0.3.4",
+        &[
+            DirEntry::File(File::new("typst.toml", b"")),
+            DirEntry::Dir(Dir::new(
+                "src",
+                &[DirEntry::File(File::new("lib.typ", b""))],
+            )),
+        ],
+    );
+
+    #[test]
+    fn package_reads_manifest_fields() {
+        let manifest = br#"
+[package]
+name = "cetz"
+version = "0.3.4"
+license = "LGPL-3.0-only"
+authors = ["Johannes Wolf", "fenjalien"]
+repository = "https://github.com/cetz-package/cetz"
+"#;
+        let pkg = EmbeddedPackage::new("preview", "cetz", &VERSION_DIR, Some(manifest));
+        assert_eq!(pkg.spec(), "@preview/cetz:0.3.4");
+        assert_eq!(pkg.file_count, 2);
+        assert_eq!(pkg.license.as_deref(), Some("LGPL-3.0-only"));
+        assert_eq!(pkg.authors, ["Johannes Wolf", "fenjalien"]);
+        assert_eq!(
+            pkg.repository.as_deref(),
+            Some("https://github.com/cetz-package/cetz")
+        );
+    }
+
+    #[test]
+    fn files_have_nested_paths() {
+        let paths: Vec<_> = files(&VERSION_DIR).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, ["src/lib.typ", "typst.toml"]);
+    }
+
+    #[test]
+    fn package_without_manifest() {
+        let pkg = EmbeddedPackage::new("local", "mine", &VERSION_DIR, None);
+        assert_eq!(pkg.license, None);
+        assert!(pkg.authors.is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+//! JSON inputs.
+
+use crate::document::Document;
+use crate::error::{Error, Result};
+use serde_json::Value as Json;
+use typst::foundations::{Array, Dict, IntoValue, Value};
+
+impl Document {
+    /// Add input data from a JSON object, e.g. a request body.
+    ///
+    /// Objects become dictionaries, arrays become arrays, and `null` becomes `none`.
+    /// Integers that fit in an `i64` become Typst integers; other numbers become floats.
+    /// Otherwise this behaves like [`with_inputs`](Self::with_inputs).
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidInputs`] if `json` is not an object, since `sys.inputs`
+    /// is always a dictionary.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let body: serde_json::Value = serde_json::from_slice(&request_body)?;
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .with_json_inputs(body)?
+    ///     .to_pdf()?;
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn with_json_inputs(self, json: Json) -> Result<Self> {
+        match json_to_value(json) {
+            Value::Dict(dict) => Ok(self.with_inputs(dict)),
+            other => Err(Error::InvalidInputs(format!(
+                "expected a JSON object, got {}",
+                other.ty()
+            ))),
+        }
+    }
+}
+
+pub(crate) fn json_to_value(json: Json) -> Value {
+    match json {
+        Json::Null => Value::None,
+        Json::Bool(b) => b.into_value(),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => i.into_value(),
+            None => n.as_f64().unwrap_or(f64::NAN).into_value(),
+        },
+        Json::String(s) => s.into_value(),
+        Json::Array(items) => items
+            .into_iter()
+            .map(json_to_value)
+            .collect::<Array>()
+            .into_value(),
+        Json::Object(map) => map
+            .into_iter()
+            .map(|(k, v)| (k.into(), json_to_value(v)))
+            .collect::<Dict>()
+            .into_value(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn json_maps_to_typst_values() {
+        let value = json_to_value(json!({
+            "name": "Ada",
+            "count": 3,
+            "big": 18446744073709551615u64,
+            "ratio": 0.5,
+            "tags": ["a", null, true],
+        }));
+        let Value::Dict(dict) = value else {
+            panic!("expected a dictionary");
+        };
+        assert_eq!(dict.get("name").unwrap(), &"Ada".into_value());
+        assert_eq!(dict.get("count").unwrap(), &3i64.into_value());
+        assert_eq!(
+            dict.get("big").unwrap(),
+            &18446744073709551615f64.into_value()
+        );
+        assert_eq!(dict.get("ratio").unwrap(), &0.5f64.into_value());
+        let tags: Array = ["a".into_value(), Value::None, true.into_value()]
+            .into_iter()
+            .collect();
+        assert_eq!(dict.get("tags").unwrap(), &tags.into_value());
+    }
+}
@@ -0,0 +1,68 @@
+//! Page geometry of the compiled document.
+
+use crate::document::Document;
+use crate::error::Result;
+
+/// Page count and page sizes, returned by [`Document::layout_info`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutInfo {
+    /// Size of each page, in order.
+    pub pages: Vec<PageSize>,
+}
+
+impl LayoutInfo {
+    /// Number of pages.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// The size of one page in typographic points (1/72 inch).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSize {
+    /// Width in points.
+    pub width: f64,
+    /// Height in points.
+    pub height: f64,
+}
+
+impl PageSize {
+    /// Width and height in millimeters.
+    pub fn to_mm(self) -> (f64, f64) {
+        const MM_PER_PT: f64 = 25.4 / 72.0;
+        (self.width * MM_PER_PT, self.height * MM_PER_PT)
+    }
+}
+
+impl Document {
+    /// Return the page count and the size of every page.
+    ///
+    /// Compiles the document if not already compiled, but exports nothing, so callers
+    /// can check pagination (or price a print job) before paying for a render. The
+    /// compiled result is cached, so a following [`to_pdf`](Self::to_pdf) reuses it.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let info = doc.layout_info()?;
+    /// if info.pages.iter().any(|page| page.width > page.height) {
+    ///     // has landscape pages
+    /// }
+    /// let price = info.page_count() as u32 * PRICE_PER_PAGE;
+    /// ```
+    pub fn layout_info(&self) -> Result<LayoutInfo> {
+        self.with_compiled(|compiled| {
+            let pages = compiled
+                .pages
+                .iter()
+                .map(|page| {
+                    let size = page.frame.size();
+                    PageSize {
+                        width: size.x.to_pt(),
+                        height: size.y.to_pt(),
+                    }
+                })
+                .collect();
+            Ok(LayoutInfo { pages })
+        })
+    }
+}
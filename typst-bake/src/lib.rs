@@ -20,7 +20,7 @@
 //! - **Multi-Format Output** - Generate PDF, SVG, or PNG with optional [page selection](`Document::select_pages`)
 //! - **Self-Contained Binary** - Templates, fonts, and packages are all embedded into the binary at compile time. No external files or internet connection needed at runtime
 //! - **Automatic Package Resolution** - Just use `#import "@preview/..."` as in Typst. Packages are resolved automatically using Typst's own cache and data directories
-//! - **Runtime Inputs** - Pass dynamic data from Rust structs to Typst via [`IntoValue`] / [`IntoDict`] derive macros
+//! - **Runtime Inputs** - Pass dynamic data from Rust structs to Typst via [`IntoValue`] / [`IntoDict`] derive macros, and read results back with [`FromValue`] / [`FromDict`]
 //! - **Runtime Files** - Inject files at runtime with [`Document::add_file`] for dynamically generated content or downloaded resources
 //! - **Optimized Binary Size** - Embedded resources are deduplicated and compressed automatically
 //!
@@ -49,25 +49,103 @@
 //! let pngs = doc.to_png(144.0)?; // 144 DPI
 //! std::fs::write("page1.png", &pngs[0])?;
 //! ```
+//!
+//! ## Input Safety
+//!
+//! Inputs passed with [`Document::with_inputs`] are **data, never code**. Every string
+//! becomes a Typst `str` value; typst-bake does not parse or evaluate it, so a string such
+//! as `#read("secret.txt")` is rendered literally and cannot call functions or read
+//! embedded files. This holds for nested fields as well: the derive macros convert each
+//! field with `IntoValue`, which never evaluates strings.
+//!
+//! Markup is an explicit opt-in on both sides. Wrap trusted strings in [`Markup`] in Rust,
+//! and render them with the `markup` helper typst-bake provides to every template:
+//!
+//! ```typ
+//! #import "/.typst-bake/markup.typ": markup
+//! #markup(sys.inputs.note)
+//! ```
+//!
+//! The helper only evaluates values created by [`Markup`] in the same process. Plain
+//! strings, and dictionaries made to look like a `Markup` value in JSON or other inputs,
+//! are refused, so text that was not wrapped in Rust can't reach it.
+//! Avoid calling `eval` on inputs directly: it accepts any string, and evaluated markup
+//! runs with the same capabilities as the template itself, including `read()` access to
+//! every embedded file.
 
+mod batch;
 mod build;
+mod clock;
+mod compiled;
+mod data_files;
 mod document;
 mod error;
+mod field;
+mod font_alias;
+mod frames;
+#[cfg(feature = "pdf")]
+mod imposition;
+mod inventory;
+#[cfg(feature = "json")]
+mod json;
+mod layout;
+mod library;
+mod limits;
+mod markup;
+#[cfg(feature = "pdf")]
+mod merge;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
 #[cfg(feature = "pdf")]
 mod pdf_config;
+#[cfg(feature = "png")]
+mod pixmap;
+mod pool;
+mod query;
+mod render_cache;
 mod resolver;
+#[cfg(feature = "serde")]
+mod serde_bridge;
 mod stats;
+mod typed;
 mod util;
 
+pub use batch::BatchOptions;
 pub use build::rebuild_if_changed;
+pub use compiled::CompiledDocument;
+#[cfg(feature = "png")]
+#[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+pub use compiled::RenderPages;
 pub use document::{Document, Pages};
-pub use error::{Diagnostic, Error, Result, SourceLocation};
+pub use error::{Diagnostic, Error, Result, Severity, SourceLocation};
+#[cfg(feature = "pdf")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+pub use imposition::Imposition;
+pub use inventory::{EmbeddedPackage, FaceStyle, FontFace, TemplateFile};
+pub use layout::{LayoutInfo, PageSize};
+pub use limits::DecompressionLimits;
+pub use markup::Markup;
+#[cfg(feature = "pdf")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+pub use merge::merge;
 #[cfg(feature = "pdf")]
 #[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
 pub use pdf_config::{PdfConfig, PdfStandard, PdfTimestamp};
+#[cfg(feature = "png")]
+#[cfg_attr(docsrs, doc(cfg(feature = "png")))]
+pub use pixmap::{Background, PngOptions, RgbaImage};
+pub use pool::{DocumentPool, PoolSession};
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub use serde_bridge::to_value;
 pub use stats::{
     CategoryStats, DedupStats, EmbedStats, HasCompressionRatio, PackageInfo, PackageStats,
+    StatsFormat,
 };
+pub use typed::RequiresInputs;
+/// Experimental Typst features, for [`Document::with_feature`].
+pub use typst::Feature;
 /// Creates a [`Document`] with embedded templates, fonts, and packages.
 ///
 /// # Usage
@@ -89,6 +167,13 @@ pub use stats::{
 /// let last_page = doc.select_pages([total - 1]).to_png(72.0)?;
 /// ```
 ///
+/// Require inputs of a given type before rendering (see [`RequiresInputs`]):
+///
+/// ```rust,ignore
+/// let invoice_doc = typst_bake::document!("invoice.typ", inputs = Invoice);
+/// let pdf = invoice_doc.with_inputs(invoice).to_pdf()?;
+/// ```
+///
 /// # Configuration
 ///
 /// Add to your `Cargo.toml`:
@@ -101,16 +186,56 @@ pub use stats::{
 /// # What Gets Embedded
 ///
 /// - **Templates**: All files in `template-dir` are embedded and accessible from `.typ` files.
-///   Paths resolve relative to the referring `.typ` file.
+///   Paths resolve relative to the referring `.typ` file; paths starting with `/` resolve
+///   from `template-dir`.
+/// - **Root directory**: `root-dir`, if set, must contain `template-dir` and is embedded in
+///   its place. Paths starting with `/` then resolve from `root-dir`, and paths given to
+///   [`Document::has_file`] or [`Document::read_template`] are relative to it.
+/// - **Several directories**: `template-dirs = ["./templates", "./shared-assets"]` replaces
+///   `template-dir` and merges every listed directory into one virtual root. The entry may
+///   live in any of them; a file present in more than one directory is a compile error.
+/// - **Template sets**: With `template-sets = true`, each subdirectory of `template-dir` is
+///   a template set containing the entry file; see [`Document::with_template_set`].
+///   `default-template-set` names the set used until another is picked, and is required.
 /// - **Fonts**: Only supported font formats (TTF, OTF, TTC) are embedded. At least one font
 ///   is required; without fonts, Typst produces invisible text.
+/// - **Google Fonts**: Families listed in `google-fonts` (e.g. `["Inter:400,700,400i",
+///   "Noto Sans KR"]`) are downloaded at build time, cached, and embedded next to the fonts
+///   in `fonts-dir`. With `google-fonts` set, `fonts-dir` becomes optional.
+/// - **Font aliases**: `font-aliases = { "Helvetica" = "Inter" }` makes embedded families
+///   available under other names; see [`Document::with_font_alias`].
 /// - **Packages**: Using packages requires no manual setup. Just use `#import "@preview/..."`
 ///   or `#import "@local/..."` as you normally would in Typst. The macro scans for package
 ///   imports and recursively resolves all dependencies at compile time. Shares Typst's own
 ///   package directories, so locally installed packages are picked up automatically.
+///
+/// # IDE Analysis
+///
+/// Under rust-analyzer, `cargo check`, and `cargo clippy` the macro skips scanning,
+/// downloading, and compression and expands to an empty stub `Document`, keeping the IDE
+/// and checks fast. Check builds are recognized by rustc emitting metadata without
+/// linking. The stub type-checks like the real document but fails with
+/// [`Error::EntryNotFound`] if rendered. Set `TYPST_BAKE_STUB=1` to force the stub
+/// elsewhere, or `TYPST_BAKE_STUB=0` to always do the full expansion, e.g. to have
+/// `cargo check` report missing templates.
+///
+/// # Build Output
+///
+/// Progress lines and warnings are printed to the build output. Set `log = "warn"` in
+/// `[package.metadata.typst-bake]` to keep only warnings, or `log = "quiet"` to print
+/// nothing; `TYPST_BAKE_LOG` takes precedence over the manifest.
+///
+/// # Documentation Builds
+///
+/// When `DOCS_RS` is set, the macro expands to the same stub without reading any
+/// configuration, so crates using typst-bake build their documentation on docs.rs without
+/// template or fonts directories or network access. This is an environment variable rather
+/// than a Cargo feature on purpose: features unify across the dependency graph, so one
+/// crate enabling it would silently empty every real build. Set `TYPST_BAKE_STUB=1` for
+/// documentation builds elsewhere.
 pub use typst_bake_macros::document;
 
-/// Derive macro for converting a struct to a Typst value.
+/// Derive macro for converting a struct or enum to a Typst value.
 ///
 /// All structs that will be passed to Typst templates (directly or nested) must derive this.
 ///
@@ -141,12 +266,113 @@ pub use typst_bake_macros::document;
 ///   - #product.name: $#product.price
 /// ]
 /// ```
+///
+/// Besides types implementing Typst's `IntoValue`, fields may be a
+/// [`std::time::Duration`], which becomes a Typst `duration`, or a
+/// [`std::time::SystemTime`], which becomes a UTC `datetime` truncated to whole seconds.
+/// This applies to the field type itself and to `Option`s of it, not to `Vec<SystemTime>`.
+///
+/// Dates and times of the `time` crate (`Date`, `Time`, `PrimitiveDateTime`,
+/// `OffsetDateTime`) and, with the `chrono` feature, of `chrono` (`NaiveDate`,
+/// `NaiveTime`, `NaiveDateTime`, `DateTime<Tz>`) become Typst `datetime` values, so
+/// templates can format them with `.display()`. Zoned values are converted to UTC, and
+/// all are truncated to whole seconds.
+///
+/// With the `rust_decimal` or `bigdecimal` feature, `rust_decimal::Decimal` and
+/// `bigdecimal::BigDecimal` fields become Typst `decimal` values without rounding
+/// through `f64`. A `rust_decimal::Decimal` always fits. A `BigDecimal` only converts if
+/// it fits the 28 significant digits and range of a Typst `decimal`; **otherwise the field
+/// silently becomes `none`** rather than a rounded number. If your amounts can exceed
+/// that, round them first or convert the field with `#[typst(with = "...")]`.
+///
+/// `HashMap<String, V>` and `BTreeMap<String, V>` fields, with `V` implementing
+/// `IntoValue`, become dictionaries. Entries are sorted by key for both, so output does
+/// not depend on hash order.
+///
+/// Besides `String`, string fields may be a `&str`, `Cow<str>`, `Arc<str>`, `Rc<str>`,
+/// or `Box<str>`, so input structs can borrow or share text instead of cloning it.
+/// With the `uuid` or `url` feature, `uuid::Uuid` (hyphenated, lowercase) and `url::Url`
+/// fields become strings too, without `.to_string()` shadow fields.
+///
+/// Fixed-size arrays `[T; N]` and tuples of two to six elements become Typst arrays, so
+/// coordinate pairs, RGB triples, and matrix rows don't need a `Vec`. Tuple elements must
+/// implement `IntoValue`.
+///
+/// `Option` fields become their value, or `none` for `None`, so templates can test them
+/// with `if inputs.due != none`.
+///
+/// A field of any other type fails to compile with an error pointing at the field, e.g.
+/// "`Product` can't be converted to a Typst value", suggesting to derive [`IntoValue`] on
+/// it or to convert it with `#[typst(with = "...")]`.
+///
+/// # Generics
+///
+/// Generic types such as `struct Page<T> { items: Vec<T> }` are supported. Every type
+/// parameter gets an `IntoValue` bound, so `Page<Invoice>` converts when `Invoice` derives
+/// [`IntoValue`].
+///
+/// # Tuple Structs
+///
+/// A newtype such as `struct Money(Decimal)` converts to its inner value, so wrapper
+/// types are transparent to templates. A tuple struct with several fields, such as
+/// `struct Point(f64, f64)`, becomes an array. Only structs with named fields can derive
+/// [`IntoDict`].
+///
+/// # Enums
+///
+/// Enums are tagged like serde's default representation, so templates see the same
+/// shape as with `Document::with_inputs_serde`: a unit variant becomes its name as a
+/// string, and a variant with data becomes a dictionary with one entry, from the
+/// variant name to its value (newtype), array (tuple), or dictionary (struct).
+///
+/// ```rust,ignore
+/// #[derive(IntoValue)]
+/// enum Status {
+///     Draft,                    // "Draft"
+///     Paid { on: String },      // (Paid: (on: "2024-05-01"))
+///     Refunded(f64),            // (Refunded: 12.5)
+/// }
+/// ```
+///
+/// ```typ
+/// #if inputs.status == "Draft" [DRAFT] else if "Paid" in inputs.status [Paid]
+/// ```
+///
+/// # Field Attributes
+///
+/// - `#[typst(rename = "key")]` - Use `key` as the dictionary key instead of the field
+///   name, e.g. to follow the kebab-case convention of Typst templates.
+/// - `#[typst(skip)]` - Leave the field out, e.g. internal IDs or database handles. The
+///   field's type then doesn't need to be convertible.
+/// - `#[typst(skip_if = "path::to::predicate")]` - Leave the field out when the predicate,
+///   called with a reference to the field, returns `true`, e.g. `"Option::is_none"` or
+///   `"Vec::is_empty"`. The key is then absent rather than `none`, so templates can fall
+///   back with `inputs.at("due", default: ..)`.
+/// - `#[typst(bytes)]` - Pass a `Vec<u8>`, `Box<[u8]>`, `&'static [u8]`, or other
+///   `AsRef<[u8]>` field as Typst `bytes`, e.g. for `image(inputs.logo)`. Without it, a
+///   `Vec<u8>` becomes an array of integers.
+/// - `#[typst(with = "path::to::function")]` - Convert the field with a function taking
+///   the field by value and returning a `typst::foundations::Value`, for third-party
+///   types that can't implement `IntoValue` because of the orphan rules.
+///
+/// `rename` also applies to enum variants.
+///
+/// ```rust,ignore
+/// #[derive(IntoValue, IntoDict)]
+/// struct Invoice {
+///     #[typst(rename = "invoice-number")]
+///     invoice_number: u32,  // sys.inputs.invoice-number
+///     #[typst(skip)]
+///     db_id: i64,           // not passed to the template
+/// }
+/// ```
 pub use typst_bake_macros::IntoValue;
 
 /// Derive macro for converting a struct to a Typst dictionary.
 ///
 /// Only the top-level struct passed to [`Document::with_inputs`] needs this.
-/// Nested structs should only derive [`IntoValue`].
+/// Nested structs should only derive [`IntoValue`]. Accepts the same
+/// [field attributes](IntoValue#field-attributes).
 ///
 /// # Example
 ///
@@ -171,6 +397,45 @@ pub use typst_bake_macros::IntoValue;
 /// ```
 pub use typst_bake_macros::IntoDict;
 
+/// Derive macro for reading a struct back from a Typst dictionary.
+///
+/// The struct implements Typst's `FromValue`, so it can be the result type of
+/// [`Document::query`], turning metadata computed in the template into typed Rust.
+/// Fields may be of any type implementing `FromValue`, including other structs deriving
+/// this macro, `Vec`s, and `Option`s. A missing entry reads as `none`, so `Option`
+/// fields may be left out; entries without a matching field are ignored.
+///
+/// Only structs with named fields are supported. `#[typst(rename = "key")]` reads the
+/// field from `key`, `#[typst(skip)]` fills it with `Default::default()`, and a field
+/// with `#[typst(skip_if = "...")]` reads as its default when the entry is missing;
+/// `with` and `bytes` can't be reversed and are rejected.
+///
+/// # Example
+///
+/// ```typ
+/// #metadata((total: items.map(i => i.price).sum(), pages: counter(page).final()))
+///   <summary>
+/// ```
+///
+/// ```rust,ignore
+/// use typst_bake::FromValue;
+///
+/// #[derive(FromValue)]
+/// struct Summary {
+///     total: f64,
+///     pages: Vec<i64>,
+/// }
+///
+/// let summary: Summary = doc.query("summary")?.remove(0);
+/// ```
+pub use typst_bake_macros::FromValue;
+
+/// Derive macro for reading a struct from a Typst dictionary.
+///
+/// Adds `from_dict(Dict)` and a `TryFrom<Dict>` conversion, the counterpart of
+/// [`IntoDict`]. Accepts the same field attributes as [`FromValue`].
+pub use typst_bake_macros::FromDict;
+
 /// Re-export include_dir for macro-generated code.
 #[doc(hidden)]
 pub use include_dir;
@@ -185,4 +450,10 @@ pub mod __internal {
     pub use include_dir;
     // Re-export typst crate for derive macros
     pub use typst;
+    // Per-field conversions used by derive macros
+    pub use super::field::{
+        bytes_value, dict_field, dict_field_or_default, DateTimeField, DecimalField, Field,
+        IntoValueField, MapField, StdTimeField, StrField, SupportedField, TupleField,
+        UnsupportedField,
+    };
 }
@@ -0,0 +1,59 @@
+//! A standard library built by this crate, for Typst features typst-as-lib leaves off.
+
+use typst::diag::FileResult;
+use typst::foundations::{Bytes, Datetime, Dict};
+use typst::syntax::{FileId, Source};
+use typst::text::{Font, FontBook};
+use typst::utils::LazyHash;
+use typst::{Feature, Library, World};
+
+/// A [`World`] that serves its own standard library and delegates everything else.
+pub(crate) struct WithLibrary<'a> {
+    pub world: &'a dyn World,
+    pub library: LazyHash<Library>,
+}
+
+impl<'a> WithLibrary<'a> {
+    /// Wrap `world` with a library holding `inputs` as `sys.inputs` and `features`
+    /// enabled.
+    pub fn new(world: &'a dyn World, inputs: Dict, features: &[Feature]) -> Self {
+        let library = Library::builder()
+            .with_inputs(inputs)
+            .with_features(features.iter().copied().collect())
+            .build();
+        Self {
+            world,
+            library: LazyHash::new(library),
+        }
+    }
+}
+
+impl World for WithLibrary<'_> {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        self.world.book()
+    }
+
+    fn main(&self) -> FileId {
+        self.world.main()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        self.world.source(id)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.world.file(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.world.font(index)
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        self.world.today(offset)
+    }
+}
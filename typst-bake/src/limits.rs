@@ -0,0 +1,181 @@
+//! Resource limits applied while decompressing embedded content.
+//!
+//! [`DecompressionLimits`] is passed to
+//! [`Document::with_decompression_limits`](crate::Document::with_decompression_limits).
+//! Each compilation gets a fresh [`DecompressionBudget`] that tracks how much has been
+//! decompressed so far and enforces the limits for every blob it hands out.
+
+use crate::error::{Error, Result};
+use crate::util::{decompress, decompress_limited, trace_span};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Upper bounds on decompressed sizes of embedded content.
+///
+/// The default sets no limits. Embedded resources are produced by the `document!` macro
+/// from your own files, so limits mainly guard against corrupted data or maliciously
+/// crafted compressed blobs that expand far beyond their stored size.
+///
+/// ```
+/// use typst_bake::DecompressionLimits;
+///
+/// let limits = DecompressionLimits {
+///     per_file: Some(16 * 1024 * 1024),
+///     total: Some(128 * 1024 * 1024),
+/// };
+/// assert!(limits.per_file.is_some());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecompressionLimits {
+    /// Maximum decompressed size of a single file, in bytes.
+    pub per_file: Option<usize>,
+    /// Maximum decompressed size of all files read during one compilation, in bytes.
+    ///
+    /// Covers the entry file, fonts, and every template or package file the document
+    /// reads. Runtime files added with [`Document::add_file`](crate::Document::add_file)
+    /// are stored uncompressed and do not count.
+    pub total: Option<usize>,
+}
+
+/// Per-compilation decompression accounting.
+///
+/// Shared between the document (entry file, fonts) and the file resolver, which runs
+/// inside Typst and can only report plain file errors. The first limit violation is
+/// remembered so the document can surface it as a typed [`Error`] afterwards.
+pub(crate) struct DecompressionBudget {
+    limits: DecompressionLimits,
+    used: AtomicUsize,
+    exceeded: Mutex<Option<(String, usize)>>,
+}
+
+impl DecompressionBudget {
+    pub(crate) fn new(limits: DecompressionLimits) -> Self {
+        Self {
+            limits,
+            used: AtomicUsize::new(0),
+            exceeded: Mutex::new(None),
+        }
+    }
+
+    /// Decompress `data` (the embedded file at `path`), enforcing the limits.
+    pub(crate) fn decompress(&self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        trace_span!(TRACE, "decompress", path);
+        let Some((room, limit)) = self.binding() else {
+            return Ok(decompress(data)?);
+        };
+
+        match decompress_limited(data, room)? {
+            Some(bytes) => {
+                self.used.fetch_add(bytes.len(), Ordering::Relaxed);
+                Ok(bytes)
+            }
+            None => Err(self.exceed(path, limit)),
+        }
+    }
+
+    /// Account for `len` bytes of `path` that were decompressed by an earlier
+    /// compilation and are being reused, enforcing the limits as if they were
+    /// decompressed now.
+    pub(crate) fn charge(&self, path: &str, len: usize) -> Result<()> {
+        match self.binding() {
+            Some((room, limit)) if len > room => Err(self.exceed(path, limit)),
+            _ => {
+                self.used.fetch_add(len, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+
+    /// The room left under the tightest configured limit, paired with the value to
+    /// report, or `None` if no limit is set.
+    fn binding(&self) -> Option<(usize, usize)> {
+        let per_file = self.limits.per_file.map(|limit| (limit, limit));
+        let total = self.limits.total.map(|limit| {
+            let used = self.used.load(Ordering::Relaxed);
+            (limit.saturating_sub(used), limit)
+        });
+        per_file
+            .into_iter()
+            .chain(total)
+            .min_by_key(|&(room, _)| room)
+    }
+
+    /// Record a violation of `limit` by `path` and return the error for it.
+    fn exceed(&self, path: &str, limit: usize) -> Error {
+        self.exceeded
+            .lock()
+            .expect("lock poisoned")
+            .get_or_insert_with(|| (path.to_owned(), limit));
+        Error::DecompressionLimit {
+            path: path.to_owned(),
+            limit,
+        }
+    }
+
+    /// The first limit violation recorded by [`decompress`](Self::decompress), if any.
+    pub(crate) fn exceeded(&self) -> Option<Error> {
+        self.exceeded
+            .lock()
+            .expect("lock poisoned")
+            .clone()
+            .map(|(path, limit)| Error::DecompressionLimit { path, limit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressed(len: usize) -> Vec<u8> {
+        zstd::encode_all(vec![b'a'; len].as_slice(), 1).unwrap()
+    }
+
+    #[test]
+    fn unlimited_budget_decompresses_everything() {
+        let budget = DecompressionBudget::new(DecompressionLimits::default());
+        assert_eq!(
+            budget.decompress("a", &compressed(4096)).unwrap().len(),
+            4096
+        );
+        assert!(budget.exceeded().is_none());
+    }
+
+    #[test]
+    fn per_file_limit_is_enforced() {
+        let budget = DecompressionBudget::new(DecompressionLimits {
+            per_file: Some(1000),
+            total: None,
+        });
+        assert!(budget.decompress("small", &compressed(1000)).is_ok());
+        assert!(matches!(
+            budget.decompress("big", &compressed(1001)),
+            Err(Error::DecompressionLimit { limit: 1000, .. })
+        ));
+        assert!(budget.exceeded().is_some());
+    }
+
+    #[test]
+    fn total_limit_spans_files() {
+        let budget = DecompressionBudget::new(DecompressionLimits {
+            per_file: Some(1000),
+            total: Some(1500),
+        });
+        assert!(budget.decompress("one", &compressed(800)).is_ok());
+        let err = budget.decompress("two", &compressed(800)).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DecompressionLimit { ref path, limit: 1500 } if path == "two"
+        ));
+    }
+
+    #[test]
+    fn reused_data_is_charged() {
+        let budget = DecompressionBudget::new(DecompressionLimits {
+            per_file: None,
+            total: Some(1500),
+        });
+        assert!(budget.charge("font.ttf", 1000).is_ok());
+        assert!(budget.charge("font.ttc", 600).is_err());
+        assert!(budget.exceeded().is_some());
+    }
+}
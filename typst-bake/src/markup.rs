@@ -0,0 +1,129 @@
+//! Opt-in evaluated markup for inputs.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::OnceLock;
+
+use typst::foundations::{Dict, IntoValue, Str, Value};
+
+/// Directory reserved for files typst-bake provides to templates. Runtime files can't be
+/// added under it, so nothing can replace the helper or a verified source.
+pub(crate) const RESERVED_DIR: &str = ".typst-bake";
+
+/// Virtual path of the template helper that evaluates [`Markup`] inputs.
+pub(crate) const HELPER_PATH: &str = ".typst-bake/markup.typ";
+
+/// Keys of the dictionary a [`Markup`] converts to. Not valid Typst identifiers, so they
+/// can only be read with `.at()` and never collide with a field of a derived struct.
+const SOURCE_KEY: &str = "typst-bake:markup";
+const TAG_KEY: &str = "typst-bake:tag";
+
+/// Source of the helper at [`HELPER_PATH`]. It never evaluates the string in its
+/// argument: it only evaluates the file [`verified_sources`] wrote for the argument's
+/// tag, which exists only for genuine [`Markup`] values.
+pub(crate) const HELPER_SOURCE: &str = r#"#let markup(value) = {
+  let tag = if type(value) == dictionary { value.at("typst-bake:tag", default: none) }
+  assert(
+    type(tag) == str and tag.match(regex("^[0-9a-f]{32}$")) != none,
+    message: "markup() takes a typst_bake::Markup input, not " + repr(type(value)),
+  )
+  eval(read("/.typst-bake/markup/" + tag + ".typ"), mode: "markup")
+}
+"#;
+
+/// Tag authenticating `source`: a SipHash of it under a secret key drawn once per
+/// process, so it can't be computed for a string that didn't go through [`Markup`].
+fn tag(source: &str) -> String {
+    static KEY: OnceLock<RandomState> = OnceLock::new();
+    let key = KEY.get_or_init(RandomState::new);
+    format!(
+        "{:016x}{:016x}",
+        key.hash_one((0u8, source)),
+        key.hash_one((1u8, source))
+    )
+}
+
+/// The runtime files backing every genuine [`Markup`] value in `inputs`, at the paths
+/// the helper reads them from. A dictionary whose tag doesn't match its source, e.g. one
+/// forged in JSON input, gets no file, so the helper fails on it.
+pub(crate) fn verified_sources(inputs: &Dict) -> Vec<(String, Vec<u8>)> {
+    fn collect(value: &Value, files: &mut Vec<(String, Vec<u8>)>) {
+        match value {
+            Value::Dict(dict) => {
+                if let (Ok(Value::Str(source)), Ok(Value::Str(claimed))) =
+                    (dict.get(SOURCE_KEY), dict.get(TAG_KEY))
+                {
+                    let expected = tag(source);
+                    if expected == claimed.as_str() {
+                        let path = format!("{RESERVED_DIR}/markup/{expected}.typ");
+                        files.push((path, source.as_bytes().to_vec()));
+                    }
+                }
+                for (_, value) in dict.iter() {
+                    collect(value, files);
+                }
+            }
+            Value::Array(array) => {
+                for value in array.iter() {
+                    collect(value, files);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut files = Vec::new();
+    for (_, value) in inputs.iter() {
+        collect(value, &mut files);
+    }
+    files
+}
+
+/// Trusted Typst markup passed as an input.
+///
+/// Strings in inputs are always plain text; see the crate documentation on input safety.
+/// Wrapping a string in `Markup` marks it as markup instead. It reaches the template as
+/// an opaque value that renders only through the `markup` helper typst-bake provides:
+///
+/// ```typ
+/// #import "/.typst-bake/markup.typ": markup
+/// #markup(sys.inputs.note)
+/// ```
+///
+/// The value carries a tag derived from its text with a key that is secret to the
+/// process, and the helper only evaluates text whose tag checks out. A lookalike
+/// dictionary from JSON, serde, or map inputs is refused, so user text can't be evaluated
+/// through the helper. For the same reason a `Markup` value only works in the process
+/// that created it. Evaluated markup runs with the template's own capabilities, including
+/// `read()` access to embedded files, so only wrap strings authored by people you trust.
+///
+/// # Example
+/// ```rust,ignore
+/// use typst_bake::{IntoDict, Markup};
+///
+/// #[derive(IntoDict)]
+/// struct Notice {
+///     /// Shown literally, even if it contains `#read(..)`.
+///     customer_comment: String,
+///     /// Written by staff, may use emphasis and links.
+///     footer: Markup,
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Markup(pub String);
+
+impl Markup {
+    /// Wrap `source` as markup.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self(source.into())
+    }
+}
+
+impl IntoValue for Markup {
+    fn into_value(self) -> Value {
+        let mut dict = Dict::new();
+        dict.insert(Str::from(TAG_KEY), tag(&self.0).into_value());
+        dict.insert(Str::from(SOURCE_KEY), Str::from(self.0).into_value());
+        dict.into_value()
+    }
+}
@@ -0,0 +1,44 @@
+//! Concatenating documents into one PDF.
+
+use crate::compiled::observed;
+use crate::document::Document;
+use crate::error::{Error, Result};
+
+/// Merge several documents into one PDF, pages in the order given.
+///
+/// Each document is compiled (or its cached result reused) with its own template,
+/// inputs, and settings, so a cover letter and a report from different templates can
+/// ship as one file. The PDF settings and output size limit of the first document apply
+/// to the merged file, and page numbers run continuously.
+///
+/// Like imposition, merging lays the pages out as a new document: the result is not
+/// tagged, and has no outline, so the parts' headings don't become bookmarks. Links to
+/// places inside a part are removed; external links are kept. Standards that require
+/// tagging are rejected, and so are parts with PDF attachments, which would otherwise be
+/// lost. Attach files to the merged PDF in a separate step instead.
+///
+/// # Errors
+/// Returns [`Error::InvalidPageSelection`] if `documents` is empty, any compilation
+/// error of the parts, and [`Error::InvalidPdfConfig`] if the first document's standard
+/// requires tagging or a part has PDF attachments.
+///
+/// # Example
+/// ```rust,ignore
+/// let letter = typst_bake::document!("letter.typ").with_inputs(customer.clone());
+/// let report = typst_bake::document!("report.typ").with_inputs(report_data);
+/// let pdf = typst_bake::merge(&[&letter, &report])?;
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "pdf")))]
+pub fn merge(documents: &[&Document]) -> Result<Vec<u8>> {
+    let Some((first, rest)) = documents.split_first() else {
+        return Err(Error::InvalidPageSelection("no documents to merge".into()));
+    };
+    observed("pdf", Vec::len, || {
+        let first = first.compile()?;
+        let rest = rest
+            .iter()
+            .map(|doc| doc.compile())
+            .collect::<Result<Vec<_>>>()?;
+        first.render_merged_pdf_inner(&rest)
+    })
+}
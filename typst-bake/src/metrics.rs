@@ -0,0 +1,253 @@
+//! Prometheus metrics for rendering.
+//!
+//! Counters are process-wide and cover every [`Document`](crate::Document). Serve the
+//! output of [`encode`] from your service's metrics endpoint:
+//!
+//! ```rust,ignore
+//! async fn metrics() -> String {
+//!     typst_bake::metrics::encode()
+//! }
+//! ```
+
+use crate::error::Error;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Upper bounds of the render latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+struct Registry {
+    /// Render calls per format, successful or not.
+    renders: BTreeMap<&'static str, u64>,
+    /// Failed render calls per (format, error kind).
+    failures: BTreeMap<(&'static str, &'static str), u64>,
+    /// Bytes produced by successful renders per format.
+    bytes: BTreeMap<&'static str, u64>,
+    /// Render latency per format, including compilation when it was not cached.
+    latency: BTreeMap<&'static str, Histogram>,
+    cache_hits: u64,
+    cache_misses: u64,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Non-cumulative counts per bucket in `LATENCY_BUCKETS`.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    renders: BTreeMap::new(),
+    failures: BTreeMap::new(),
+    bytes: BTreeMap::new(),
+    latency: BTreeMap::new(),
+    cache_hits: 0,
+    cache_misses: 0,
+});
+
+fn registry() -> MutexGuard<'static, Registry> {
+    // Counters stay meaningful even if a panic interrupted an update.
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Record one render call: its format, duration, and output size or error.
+pub(crate) fn record_render(
+    format: &'static str,
+    elapsed: Duration,
+    result: Result<usize, &Error>,
+) {
+    let mut registry = registry();
+    *registry.renders.entry(format).or_default() += 1;
+    match result {
+        Ok(bytes) => *registry.bytes.entry(format).or_default() += bytes as u64,
+        Err(err) => {
+            *registry
+                .failures
+                .entry((format, error_kind(err)))
+                .or_default() += 1
+        }
+    }
+
+    let seconds = elapsed.as_secs_f64();
+    let histogram = registry.latency.entry(format).or_default();
+    if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+        histogram.buckets[bucket] += 1;
+    }
+    histogram.count += 1;
+    histogram.sum += seconds;
+}
+
+/// Record whether a compile request found the compiled document in the cache.
+pub(crate) fn record_compile_cache(hit: bool) {
+    let mut registry = registry();
+    if hit {
+        registry.cache_hits += 1;
+    } else {
+        registry.cache_misses += 1;
+    }
+}
+
+/// Label value for an error variant.
+fn error_kind(err: &Error) -> &'static str {
+    match err {
+        Error::EntryNotFound(_) => "entry_not_found",
+        Error::InvalidUtf8 => "invalid_utf8",
+        Error::Compilation(_) => "compilation",
+        Error::PdfGeneration(_) => "pdf_generation",
+        Error::PngEncoding(_) => "png_encoding",
+        Error::JpegEncoding(_) => "jpeg_encoding",
+        Error::InvalidInputs(_) => "invalid_inputs",
+        Error::InvalidFilePath(_) => "invalid_file_path",
+        Error::FileNotFound(_) => "file_not_found",
+        Error::TemplateSetNotFound(_) => "template_set_not_found",
+        Error::InvalidPageSelection(_) => "invalid_page_selection",
+        Error::InvalidQuery(_) => "invalid_query",
+        Error::InvalidPdfConfig(_) => "invalid_pdf_config",
+        Error::TooManyPages { .. } => "too_many_pages",
+        Error::OutputTooLarge { .. } => "output_too_large",
+        Error::DecompressionLimit { .. } => "decompression_limit",
+        Error::Decompression(_) => "decompression",
+    }
+}
+
+/// Render all metrics in the Prometheus text exposition format.
+///
+/// | Metric | Type | Labels |
+/// |--------|------|--------|
+/// | `typst_bake_renders_total` | counter | `format` |
+/// | `typst_bake_render_failures_total` | counter | `format`, `kind` |
+/// | `typst_bake_output_bytes_total` | counter | `format` |
+/// | `typst_bake_render_duration_seconds` | histogram | `format` |
+/// | `typst_bake_compile_cache_hits_total` | counter | |
+/// | `typst_bake_compile_cache_misses_total` | counter | |
+///
+/// `format` is `pdf`, `svg`, `png`, `jpeg`, or `rgba` (for `to_pixmaps`); `kind` is the
+/// snake_case name of the [`Error`] variant, e.g. `compilation` or `too_many_pages`.
+pub fn encode() -> String {
+    let registry = registry();
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "renders_total",
+        "counter",
+        "Render calls, successful or not.",
+    );
+    for (format, count) in &registry.renders {
+        let _ = writeln!(
+            out,
+            "typst_bake_renders_total{{format=\"{format}\"}} {count}"
+        );
+    }
+
+    header(
+        &mut out,
+        "render_failures_total",
+        "counter",
+        "Failed render calls.",
+    );
+    for ((format, kind), count) in &registry.failures {
+        let _ = writeln!(
+            out,
+            "typst_bake_render_failures_total{{format=\"{format}\",kind=\"{kind}\"}} {count}"
+        );
+    }
+
+    header(
+        &mut out,
+        "output_bytes_total",
+        "counter",
+        "Bytes produced by successful renders.",
+    );
+    for (format, bytes) in &registry.bytes {
+        let _ = writeln!(
+            out,
+            "typst_bake_output_bytes_total{{format=\"{format}\"}} {bytes}"
+        );
+    }
+
+    header(
+        &mut out,
+        "render_duration_seconds",
+        "histogram",
+        "Render latency, including compilation on a cache miss.",
+    );
+    for (format, histogram) in &registry.latency {
+        let mut cumulative = 0;
+        for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "typst_bake_render_duration_seconds_bucket{{format=\"{format}\",le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "typst_bake_render_duration_seconds_bucket{{format=\"{format}\",le=\"+Inf\"}} {}",
+            histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "typst_bake_render_duration_seconds_sum{{format=\"{format}\"}} {}",
+            histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "typst_bake_render_duration_seconds_count{{format=\"{format}\"}} {}",
+            histogram.count
+        );
+    }
+
+    header(
+        &mut out,
+        "compile_cache_hits_total",
+        "counter",
+        "Compile requests served from the cached document.",
+    );
+    let _ = writeln!(
+        out,
+        "typst_bake_compile_cache_hits_total {}",
+        registry.cache_hits
+    );
+    header(
+        &mut out,
+        "compile_cache_misses_total",
+        "counter",
+        "Compile requests that compiled the document.",
+    );
+    let _ = writeln!(
+        out,
+        "typst_bake_compile_cache_misses_total {}",
+        registry.cache_misses
+    );
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP typst_bake_{name} {help}");
+    let _ = writeln!(out, "# TYPE typst_bake_{name} {kind}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_reports_renders_and_failures() {
+        record_render("pdf", Duration::from_millis(30), Ok(1000));
+        record_render("pdf", Duration::from_secs(20), Err(&Error::InvalidUtf8));
+
+        let text = encode();
+        assert!(text.contains("# TYPE typst_bake_render_duration_seconds histogram"));
+        assert!(text.contains("kind=\"invalid_utf8\""));
+        assert!(
+            text.contains("typst_bake_render_duration_seconds_bucket{format=\"pdf\",le=\"+Inf\"}")
+        );
+    }
+}
@@ -2,13 +2,14 @@
 //!
 //! [`PdfConfig`] is passed to [`Document::with_pdf_config`](crate::Document::with_pdf_config)
 //! to control PDF-only export settings (tagging, conformance standard, document
-//! identifier, creation timestamp). These options affect the PDF export stage only;
+//! identifier, creation timestamp, imposition). These options affect the PDF export stage only;
 //! SVG/PNG output ignores them.
 //!
 //! All typst-pdf coupling is isolated to the private conversion functions in this
 //! module, so a typst version bump only needs to be checked here.
 
 use crate::error::{Error, Result};
+use crate::imposition::Imposition;
 
 /// A PDF conformance standard to enforce on export.
 ///
@@ -285,6 +286,25 @@ fn civil_from_unix(secs: i64) -> (i32, u8, u8, u8, u8, u8) {
 /// [`PdfConfig::default()`] maps exactly to typst's default PDF options (tagged PDF,
 /// PDF 1.7, auto identifier, no explicit timestamp), so leaving it untouched does not
 /// change output.
+///
+/// # Accessible PDFs
+///
+/// PDFs are tagged by default: they carry a structure tree that screen readers follow,
+/// built from the semantics of the template (headings, lists, tables, figures). For
+/// PDF/UA-1 conformance, as required by many public-sector customers:
+///
+/// 1. Export with [`PdfStandard::Ua1`] and keep `tagged: true`.
+/// 2. Give the document a title and a language, with
+///    [`Document::with_title`](crate::Document::with_title) and
+///    [`Document::with_lang`](crate::Document::with_lang) or with `set document(title: ..)`
+///    and `set text(lang: ..)` in the template.
+/// 3. Give every image and figure alternative text (`image(.., alt: "..")`) and mark
+///    decorative content as an artifact with `pdf.artifact`.
+///
+/// Typst checks these rules while exporting, and [`to_pdf`](crate::Document::to_pdf)
+/// fails with [`Error::PdfGeneration`] naming the element that violates them, so a
+/// successful export can be trusted. Page selection and imposition drop the structure
+/// tree and are rejected under `Ua1`.
 #[derive(Clone, Debug)]
 pub struct PdfConfig {
     /// PDF conformance standard. Defaults to [`PdfStandard::V1_7`].
@@ -303,6 +323,13 @@ pub struct PdfConfig {
     /// is `auto`. Required for any PDF/A standard (which mandates a document date) unless
     /// the template sets the date itself.
     pub timestamp: Option<PdfTimestamp>,
+    /// Arrange pages on print sheets (n-up or booklet) instead of one page per PDF page.
+    ///
+    /// Imposed output is never tagged, so standards that require tagging are rejected.
+    /// It has no outline and no links to places inside the document, and a document
+    /// with PDF attachments fails to export rather than losing them. Combined with page
+    /// selection, only the selected pages are imposed.
+    pub imposition: Option<Imposition>,
 }
 
 impl Default for PdfConfig {
@@ -314,6 +341,7 @@ impl Default for PdfConfig {
             tagged: true,
             ident: None,
             timestamp: None,
+            imposition: None,
         }
     }
 }
@@ -0,0 +1,144 @@
+//! Raster page images.
+
+#[cfg(feature = "jpeg")]
+use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use typst::foundations::Smart;
+use typst::layout::Page;
+use typst::visualize::{Color, Paint};
+
+/// PNG export options, taken by [`Document::to_png_with`](crate::Document::to_png_with),
+/// [`Document::render_pages`](crate::Document::render_pages), and
+/// [`Document::to_png_with_progress`](crate::Document::to_png_with_progress).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PngOptions {
+    /// Resolution in dots per inch (e.g., 72 for 1:1, 144 for Retina, 300 for print).
+    pub dpi: f32,
+    /// Resolution of individual pages, by zero-based page index in the whole document.
+    /// Pages not listed use `dpi`.
+    pub page_dpi: BTreeMap<usize, f32>,
+    /// What to paint behind pages that don't set their own fill.
+    pub background: Background,
+}
+
+impl PngOptions {
+    /// The resolution page `index` is rendered at, in pixels per point.
+    pub(crate) fn pixel_per_pt(&self, index: usize) -> f32 {
+        self.page_dpi.get(&index).copied().unwrap_or(self.dpi) / 72.0
+    }
+}
+
+impl Default for PngOptions {
+    /// 72 dpi for every page on the default white background, like `to_png(72.0)`.
+    fn default() -> Self {
+        Self {
+            dpi: 72.0,
+            page_dpi: BTreeMap::new(),
+            background: Background::Default,
+        }
+    }
+}
+
+/// The background of rendered pages.
+///
+/// Applies to pages whose fill is left at its default; a template's explicit
+/// `set page(fill: ..)` always wins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    /// White, as in the PDF.
+    #[default]
+    Default,
+    /// No background, for compositing page images onto something else.
+    Transparent,
+    /// A solid color given as red, green, blue, and alpha.
+    Rgba(u8, u8, u8, u8),
+}
+
+/// `page` with `background` applied to its fill.
+pub(crate) fn with_background(page: &Page, background: Background) -> Cow<'_, Page> {
+    let fill = match background {
+        Background::Default => return Cow::Borrowed(page),
+        Background::Transparent => None,
+        Background::Rgba(r, g, b, a) => Some(Paint::Solid(Color::from_u8(r, g, b, a))),
+    };
+    if page.fill.is_custom() {
+        return Cow::Borrowed(page);
+    }
+    let mut page = page.clone();
+    page.fill = Smart::Custom(fill);
+    Cow::Owned(page)
+}
+
+/// A rendered page as raw, non-premultiplied RGBA pixels, returned by
+/// [`Document::to_pixmaps`](crate::Document::to_pixmaps).
+///
+/// `data` holds `width * height` pixels of four bytes each, row by row from the top-left
+/// corner, ready to upload as a GPU texture or to hand to an image library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// RGBA bytes, 8 bits per channel.
+    pub data: Vec<u8>,
+}
+
+/// Encode `page` as a JPEG of the given quality (1-100), flattened onto white since JPEG
+/// has no transparency.
+#[cfg(feature = "jpeg")]
+pub(crate) fn render_jpeg(
+    page: &typst::layout::Page,
+    pixel_per_pt: f32,
+    quality: u8,
+) -> Result<Vec<u8>> {
+    let pixmap = typst_render::render(page, pixel_per_pt);
+    let (Ok(width), Ok(height)) = (
+        u16::try_from(pixmap.width()),
+        u16::try_from(pixmap.height()),
+    ) else {
+        return Err(Error::JpegEncoding(format!(
+            "{}x{} pixels exceeds the JPEG limit of 65535",
+            pixmap.width(),
+            pixmap.height()
+        )));
+    };
+    // Compositing premultiplied color over white is `c + (255 - a)`.
+    let rgb: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let rest = 255 - pixel.alpha();
+            [
+                pixel.red() + rest,
+                pixel.green() + rest,
+                pixel.blue() + rest,
+            ]
+        })
+        .collect();
+
+    let mut jpeg = Vec::new();
+    jpeg_encoder::Encoder::new(&mut jpeg, quality.clamp(1, 100))
+        .encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| Error::JpegEncoding(e.to_string()))?;
+    Ok(jpeg)
+}
+
+/// Render `page` and convert it out of tiny-skia's premultiplied format.
+pub(crate) fn render(page: &typst::layout::Page, pixel_per_pt: f32) -> RgbaImage {
+    let pixmap = typst_render::render(page, pixel_per_pt);
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage {
+        width: pixmap.width(),
+        height: pixmap.height(),
+        data,
+    }
+}
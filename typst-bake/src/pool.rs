@@ -0,0 +1,122 @@
+//! A bounded set of render sessions for concurrent servers.
+
+use crate::document::Document;
+use crate::error::Result;
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex};
+use typst::foundations::Dict;
+
+/// Hands out up to `size` concurrent render sessions for one embedded bundle.
+///
+/// Each session is a fresh copy of the configured document: same builder settings, its
+/// own inputs and compiled result, so sessions never wait on each other's compile lock.
+/// Embedded fonts are decompressed once when the pool is created and shared by every
+/// session. Requests beyond `size` wait in [`session`](Self::session) until one is
+/// returned, bounding the memory and CPU a burst of traffic can take.
+///
+/// # Example
+/// ```rust,ignore
+/// use typst_bake::DocumentPool;
+///
+/// static POOL: LazyLock<DocumentPool> = LazyLock::new(|| {
+///     DocumentPool::new(typst_bake::document!("invoice.typ"), 8).expect("fonts load")
+/// });
+///
+/// fn handle(invoice: Invoice) -> typst_bake::Result<Vec<u8>> {
+///     POOL.session().with_inputs(invoice).to_pdf()
+/// }
+/// ```
+pub struct DocumentPool {
+    template: Document,
+    size: usize,
+    /// Number of sessions currently handed out.
+    active: Mutex<usize>,
+    released: Condvar,
+}
+
+impl DocumentPool {
+    /// Create a pool of `size` sessions (at least one) rendering `document`.
+    ///
+    /// # Errors
+    /// Returns an error if the embedded fonts cannot be decompressed.
+    pub fn new(document: Document, size: usize) -> Result<Self> {
+        document.warm_fonts()?;
+        Ok(Self {
+            template: document,
+            size: size.max(1),
+            active: Mutex::new(0),
+            released: Condvar::new(),
+        })
+    }
+
+    /// Maximum number of concurrent sessions.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Wait for a free slot and return a session.
+    pub fn session(&self) -> PoolSession<'_> {
+        let mut active = self.active.lock().expect("lock poisoned");
+        while *active >= self.size {
+            active = self.released.wait(active).expect("lock poisoned");
+        }
+        *active += 1;
+        drop(active);
+        self.new_session()
+    }
+
+    /// Return a session if a slot is free, without waiting.
+    pub fn try_session(&self) -> Option<PoolSession<'_>> {
+        let mut active = self.active.lock().expect("lock poisoned");
+        if *active >= self.size {
+            return None;
+        }
+        *active += 1;
+        drop(active);
+        Some(self.new_session())
+    }
+
+    fn new_session(&self) -> PoolSession<'_> {
+        let document = self
+            .template
+            .fork()
+            .with_memo_retention(self.template.batch_retention());
+        PoolSession {
+            pool: self,
+            document: Some(document),
+        }
+    }
+}
+
+/// A document checked out of a [`DocumentPool`]. Dereferences to [`Document`] for
+/// rendering; the slot is returned when the session is dropped.
+pub struct PoolSession<'a> {
+    pool: &'a DocumentPool,
+    /// Always `Some` until dropped; an `Option` so `with_inputs` can move it.
+    document: Option<Document>,
+}
+
+impl PoolSession<'_> {
+    /// Set the inputs for this session, like [`Document::with_inputs`].
+    pub fn with_inputs<T: Into<Dict>>(mut self, inputs: T) -> Self {
+        self.document = self.document.take().map(|doc| doc.with_inputs(inputs));
+        self
+    }
+}
+
+impl Deref for PoolSession<'_> {
+    type Target = Document;
+
+    fn deref(&self) -> &Document {
+        self.document.as_ref().expect("session document is present")
+    }
+}
+
+impl Drop for PoolSession<'_> {
+    fn drop(&mut self) {
+        // Drop the document first so its memory is freed before another session starts.
+        self.document = None;
+        *self.pool.active.lock().expect("lock poisoned") -= 1;
+        self.pool.released.notify_one();
+    }
+}
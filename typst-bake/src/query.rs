@@ -0,0 +1,60 @@
+//! Reading values back out of the compiled document.
+
+use crate::document::Document;
+use crate::error::{Error, Result};
+use typst::foundations::{FromValue, Label, Selector, Value};
+use typst::introspection::MetadataElem;
+use typst::utils::PicoStr;
+
+impl Document {
+    /// Return the elements carrying `label`, like `typst query`.
+    ///
+    /// Compiles the document if not already compiled. `label` may be written with or
+    /// without angle brackets. For `metadata` elements the result is the stored value,
+    /// which makes `#metadata(..) <label>` the way to pass computed data, such as totals,
+    /// back to Rust; any other element is returned as content. Each value is converted to
+    /// `T` with Typst's `FromValue`, so numbers, strings, arrays, and dictionaries can be
+    /// read directly, and `Value` keeps them as they are. Structs deriving
+    /// [`FromValue`](crate::FromValue) read dictionaries into typed fields.
+    ///
+    /// Results are in document order. A value that doesn't convert to `T` fails with
+    /// [`Error::InvalidQuery`].
+    ///
+    /// # Example
+    ///
+    /// ```typ
+    /// #metadata(items.map(i => i.price).sum()) <total>
+    /// ```
+    ///
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("invoice.typ").with_inputs(invoice);
+    /// let total: f64 = doc.query::<f64>("total")?[0];
+    /// let pdf = doc.to_pdf()?; // reuses the compiled document
+    /// ```
+    pub fn query<T: FromValue>(&self, label: &str) -> Result<Vec<T>> {
+        let name = label
+            .strip_prefix('<')
+            .and_then(|l| l.strip_suffix('>'))
+            .unwrap_or(label);
+        let label = Label::new(PicoStr::intern(name))
+            .ok_or_else(|| Error::InvalidQuery(format!("invalid label: {label:?}")))?;
+
+        self.with_compiled(|compiled| {
+            compiled
+                .introspector
+                .query(&Selector::Label(label))
+                .into_iter()
+                .map(|elem| {
+                    let value = match elem.to_packed::<MetadataElem>() {
+                        Some(metadata) => metadata.value.clone(),
+                        None => Value::Content(elem),
+                    };
+                    let ty = value.ty();
+                    T::from_value(value).map_err(|e| {
+                        Error::InvalidQuery(format!("<{name}> holds a {ty}: {}", e.message()))
+                    })
+                })
+                .collect()
+        })
+    }
+}
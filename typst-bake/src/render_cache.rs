@@ -0,0 +1,111 @@
+//! Export output cached across documents. Enabled by [`Document::with_cache`].
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// `(document fingerprint, export format and options)`.
+type Key = (String, String);
+
+/// A least-recently-used cache of export output, shared by every document built from the
+/// same embedded content.
+pub(crate) struct RenderCache {
+    inner: Mutex<Entries>,
+}
+
+struct Entries {
+    capacity: usize,
+    /// Value and the tick of its last use.
+    map: HashMap<Key, (u64, Box<dyn Any + Send + Sync>)>,
+    tick: u64,
+}
+
+impl RenderCache {
+    /// The cache for documents with `content_hash`, created on first use. `capacity` is the
+    /// maximum number of outputs kept; a later call with a different value resizes it.
+    pub fn shared(content_hash: &'static str, capacity: usize) -> Arc<Self> {
+        static CACHES: OnceLock<Mutex<HashMap<&'static str, Arc<RenderCache>>>> = OnceLock::new();
+        let mut caches = CACHES
+            .get_or_init(Mutex::default)
+            .lock()
+            .expect("lock poisoned");
+        let cache = caches
+            .entry(content_hash)
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    inner: Mutex::new(Entries {
+                        capacity,
+                        map: HashMap::new(),
+                        tick: 0,
+                    }),
+                })
+            })
+            .clone();
+        cache.lock().resize(capacity);
+        cache
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.inner.lock().expect("lock poisoned")
+    }
+
+    /// A copy of the output stored under `key`, if any.
+    pub fn get<T: Clone + 'static>(&self, key: &Key) -> Option<T> {
+        let mut entries = self.lock();
+        entries.tick += 1;
+        let tick = entries.tick;
+        let (used, value) = entries.map.get_mut(key)?;
+        *used = tick;
+        value.downcast_ref::<T>().cloned()
+    }
+
+    /// Store `value` under `key`, evicting the least recently used output if full.
+    pub fn insert<T: Send + Sync + 'static>(&self, key: Key, value: T) {
+        let mut entries = self.lock();
+        if entries.capacity == 0 {
+            return;
+        }
+        entries.tick += 1;
+        let tick = entries.tick;
+        entries.map.insert(key, (tick, Box::new(value)));
+        let capacity = entries.capacity;
+        entries.resize(capacity);
+    }
+}
+
+impl Entries {
+    fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.map.len() > capacity {
+            let oldest = self
+                .map
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone())
+                .expect("map is not empty");
+            self.map.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> Key {
+        (name.to_string(), "pdf".to_string())
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let cache = RenderCache::shared("render-cache-test", 2);
+        cache.insert(key("a"), vec![1u8]);
+        cache.insert(key("b"), vec![2u8]);
+        assert_eq!(cache.get::<Vec<u8>>(&key("a")), Some(vec![1]));
+
+        cache.insert(key("c"), vec![3u8]);
+        assert_eq!(cache.get::<Vec<u8>>(&key("b")), None);
+        assert_eq!(cache.get::<Vec<u8>>(&key("a")), Some(vec![1]));
+        assert_eq!(cache.get::<Vec<u8>>(&key("c")), Some(vec![3]));
+    }
+}
@@ -2,10 +2,12 @@
 //!
 //! Uses lazy decompression - files are decompressed only when accessed.
 
-use crate::util::decompress;
+use crate::limits::DecompressionBudget;
+use crate::util::trace_span;
 use include_dir::Dir;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
 use typst::diag::{FileError, FileResult};
 use typst::foundations::Bytes;
 use typst::syntax::{FileId, Source};
@@ -19,11 +21,19 @@ pub use typst_as_lib::file_resolver::FileResolver;
 pub struct EmbeddedResolver {
     files: HashMap<String, &'static [u8]>,
     runtime_files: HashMap<String, Vec<u8>>,
+    budget: Arc<DecompressionBudget>,
 }
 
 impl EmbeddedResolver {
     /// Create a new resolver from embedded directories.
-    pub fn new(templates: &'static Dir<'static>, packages: &'static Dir<'static>) -> Self {
+    ///
+    /// Every embedded file is decompressed through `budget`, which enforces the
+    /// document's decompression limits.
+    pub fn new(
+        templates: &'static Dir<'static>,
+        packages: &'static Dir<'static>,
+        budget: Arc<DecompressionBudget>,
+    ) -> Self {
         let mut files = HashMap::new();
 
         collect_files(templates, "", &mut files);
@@ -32,6 +42,7 @@ impl EmbeddedResolver {
         Self {
             files,
             runtime_files: HashMap::new(),
+            budget,
         }
     }
 
@@ -49,6 +60,7 @@ impl EmbeddedResolver {
     /// Runtime files take priority over embedded files.
     fn decompress_file(&self, id: FileId) -> FileResult<Vec<u8>> {
         let path = self.get_path(id);
+        trace_span!(DEBUG, "resolve", path = path.as_str());
 
         // Runtime files are stored uncompressed — return directly.
         if let Some(data) = self.runtime_files.get(&path) {
@@ -60,7 +72,7 @@ impl EmbeddedResolver {
             .get(&path)
             .copied()
             .ok_or_else(|| not_found(id))?;
-        decompress(compressed).map_err(|e| {
+        self.budget.decompress(&path, compressed).map_err(|e| {
             FileError::Other(Some(format!("Decompression failed for {path}: {e}").into()))
         })
     }
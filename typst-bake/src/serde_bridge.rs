@@ -0,0 +1,84 @@
+//! Inputs from any `serde::Serialize` type.
+
+use crate::document::Document;
+use crate::error::{Error, Result};
+use crate::json::json_to_value;
+use serde::Serialize;
+use typst::foundations::Value;
+
+/// Convert a `Serialize` value into a Typst value.
+///
+/// The value goes through the JSON data model, with the mapping of
+/// [`Document::with_json_inputs`]: structs and maps become dictionaries, sequences
+/// become arrays, and `None` / unit become `none`. Map keys must serialize as strings or
+/// numbers, and byte buffers become arrays of integers.
+///
+/// # Errors
+/// Returns [`Error::InvalidInputs`] if the value can't be serialized, e.g. because of a
+/// map with non-string keys.
+///
+/// # Example
+/// ```rust,ignore
+/// #[derive(serde::Serialize)]
+/// struct Customer { name: String, orders: Vec<Order> }
+///
+/// let value = typst_bake::to_value(&customer)?;
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    serde_json::to_value(value)
+        .map(json_to_value)
+        .map_err(|e| Error::InvalidInputs(e.to_string()))
+}
+
+impl Document {
+    /// Add input data from any `Serialize` type, without deriving
+    /// [`IntoValue`](crate::IntoValue) / [`IntoDict`](crate::IntoDict).
+    ///
+    /// Fields are converted as described in [`to_value`].
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidInputs`] if serialization fails or the value does not
+    /// serialize to a map, such as a struct.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// #[derive(serde::Serialize)]
+    /// struct Inputs { title: String, items: Vec<Item> }
+    ///
+    /// let pdf = typst_bake::document!("main.typ")
+    ///     .with_inputs_serde(&inputs)?
+    ///     .to_pdf()?;
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn with_inputs_serde<T: Serialize + ?Sized>(self, inputs: &T) -> Result<Self> {
+        match to_value(inputs)? {
+            Value::Dict(dict) => Ok(self.with_inputs(dict)),
+            other => Err(Error::InvalidInputs(format!(
+                "expected a struct or map, got {}",
+                other.ty()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use typst::foundations::IntoValue;
+
+    #[test]
+    fn serializable_values_convert() {
+        let item = BTreeMap::from([("price", Some(1.5f64)), ("note", None)]);
+        let value = to_value(&item).unwrap();
+        let Value::Dict(dict) = value else {
+            panic!("expected a dictionary");
+        };
+        assert_eq!(dict.get("price").unwrap(), &1.5f64.into_value());
+        assert_eq!(dict.get("note").unwrap(), &Value::None);
+
+        let keys: BTreeMap<(u8, u8), u8> = BTreeMap::from([((1, 2), 3)]);
+        assert!(matches!(to_value(&keys), Err(Error::InvalidInputs(_))));
+    }
+}
@@ -110,6 +110,152 @@ impl EmbedStats {
     pub fn display(&self) {
         print!("{self}");
     }
+
+    /// Render the statistics in the given format.
+    ///
+    /// [`StatsFormat::Pretty`] is the same table as the `Display` implementation; the
+    /// other formats are meant for log pipelines and tooling.
+    pub fn format(&self, format: StatsFormat) -> String {
+        match format {
+            StatsFormat::Pretty => self.to_string(),
+            StatsFormat::Json => self.to_json(),
+            StatsFormat::Compact => self.to_compact(),
+            StatsFormat::Tsv => self.to_tsv(),
+        }
+    }
+
+    /// Serialize the statistics as a single-line JSON object. Sizes are in bytes.
+    ///
+    /// ```text
+    /// {"templates":{"original_size":1000,"compressed_size":200,"file_count":1},...}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let category = |c: &CategoryStats| {
+            format!(
+                r#"{{"original_size":{},"compressed_size":{},"file_count":{}}}"#,
+                c.original_size, c.compressed_size, c.file_count
+            )
+        };
+        let packages = self
+            .packages
+            .packages
+            .iter()
+            .map(|p| {
+                format!(
+                    r#"{{"name":{},"original_size":{},"compressed_size":{},"file_count":{}}}"#,
+                    json_string(&p.name),
+                    p.original_size,
+                    p.compressed_size,
+                    p.file_count
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            concat!(
+                r#"{{"templates":{},"fonts":{},"#,
+                r#""packages":{{"original_size":{},"compressed_size":{},"packages":[{}]}},"#,
+                r#""dedup":{{"total_files":{},"unique_blobs":{},"duplicate_count":{},"saved_bytes":{}}},"#,
+                r#""compression_level":{},"total_original":{},"total_compressed":{},"#,
+                r#""total_deduplicated":{},"file_count":{}}}"#
+            ),
+            category(&self.templates),
+            category(&self.fonts),
+            self.packages.original_size,
+            self.packages.compressed_size,
+            packages,
+            self.dedup.total_files,
+            self.dedup.unique_blobs,
+            self.dedup.duplicate_count,
+            self.dedup.saved_bytes,
+            self.compression_level,
+            self.total_original(),
+            self.total_compressed(),
+            self.total_deduplicated(),
+            self.total_file_count(),
+        )
+    }
+
+    /// One-line `key=value` summary of the totals. Sizes are in bytes.
+    ///
+    /// ```text
+    /// original=4000 compressed=1000 deduplicated=900 files=4 packages=0 level=19
+    /// ```
+    pub fn to_compact(&self) -> String {
+        format!(
+            "original={} compressed={} deduplicated={} files={} packages={} level={}",
+            self.total_original(),
+            self.total_compressed(),
+            self.total_deduplicated(),
+            self.total_file_count(),
+            self.packages.packages.len(),
+            self.compression_level,
+        )
+    }
+
+    /// Tab-separated table with a header row and one row per category and package.
+    /// Sizes are in bytes.
+    ///
+    /// ```text
+    /// kind	name	original_size	compressed_size	file_count
+    /// templates	-	1000	200	1
+    /// fonts	-	2000	600	2
+    /// package	@preview/cetz:0.3.4	1000	200	40
+    /// total	-	4000	1000	43
+    /// ```
+    pub fn to_tsv(&self) -> String {
+        let mut out = String::from("kind\tname\toriginal_size\tcompressed_size\tfile_count\n");
+        let mut row = |kind: &str, name: &str, original: usize, compressed: usize, files: usize| {
+            out.push_str(&format!(
+                "{kind}\t{name}\t{original}\t{compressed}\t{files}\n"
+            ));
+        };
+        row(
+            "templates",
+            "-",
+            self.templates.original_size,
+            self.templates.compressed_size,
+            self.templates.file_count,
+        );
+        row(
+            "fonts",
+            "-",
+            self.fonts.original_size,
+            self.fonts.compressed_size,
+            self.fonts.file_count,
+        );
+        for pkg in &self.packages.packages {
+            row(
+                "package",
+                &pkg.name,
+                pkg.original_size,
+                pkg.compressed_size,
+                pkg.file_count,
+            );
+        }
+        row(
+            "total",
+            "-",
+            self.total_original(),
+            self.total_compressed(),
+            self.total_file_count(),
+        );
+        out
+    }
+}
+
+/// Output formats for [`EmbedStats::format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsFormat {
+    /// Human-readable table, as printed by [`EmbedStats::display`].
+    #[default]
+    Pretty,
+    /// Single-line JSON object, see [`EmbedStats::to_json`].
+    Json,
+    /// One-line `key=value` summary, see [`EmbedStats::to_compact`].
+    Compact,
+    /// Tab-separated rows, see [`EmbedStats::to_tsv`].
+    Tsv,
 }
 
 impl std::fmt::Display for EmbedStats {
@@ -237,6 +383,25 @@ fn compression_ratio(original: usize, compressed: usize) -> f64 {
     1.0 - (compressed as f64 / original as f64)
 }
 
+/// Quote and escape a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
 /// Format bytes into a human-readable size string.
 fn format_size(bytes: usize) -> String {
     const KB: usize = 1024;
@@ -298,9 +463,8 @@ mod tests {
         assert!((stats.compression_ratio() - 0.75).abs() < 0.001);
     }
 
-    #[test]
-    fn test_embed_stats_totals() {
-        let stats = EmbedStats {
+    fn sample_stats() -> EmbedStats {
+        EmbedStats {
             templates: CategoryStats {
                 original_size: 1000,
                 compressed_size: 200, // 80% compression
@@ -323,7 +487,12 @@ mod tests {
                 saved_bytes: 100,
             },
             compression_level: 19,
-        };
+        }
+    }
+
+    #[test]
+    fn test_embed_stats_totals() {
+        let stats = sample_stats();
         // Total: 4000 -> 1000 (75% compression)
         assert_eq!(stats.total_original(), 4000);
         assert_eq!(stats.total_compressed(), 1000);
@@ -331,4 +500,45 @@ mod tests {
         // Deduplicated: 1000 - 100 = 900
         assert_eq!(stats.total_deduplicated(), 900);
     }
+
+    #[test]
+    fn test_compact_format() {
+        assert_eq!(
+            sample_stats().format(StatsFormat::Compact),
+            "original=4000 compressed=1000 deduplicated=900 files=3 packages=0 level=19"
+        );
+    }
+
+    #[test]
+    fn test_tsv_format() {
+        let mut stats = sample_stats();
+        stats.packages.packages.push(PackageInfo {
+            name: "@preview/cetz:0.3.4".to_owned(),
+            original_size: 1000,
+            compressed_size: 200,
+            file_count: 40,
+        });
+        assert_eq!(
+            stats.to_tsv(),
+            "kind\tname\toriginal_size\tcompressed_size\tfile_count\n\
+             templates\t-\t1000\t200\t1\n\
+             fonts\t-\t2000\t600\t2\n\
+             package\t@preview/cetz:0.3.4\t1000\t200\t40\n\
+             total\t-\t4000\t1000\t43\n"
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let json = sample_stats().to_json();
+        assert!(json.starts_with(
+            r#"{"templates":{"original_size":1000,"compressed_size":200,"file_count":1},"#
+        ));
+        assert!(json.ends_with(r#""total_deduplicated":900,"file_count":3}"#));
+    }
+
+    #[test]
+    fn test_json_string_escaping() {
+        assert_eq!(json_string("a\"b\\c\n"), r#""a\"b\\c\n""#);
+    }
 }
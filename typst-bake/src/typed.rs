@@ -0,0 +1,50 @@
+//! Compile-time enforcement of required inputs.
+
+use crate::document::Document;
+use std::marker::PhantomData;
+use typst::foundations::Dict;
+
+/// A [`Document`] that cannot be rendered until its inputs are provided.
+///
+/// Created by [`Document::require_inputs`], or by `document!("main.typ", inputs = T)`.
+/// It has no rendering methods: [`with_inputs`](Self::with_inputs) is the only way back to
+/// a [`Document`], so forgetting the inputs is a compile error rather than a document
+/// full of `none`.
+///
+/// # Example
+/// ```rust,ignore
+/// let doc = typst_bake::document!("invoice.typ", inputs = Invoice);
+/// // doc.to_pdf()?; // error: no method named `to_pdf` found
+/// let pdf = doc.with_inputs(invoice).to_pdf()?;
+/// ```
+#[must_use = "call `with_inputs` to get a renderable `Document`"]
+pub struct RequiresInputs<T> {
+    document: Document,
+    inputs: PhantomData<fn(T)>,
+}
+
+impl<T: Into<Dict>> RequiresInputs<T> {
+    pub(crate) fn new(document: Document) -> Self {
+        Self {
+            document,
+            inputs: PhantomData,
+        }
+    }
+
+    /// Provide the inputs, yielding a [`Document`] ready to render.
+    pub fn with_inputs(self, inputs: T) -> Document {
+        self.document.with_inputs(inputs)
+    }
+
+    /// Apply other builder methods to the wrapped document while the inputs are
+    /// still pending.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let doc = typst_bake::document!("invoice.typ", inputs = Invoice)
+    ///     .configure(|doc| doc.with_lang("de").with_max_pages(20));
+    /// ```
+    pub fn configure(self, f: impl FnOnce(Document) -> Document) -> Self {
+        Self::new(f(self.document))
+    }
+}
@@ -1,6 +1,101 @@
-use std::io::Cursor;
+use std::io::{Cursor, Read};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Enter a `tracing` span at the given level until the end of the enclosing block.
+/// Expands to nothing without the `tracing` feature.
+macro_rules! trace_span {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($args)*).entered();
+    };
+}
+pub(crate) use trace_span;
+
+/// Quote `s` as a Typst string literal, for code generated into preludes.
+pub(crate) fn typst_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Quote `items` as a Typst array of strings, e.g. `("a", "b",)`. The trailing comma keeps a
+/// single item an array.
+pub(crate) fn typst_array(items: &[String]) -> String {
+    let items: String = items.iter().map(|s| typst_str(s) + ", ").collect();
+    format!("({})", items.trim_end_matches(' '))
+}
+
+/// `time` as a UTC date and time, truncated to whole seconds like datetimes created in
+/// Typst. `None` outside the years -9999..=9999, which Typst can't represent either.
+pub(crate) fn utc_datetime(time: SystemTime) -> Option<time::OffsetDateTime> {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        // Round towards the past, so 0.5 s before the epoch is 23:59:59.
+        Err(before) => {
+            let before = before.duration();
+            -(before.as_secs() as i64) - i64::from(before.subsec_nanos() > 0)
+        }
+    };
+    time::OffsetDateTime::from_unix_timestamp(seconds).ok()
+}
 
 /// Decompress zstd-compressed data.
 pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
     zstd::decode_all(Cursor::new(data))
 }
+
+/// Decompress zstd-compressed data, giving up once the output exceeds `max_len` bytes.
+///
+/// Returns `Ok(None)` when the limit is exceeded. Decoding stops there, so an oversized
+/// blob never allocates more than `max_len + 1` bytes. `usize::MAX` means no limit.
+pub(crate) fn decompress_limited(
+    data: &[u8],
+    max_len: usize,
+) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let decoder = zstd::stream::read::Decoder::new(data)?;
+    let mut out = Vec::new();
+    decoder
+        .take((max_len as u64).saturating_add(1))
+        .read_to_end(&mut out)?;
+    Ok((out.len() <= max_len).then_some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typst_str_escapes_quotes_and_controls() {
+        assert_eq!(typst_str("plain"), "\"plain\"");
+        assert_eq!(typst_str("a\"b\\c\nd\u{7}"), r#""a\"b\\c\nd\u{7}""#);
+    }
+
+    #[test]
+    fn typst_array_keeps_single_items_arrays() {
+        assert_eq!(typst_array(&["a".into()]), r#"("a",)"#);
+        assert_eq!(typst_array(&["a".into(), "b".into()]), r#"("a", "b",)"#);
+    }
+
+    #[test]
+    fn decompress_limited_stops_at_the_limit() {
+        let data = zstd::encode_all(&[7u8; 100][..], 0).unwrap();
+        assert_eq!(decompress_limited(&data, 100).unwrap(), Some(vec![7; 100]));
+        assert_eq!(decompress_limited(&data, 99).unwrap(), None);
+        assert_eq!(
+            decompress_limited(&data, usize::MAX).unwrap(),
+            Some(vec![7; 100])
+        );
+    }
+}
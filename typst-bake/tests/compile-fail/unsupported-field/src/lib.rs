@@ -0,0 +1,7 @@
+use std::time::Instant;
+
+#[derive(typst_bake::IntoValue)]
+pub struct Invoice {
+    pub number: u32,
+    pub created: Instant,
+}
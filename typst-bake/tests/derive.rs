@@ -0,0 +1,126 @@
+//! The derive macros expanded in a real crate.
+//!
+//! The macro crate's unit tests only inspect the generated tokens; these check that the
+//! expansions compile and produce the values templates see.
+
+use typst::foundations::{dict, IntoValue};
+use typst_bake::{FromDict, FromValue, IntoDict, IntoValue};
+
+#[derive(IntoValue, IntoDict, FromValue, FromDict, Debug, Clone, PartialEq)]
+struct Page<T> {
+    items: Vec<T>,
+}
+
+#[test]
+fn test_generic_struct_round_trip() {
+    let page = Page {
+        items: vec![1_i64, 2, 3],
+    };
+
+    let dict = page.clone().into_dict();
+    assert_eq!(dict, dict! { "items" => vec![1_i64, 2, 3] });
+    assert_eq!(Page::<i64>::from_dict(dict).unwrap(), page);
+
+    let value = page.clone().into_value();
+    assert_eq!(value, dict! { "items" => vec![1_i64, 2, 3] }.into_value());
+    assert_eq!(value.cast::<Page<i64>>().unwrap(), page);
+}
+
+#[test]
+fn test_generic_struct_nests() {
+    let pages = Page {
+        items: vec![Page {
+            items: vec!["a".to_owned()],
+        }],
+    };
+
+    let expected = dict! { "items" => vec![dict! { "items" => vec!["a".to_owned()] }] };
+    assert_eq!(typst::foundations::Dict::from(pages.clone()), expected);
+    assert_eq!(Page::from_dict(expected).unwrap(), pages);
+}
+
+#[derive(IntoValue)]
+enum Status {
+    Draft,
+    #[typst(rename = "sent")]
+    Sent(String),
+    Split(i64, i64),
+    Paid {
+        amount: i64,
+    },
+}
+
+#[test]
+fn test_enum_is_externally_tagged() {
+    assert_eq!(Status::Draft.into_value(), "Draft".to_owned().into_value());
+    assert_eq!(
+        Status::Sent("ada@example.com".to_owned()).into_value(),
+        dict! { "sent" => "ada@example.com".to_owned() }.into_value()
+    );
+    assert_eq!(
+        Status::Split(1, 2).into_value(),
+        dict! { "Split" => vec![1_i64, 2] }.into_value()
+    );
+    assert_eq!(
+        Status::Paid { amount: 5 }.into_value(),
+        dict! { "Paid" => dict! { "amount" => 5_i64 } }.into_value()
+    );
+}
+
+#[derive(IntoValue)]
+struct Money(f64);
+
+#[derive(IntoValue)]
+struct Point(f64, f64);
+
+#[derive(IntoValue, IntoDict)]
+struct Line {
+    price: Money,
+    origin: Point,
+}
+
+#[test]
+fn test_tuple_structs() {
+    assert_eq!(Money(1.5).into_value(), 1.5_f64.into_value());
+    assert_eq!(
+        Point(1.0, 2.0).into_value(),
+        vec![1.0_f64, 2.0].into_value()
+    );
+    assert_eq!(
+        Line {
+            price: Money(9.5),
+            origin: Point(0.0, 1.0),
+        }
+        .into_dict(),
+        dict! { "price" => 9.5_f64, "origin" => vec![0.0_f64, 1.0] }
+    );
+}
+
+mod ip {
+    use std::net::Ipv4Addr;
+
+    use typst::foundations::{IntoValue, Value};
+
+    pub fn to_value(addr: Ipv4Addr) -> Value {
+        addr.to_string().into_value()
+    }
+}
+
+#[derive(IntoValue, IntoDict)]
+struct Server {
+    #[typst(with = "ip::to_value")]
+    addr: std::net::Ipv4Addr,
+    port: u16,
+}
+
+#[test]
+fn test_with_converts_the_field() {
+    let server = Server {
+        addr: std::net::Ipv4Addr::LOCALHOST,
+        port: 8080,
+    };
+    assert_eq!(
+        server.into_dict(),
+        dict! { "addr" => "127.0.0.1".to_owned(), "port" => 8080_i64 }
+    );
+}
@@ -0,0 +1,71 @@
+//! Compile errors reported by the derive macros.
+//!
+//! Each case is a small crate under `tests/compile-fail/` that must fail to build. The
+//! exact rustc output changes between compiler versions, so the tests only check the
+//! parts users rely on: the message and the line it points at.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Get the workspace root directory
+fn workspace_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// Run `cargo check` on a compile-fail crate and return its diagnostics, one per line.
+fn check_fails(name: &str) -> String {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/compile-fail")
+        .join(name)
+        .join("Cargo.toml");
+
+    // A target directory of its own under `target/`: the outer `cargo test` holds the lock
+    // on the workspace one while tests run. It persists between runs, so typst is only
+    // built the first time. The fixture has no lockfile; `--offline` resolves it from the
+    // crates the workspace build already downloaded instead of reaching the network.
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--quiet",
+            "--offline",
+            "--message-format=short",
+            "--manifest-path",
+        ])
+        .arg(&manifest)
+        .env(
+            "CARGO_TARGET_DIR",
+            workspace_root().join("target/compile-fail"),
+        )
+        .output()
+        .expect("Failed to run cargo check");
+
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(
+        !output.status.success(),
+        "{name} compiled, but should have failed:\n{stderr}"
+    );
+    stderr
+}
+
+#[test]
+fn test_unsupported_field_points_at_the_field() {
+    let stderr = check_fails("unsupported-field");
+
+    // `created: Instant` is on line 6 of the fixture
+    let error = stderr
+        .lines()
+        .find(|line| line.contains("can't be converted to a Typst value"))
+        .unwrap_or_else(|| panic!("on_unimplemented message missing:\n{stderr}"));
+    assert!(error.contains("Instant"), "{error}");
+    assert!(
+        error.contains("src/lib.rs:6:"),
+        "error doesn't point at the field: {error}"
+    );
+    assert!(
+        !stderr.contains("src/lib.rs:5:"),
+        "`number: u32` was rejected:\n{stderr}"
+    );
+}